@@ -0,0 +1,219 @@
+//! Measures the GPU time of each SMAA pass on a headless device, for several resolutions, scenes,
+//! backends and presets, and of the CMAA2 passes for comparison.
+//!
+//! Run with `cargo bench`. Arguments filter the configurations by name, for instance
+//! `cargo bench -- 1920x1080/stripes` only measures that resolution and scene.
+
+use smaa::*;
+use std::borrow::Cow;
+use std::time::Duration;
+
+const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1920, 1080), (2560, 1440), (3840, 2160)];
+const SCENES: [&str; 2] = ["stripes", "rings"];
+/// Name, mode and backend of each measured configuration. CMAA2 always runs as compute
+/// dispatches, so its passes are reported in the columns of the SMAA passes they replace.
+const ALGORITHMS: [(&str, SmaaMode, SmaaBackend); 4] = [
+    ("Render", SmaaMode::Smaa1X, SmaaBackend::Render),
+    ("Compute", SmaaMode::Smaa1X, SmaaBackend::Compute),
+    ("ComputeFused", SmaaMode::Smaa1X, SmaaBackend::ComputeFused),
+    ("Cmaa2", SmaaMode::Cmaa2, SmaaBackend::Render),
+];
+const PRESETS: [SmaaPreset; 4] = [
+    SmaaPreset::Low,
+    SmaaPreset::Medium,
+    SmaaPreset::High,
+    SmaaPreset::Ultra,
+];
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Frames to render before measuring, so that shader compilation and allocations settle.
+const WARMUP_FRAMES: usize = 5;
+const MEASURED_FRAMES: usize = 20;
+
+fn main() {
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+
+    // Initialize wgpu without a surface
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter =
+        futures::executor::block_on(instance.request_adapter(&Default::default())).unwrap();
+    if !adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+        eprintln!(
+            "{} doesn't support timestamp queries, which are needed to time the passes",
+            adapter.get_info().name
+        );
+        return;
+    }
+    let (device, queue) = futures::executor::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            required_features: wgpu::Features::TIMESTAMP_QUERY,
+            required_limits: adapter.limits(),
+            ..Default::default()
+        },
+        None,
+    ))
+    .unwrap();
+    let compute = adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+    println!(
+        "{:?} on {}",
+        adapter.get_info().backend,
+        adapter.get_info().name
+    );
+
+    // Prepare scenes
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../src/test_scene.wgsl"))),
+    });
+    let scene_pipelines: Vec<_> = SCENES
+        .iter()
+        .map(|scene| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: None,
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: &format!("fs_{scene}"),
+                    targets: &[Some(FORMAT.into())],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        })
+        .collect();
+
+    println!(
+        "{:<40} {:>10} {:>10} {:>10} {:>10}",
+        "configuration", "edges", "weights", "blending", "total"
+    );
+    for (width, height) in RESOLUTIONS {
+        let output = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: if compute {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::STORAGE_BINDING
+            } else {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            },
+            view_formats: &[],
+        });
+        let output_view = output.create_view(&Default::default());
+
+        for (scene, scene_pipeline) in SCENES.iter().zip(&scene_pipelines) {
+            for (algorithm, mode, backend) in ALGORITHMS {
+                if (mode == SmaaMode::Cmaa2 || backend != SmaaBackend::Render) && !compute {
+                    continue;
+                }
+                for preset in PRESETS {
+                    let name = format!("{width}x{height}/{scene}/{algorithm}/{preset:?}");
+                    if !filters.iter().all(|filter| name.contains(filter.as_str())) {
+                        continue;
+                    }
+
+                    let mut smaa_target = SmaaTarget::with_config(
+                        &device,
+                        &queue,
+                        width,
+                        height,
+                        FORMAT,
+                        mode,
+                        SmaaConfig {
+                            backend,
+                            preset,
+                            profile: true,
+                            ..Default::default()
+                        },
+                    );
+
+                    let mut samples = Vec::new();
+                    for frame in 0..WARMUP_FRAMES + MEASURED_FRAMES + 1 {
+                        render_frame(
+                            &device,
+                            &queue,
+                            &mut smaa_target,
+                            &output_view,
+                            scene_pipeline,
+                        );
+                        if frame > WARMUP_FRAMES {
+                            samples.extend(smaa_target.pass_times());
+                        }
+                    }
+
+                    let median = |pass: fn(&SmaaPassTimes) -> Duration| {
+                        let mut times: Vec<Duration> = samples.iter().map(pass).collect();
+                        times.sort();
+                        times.get(times.len() / 2).copied().unwrap_or_default()
+                    };
+                    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+                    println!(
+                        "{:<40} {:>7.3} ms {:>7.3} ms {:>7.3} ms {:>7.3} ms",
+                        name,
+                        ms(median(|times| times.edge_detection)),
+                        ms(median(|times| times.blend_weights)),
+                        ms(median(|times| times.neighborhood_blending)),
+                        ms(median(SmaaPassTimes::total)),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn render_frame(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    smaa_target: &mut SmaaTarget,
+    output_view: &wgpu::TextureView,
+    scene_pipeline: &wgpu::RenderPipeline,
+) {
+    let smaa_frame = smaa_target.start_frame(device, queue, output_view);
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &smaa_frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(scene_pipeline);
+        rpass.draw(0..3, 0..1);
+    }
+    queue.submit(Some(encoder.finish()));
+    smaa_frame.resolve();
+
+    // Wait for the frame, so that its timestamps are read back when the next one is resolved and
+    // every frame is measured.
+    device.poll(wgpu::Maintain::Wait);
+}
@@ -0,0 +1,439 @@
+//! Interactive demo for comparing the settings on a scene with thin lines, curves and text.
+//!
+//! Run with `cargo run --example demo --features winit`, then press:
+//!
+//! - `M` to switch between the antialiasing modes,
+//! - `P` to switch between the quality presets,
+//! - `E` to switch between the edge detection modes,
+//! - `D` to show the difference made by antialiasing, the detected edges or the blend weights,
+//! - `C` to compare the scene without antialiasing on the left with the output on the right,
+//! - `Space` to pause the animation, and `Escape` to quit.
+//!
+//! The current settings are shown in the title of the window.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use smaa::winit_surface::SmaaSurface;
+use smaa::{SmaaConfig, SmaaDebugOutput, SmaaEdgeDetection, SmaaMode, SmaaPreset, SmaaTarget};
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::keyboard::{Key, NamedKey};
+use winit::window::Window;
+
+const PRESETS: [SmaaPreset; 4] = [
+    SmaaPreset::Low,
+    SmaaPreset::Medium,
+    SmaaPreset::High,
+    SmaaPreset::Ultra,
+];
+const EDGE_DETECTION: [SmaaEdgeDetection; 3] = [
+    SmaaEdgeDetection::Luma,
+    SmaaEdgeDetection::Alpha,
+    SmaaEdgeDetection::LumaAndAlpha,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum DebugView {
+    None,
+    Difference,
+    Edges,
+    BlendWeights,
+}
+
+struct Settings {
+    /// Modes that the adapter and surface support.
+    modes: Vec<SmaaMode>,
+    mode: usize,
+    preset: usize,
+    edge_detection: usize,
+    debug_view: DebugView,
+    compare: bool,
+    paused: bool,
+}
+
+impl Settings {
+    fn mode(&self) -> SmaaMode {
+        self.modes[self.mode]
+    }
+
+    /// Settings of the target. CMAA2 only detects edges in luma and has no debug output.
+    fn config(&self, alpha_mode: wgpu::CompositeAlphaMode) -> SmaaConfig {
+        let cmaa2 = self.mode() == SmaaMode::Cmaa2;
+        SmaaConfig {
+            preset: PRESETS[self.preset],
+            edge_detection: match cmaa2 {
+                true => SmaaEdgeDetection::Luma,
+                false => EDGE_DETECTION[self.edge_detection],
+            },
+            debug_output: match self.debug_view {
+                DebugView::Difference if !cmaa2 => {
+                    SmaaDebugOutput::Difference { amplification: 4.0 }
+                }
+                _ => SmaaDebugOutput::None,
+            },
+            composite_alpha_mode: alpha_mode,
+            ..Default::default()
+        }
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "SMAA demo: {:?}, {:?}, {:?} edges, debug view {:?}{}{}",
+            self.mode(),
+            PRESETS[self.preset],
+            EDGE_DETECTION[self.edge_detection],
+            self.debug_view,
+            if self.compare { ", compare" } else { "" },
+            if self.paused { ", paused" } else { "" },
+        )
+    }
+
+    /// Apply a key press, returning whether the target has to be created again.
+    fn handle_key(&mut self, key: &Key) -> bool {
+        match key.as_ref() {
+            Key::Character("m") => self.mode = (self.mode + 1) % self.modes.len(),
+            Key::Character("p") => self.preset = (self.preset + 1) % PRESETS.len(),
+            Key::Character("e") => {
+                self.edge_detection = (self.edge_detection + 1) % EDGE_DETECTION.len()
+            }
+            Key::Character("d") => {
+                self.debug_view = match self.debug_view {
+                    DebugView::None => DebugView::Difference,
+                    DebugView::Difference => DebugView::Edges,
+                    DebugView::Edges => DebugView::BlendWeights,
+                    DebugView::BlendWeights => DebugView::None,
+                }
+            }
+            Key::Character("c") => self.compare = !self.compare,
+            Key::Named(NamedKey::Space) => self.paused = !self.paused,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Uniforms of the scene, see `Scene` in demo.wgsl.
+fn scene_uniforms(size: (u32, u32), time: f32, divider: f32) -> Vec<u8> {
+    [size.0 as f32, size.1 as f32, time, divider]
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect()
+}
+
+fn main() {
+    // Initialize wgpu
+    let event_loop: EventLoop<()> = EventLoop::new().unwrap();
+    let window = Arc::new(Window::new(&event_loop).unwrap());
+    let window_size = window.inner_size();
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let surface = instance.create_surface(window.clone()).unwrap();
+    let adapter =
+        futures::executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        }))
+        .unwrap();
+    let (device, queue) =
+        futures::executor::block_on(adapter.request_device(&Default::default(), None)).unwrap();
+    let capabilities = surface.get_capabilities(&adapter);
+    let format = capabilities.formats[0];
+
+    // CMAA2 writes the output from compute shaders, which the surface might not support.
+    let cmaa2 = capabilities
+        .usages
+        .contains(wgpu::TextureUsages::STORAGE_BINDING)
+        && adapter
+            .get_texture_format_features(format)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::STORAGE_BINDING)
+        && adapter.get_downlevel_capabilities().flags.contains(
+            wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::INDIRECT_EXECUTION,
+        );
+    let mut modes = vec![SmaaMode::Disabled, SmaaMode::Smaa1X, SmaaMode::Ssaa2x];
+    if cmaa2 {
+        modes.push(SmaaMode::Cmaa2);
+    }
+    let mut settings = Settings {
+        modes,
+        mode: 1,
+        preset: 2,
+        edge_detection: 0,
+        debug_view: DebugView::None,
+        compare: false,
+        paused: false,
+    };
+
+    let config = wgpu::SurfaceConfiguration {
+        usage: match cmaa2 {
+            true => wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::STORAGE_BINDING,
+            false => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        },
+        format,
+        width: window_size.width.max(1),
+        height: window_size.height.max(1),
+        present_mode: wgpu::PresentMode::AutoVsync,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    };
+    let alpha_mode = config.alpha_mode;
+    let mut smaa_surface = SmaaSurface::new(
+        &device,
+        &queue,
+        surface,
+        config,
+        settings.mode(),
+        settings.config(alpha_mode),
+    );
+    window.set_title(&settings.title());
+
+    // Prepare scene
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("demo.wgsl"))),
+    });
+    let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    let scene_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[uniform_entry(0)],
+    });
+    let debug_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            uniform_entry(2),
+        ],
+    });
+    let create_pipeline = |layout: &wgpu::BindGroupLayout, entry_point| {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::all(),
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    };
+    let scene_pipeline = create_pipeline(&scene_layout, "fs_scene");
+    let debug_pipeline = create_pipeline(&debug_layout, "fs_debug");
+    let create_uniform_buffer = |size| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    };
+    // The scene is drawn once into the frame, and once more without antialiasing into the
+    // left half of the output when comparing.
+    let scene_buffers = [create_uniform_buffer(16), create_uniform_buffer(16)];
+    let scene_bind_groups = scene_buffers.each_ref().map(|buffer| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &scene_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        })
+    });
+    let debug_buffer = create_uniform_buffer(24);
+
+    let mut time = Duration::ZERO;
+    let mut last_frame = Instant::now();
+
+    // Main loop
+    let _ = event_loop.run(move |event, event_loop| match event {
+        Event::AboutToWait => window.request_redraw(),
+        Event::WindowEvent { event, .. } => {
+            smaa_surface.handle_event(&device, &window, &event);
+            match event {
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key,
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    if logical_key == Key::Named(NamedKey::Escape) {
+                        event_loop.exit();
+                    }
+                    if settings.handle_key(&logical_key) {
+                        let (width, height) =
+                            (smaa_surface.config().width, smaa_surface.config().height);
+                        *smaa_surface.target_mut() = SmaaTarget::with_config(
+                            &device,
+                            &queue,
+                            width,
+                            height,
+                            format,
+                            settings.mode(),
+                            settings.config(alpha_mode),
+                        );
+                        window.set_title(&settings.title());
+                    }
+                }
+                WindowEvent::RedrawRequested => {
+                    let now = Instant::now();
+                    if !settings.paused {
+                        time += now - last_frame;
+                    }
+                    last_frame = now;
+                    let output_frame = smaa_surface.current_texture(&device).unwrap();
+                    let output_view = output_frame.texture.create_view(&Default::default());
+                    let output_size = (smaa_surface.config().width, smaa_surface.config().height);
+                    let target = smaa_surface.target_mut();
+                    let render_size = target.render_size();
+                    queue.write_buffer(
+                        &scene_buffers[0],
+                        0,
+                        &scene_uniforms(render_size, time.as_secs_f32(), -1.0),
+                    );
+                    queue.write_buffer(
+                        &scene_buffers[1],
+                        0,
+                        &scene_uniforms(
+                            output_size,
+                            time.as_secs_f32(),
+                            output_size.0 as f32 / 2.0,
+                        ),
+                    );
+
+                    let smaa_frame = target.start_frame(&device, &queue, &output_view);
+                    let mut encoder = device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                    {
+                        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: None,
+                            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: &smaa_frame,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                    store: wgpu::StoreOp::Store,
+                                },
+                            })],
+                            depth_stencil_attachment: None,
+                            occlusion_query_set: None,
+                            timestamp_writes: None,
+                        });
+                        rpass.set_pipeline(&scene_pipeline);
+                        rpass.set_bind_group(0, &scene_bind_groups[0], &[]);
+                        rpass.draw(0..3, 0..1);
+                    }
+                    queue.submit(Some(encoder.finish()));
+                    smaa_frame.resolve();
+
+                    // Draw the debug view and the scene without antialiasing over the output.
+                    let debug_view = match settings.debug_view {
+                        DebugView::Edges => target.edges_view().map(|view| (view, 0u32)),
+                        DebugView::BlendWeights => {
+                            target.blend_weights_view().map(|view| (view, 1))
+                        }
+                        _ => None,
+                    };
+                    let debug_bind_group;
+                    let mut encoder = device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: None,
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &output_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                    });
+                    if let Some((view, kind)) = debug_view {
+                        let uniforms: Vec<u8> = [render_size.0, render_size.1]
+                            .into_iter()
+                            .chain([output_size.0, output_size.1])
+                            .flat_map(|size| (size as f32).to_ne_bytes())
+                            .chain(kind.to_ne_bytes())
+                            .chain(0u32.to_ne_bytes())
+                            .collect();
+                        queue.write_buffer(&debug_buffer, 0, &uniforms);
+                        debug_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: None,
+                            layout: &debug_layout,
+                            entries: &[
+                                wgpu::BindGroupEntry {
+                                    binding: 1,
+                                    resource: wgpu::BindingResource::TextureView(view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 2,
+                                    resource: debug_buffer.as_entire_binding(),
+                                },
+                            ],
+                        });
+                        rpass.set_pipeline(&debug_pipeline);
+                        rpass.set_bind_group(0, &debug_bind_group, &[]);
+                        rpass.draw(0..3, 0..1);
+                    }
+                    if settings.compare {
+                        rpass.set_scissor_rect(0, 0, output_size.0 / 2 + 1, output_size.1);
+                        rpass.set_pipeline(&scene_pipeline);
+                        rpass.set_bind_group(0, &scene_bind_groups[1], &[]);
+                        rpass.draw(0..3, 0..1);
+                    }
+                    drop(rpass);
+                    queue.submit(Some(encoder.finish()));
+                    output_frame.present();
+                }
+                WindowEvent::CloseRequested => event_loop.exit(),
+                _ => (),
+            }
+        }
+        _ => (),
+    });
+}
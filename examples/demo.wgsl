@@ -0,0 +1,131 @@
+// Test scene for the demo, drawn with hard edges so that they alias: a fan of thin lines,
+// concentric rings, a sine curve, a spinning square and some text.
+
+struct Scene {
+    size: vec2<f32>,
+    time: f32,
+    // Horizontal position of the line between the halves of the split-screen compare, or a
+    // negative value to not draw it.
+    divider: f32,
+}
+
+@group(0) @binding(0) var<uniform> scene: Scene;
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Distance from `p` to the segment from `a` to `b`.
+fn segment(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+fn rotate(p: vec2<f32>, angle: f32) -> vec2<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec2<f32>(c * p.x - s * p.y, s * p.x + c * p.y);
+}
+
+// Whether the point, in units of font pixels from the top left of the text, is covered by one of
+// the glyphs of "SMAA".
+fn text(q: vec2<f32>) -> bool {
+    // Rows of the 5x7 glyphs of S, M and A, with the leftmost column in the highest bit.
+    var glyphs = array<u32, 21>(
+        15u, 16u, 16u, 14u, 1u, 1u, 30u,
+        17u, 27u, 21u, 21u, 17u, 17u, 17u,
+        14u, 17u, 17u, 31u, 17u, 17u, 17u,
+    );
+    var letters = array<u32, 4>(0u, 1u, 2u, 2u);
+    if q.x < 0.0 || q.y < 0.0 || q.x >= 24.0 || q.y >= 7.0 {
+        return false;
+    }
+    let column = u32(q.x) % 6u;
+    let row = u32(q.y);
+    if column >= 5u {
+        return false;
+    }
+    let glyph = letters[u32(q.x) / 6u];
+    return ((glyphs[glyph * 7u + row] >> (4u - column)) & 1u) != 0u;
+}
+
+@fragment
+fn fs_scene(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    if scene.divider >= 0.0 && abs(position.x - scene.divider) < 1.0 {
+        return vec4<f32>(1.0, 0.8, 0.0, 1.0);
+    }
+    // Units of the height of the frame, with the origin at its center.
+    let p = (position.xy - scene.size * 0.5) / scene.size.y;
+    let thin = 0.0008;
+    var color = vec4<f32>(0.1, 0.1, 0.12, 0.0);
+
+    // Fan of thin lines at slowly changing angles.
+    let origin = vec2<f32>(-0.8, 0.4);
+    for (var i = 0; i < 24; i++) {
+        let angle = -0.05 - f32(i) * 0.06 + 0.02 * sin(scene.time * 0.3);
+        let end = origin + vec2<f32>(cos(angle), sin(angle)) * 0.8;
+        if segment(p, origin, end) < thin {
+            color = vec4<f32>(1.0);
+        }
+    }
+
+    // Concentric rings of decreasing width.
+    let ring = length(p - vec2<f32>(0.45, -0.1));
+    for (var i = 1; i < 8; i++) {
+        if abs(ring - f32(i) * 0.04) < 0.006 / f32(i) {
+            color = vec4<f32>(0.3, 0.8, 1.0, 1.0);
+        }
+    }
+
+    // Sine curve, with its distance corrected by the slope so that its width stays even.
+    let x = p.x * 12.0 + scene.time;
+    let wave = 0.06 * sin(x);
+    let slope = 0.72 * cos(x);
+    if abs(p.y - 0.3 - wave) / sqrt(1.0 + slope * slope) < 0.003 {
+        color = vec4<f32>(1.0, 0.4, 0.2, 1.0);
+    }
+
+    // Spinning square.
+    let square = abs(rotate(p - vec2<f32>(-0.1, -0.25), scene.time * 0.2));
+    if max(square.x, square.y) < 0.12 {
+        color = vec4<f32>(0.9, 0.9, 0.3, 1.0);
+    }
+
+    // Text at several sizes, tilted slightly so that its edges aren't aligned with the pixels.
+    var sizes = array<f32, 3>(0.004, 0.008, 0.016);
+    for (var i = 0; i < 3; i++) {
+        let corner = vec2<f32>(0.15, -0.42 + f32(i) * 0.1 + f32(i * i) * 0.02);
+        let q = rotate(p - corner, -0.05) / sizes[i];
+        if text(q) {
+            color = vec4<f32>(1.0);
+        }
+    }
+    return color;
+}
+
+struct Debug {
+    // Size of the frame in the debug texture, and of the output.
+    render_size: vec2<f32>,
+    output_size: vec2<f32>,
+    // 0 for edges, 1 for blend weights.
+    kind: u32,
+}
+
+@group(0) @binding(1) var debug_tex: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> debug: Debug;
+
+@fragment
+fn fs_debug(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coord = vec2<u32>(position.xy * debug.render_size / debug.output_size);
+    let value = textureLoad(debug_tex, coord, 0);
+    if debug.kind == 0u {
+        // Left edges in red, top edges in green.
+        return vec4<f32>(value.r, value.g, 0.0, 1.0);
+    }
+    // Weights of horizontal edges in red, of vertical edges in green.
+    return vec4<f32>(value.r + value.g, value.b + value.a, 0.0, 1.0);
+}
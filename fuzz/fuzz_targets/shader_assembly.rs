@@ -0,0 +1,8 @@
+//! Assembles the GLSL of a stage with settings picked from the input and translates it with naga,
+//! which panics if the source doesn't parse or the module isn't valid.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| smaa::fuzzing::assemble_shader(data));
@@ -0,0 +1,159 @@
+//! Antialias a PNG image with SMAA on a headless device and write the result as a PNG, for
+//! batch processing and for reproducing quality issues outside of an application.
+//!
+//! ```text
+//! cargo run --features cli --bin smaa-cli -- [--preset <preset>] [--edges <mode>] <input> <output>
+//! ```
+
+use std::process::ExitCode;
+
+use smaa::{OffscreenSmaa, SmaaBlendSpace, SmaaConfig, SmaaEdgeDetection, SmaaPreset};
+
+const USAGE: &str = "\
+usage: smaa-cli [options] <input.png> <output.png>
+
+options:
+    --preset <low|medium|high|ultra>       quality preset [default: high]
+    --edges <luma|alpha|luma-and-alpha>    what edges are detected in [default: luma]
+    -h, --help                             print this message";
+
+struct Args {
+    input: String,
+    output: String,
+    config: SmaaConfig,
+}
+
+fn parse_preset(value: &str) -> Result<SmaaPreset, String> {
+    match value {
+        "low" => Ok(SmaaPreset::Low),
+        "medium" => Ok(SmaaPreset::Medium),
+        "high" => Ok(SmaaPreset::High),
+        "ultra" => Ok(SmaaPreset::Ultra),
+        _ => Err(format!("unknown preset `{value}`")),
+    }
+}
+
+fn parse_edges(value: &str) -> Result<SmaaEdgeDetection, String> {
+    match value {
+        "luma" => Ok(SmaaEdgeDetection::Luma),
+        "alpha" => Ok(SmaaEdgeDetection::Alpha),
+        "luma-and-alpha" => Ok(SmaaEdgeDetection::LumaAndAlpha),
+        "depth" => Err("depth edge detection needs a depth buffer, which PNG input lacks".into()),
+        _ => Err(format!("unknown edge detection mode `{value}`")),
+    }
+}
+
+/// Parse the arguments, or return `None` if help was requested.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut config = SmaaConfig {
+        blend_space: SmaaBlendSpace::Linear,
+        ..Default::default()
+    };
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{arg}`"))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--preset" => config.preset = parse_preset(&value()?)?,
+            "--edges" => config.edge_detection = parse_edges(&value()?)?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ => paths.push(arg),
+        }
+    }
+    match <[String; 2]>::try_from(paths) {
+        Ok([input, output]) => Ok(Some(Args {
+            input,
+            output,
+            config,
+        })),
+        Err(_) => Err("expected an input and an output path".into()),
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let image = image::open(&args.input)
+        .map_err(|e| format!("failed to read {}: {e}", args.input))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    let mut smaa = OffscreenSmaa::with_config(args.config).ok_or("no adapter available")?;
+    let pixels = smaa.antialias(width, height, &image);
+    image::RgbaImage::from_raw(width, height, pixels)
+        .expect("antialiased image has the size of the input")
+        .save_with_format(&args.output, image::ImageFormat::Png)
+        .map_err(|e| format!("failed to write {}: {e}", args.output))
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => match run(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Ok(None) => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options_and_paths() {
+        let args = parse(&["--preset", "ultra", "in.png", "--edges", "alpha", "out.png"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (args.input.as_str(), args.output.as_str()),
+            ("in.png", "out.png")
+        );
+        assert_eq!(args.config.preset, SmaaPreset::Ultra);
+        assert_eq!(args.config.edge_detection, SmaaEdgeDetection::Alpha);
+        assert_eq!(args.config.blend_space, SmaaBlendSpace::Linear);
+    }
+
+    #[test]
+    fn help() {
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert!(parse(&["in.png", "-h"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let error = |args: &[&str]| parse(args).err().unwrap();
+        assert_eq!(
+            error(&["in.png", "out.png", "--preset"]),
+            "missing value for `--preset`"
+        );
+        assert_eq!(
+            error(&["--fast", "in.png", "out.png"]),
+            "unknown option `--fast`"
+        );
+        assert_eq!(
+            error(&["--preset", "extreme", "in.png", "out.png"]),
+            "unknown preset `extreme`"
+        );
+        assert!(error(&["--edges", "depth", "in.png", "out.png"]).contains("depth buffer"));
+        assert_eq!(error(&["in.png"]), "expected an input and an output path");
+        assert_eq!(
+            error(&["in.png", "out.png", "extra.png"]),
+            "expected an input and an output path"
+        );
+    }
+}
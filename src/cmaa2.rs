@@ -0,0 +1,425 @@
+//! Implementation of Conservative Morphological Anti-Aliasing 2.0 as compute dispatches, used by
+//! [`SmaaMode::Cmaa2`].
+//!
+//! [`SmaaMode::Cmaa2`]: crate::SmaaMode::Cmaa2
+
+use std::collections::HashMap;
+
+use crate::shader::WORKGROUP_SIZE;
+use crate::{
+    create_args_buffer, is_float_format, storage_format_name, timing, SmaaConfig, SmaaPreset,
+};
+
+/// Size in bytes of the header of the candidates buffer, which holds the indirect dispatch
+/// arguments of the candidate pass followed by the number of candidates.
+const CANDIDATES_HEADER_SIZE: u64 = 16;
+
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+fn buffer_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+struct BindGroupLayouts {
+    detect_edges: wgpu::BindGroupLayout,
+    process_candidates: wgpu::BindGroupLayout,
+    apply_blends: wgpu::BindGroupLayout,
+}
+impl BindGroupLayouts {
+    fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        let create = |label, entries: &[wgpu::BindGroupLayoutEntry]| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries,
+            })
+        };
+        Self {
+            detect_edges: create(
+                "smaa.cmaa2.bind_group_layout.detect_edges",
+                &[
+                    texture_entry(0),
+                    buffer_entry(1, false),
+                    buffer_entry(2, false),
+                ],
+            ),
+            process_candidates: create(
+                "smaa.cmaa2.bind_group_layout.process_candidates",
+                &[
+                    texture_entry(0),
+                    buffer_entry(3, true),
+                    buffer_entry(4, true),
+                    buffer_entry(5, false),
+                    buffer_entry(6, false),
+                ],
+            ),
+            apply_blends: create(
+                "smaa.cmaa2.bind_group_layout.apply_blends",
+                &[
+                    texture_entry(0),
+                    buffer_entry(5, false),
+                    buffer_entry(6, false),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: output_format,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            ),
+        }
+    }
+}
+
+struct Pipelines {
+    detect_edges: wgpu::ComputePipeline,
+    process_candidates: wgpu::ComputePipeline,
+    apply_blends: wgpu::ComputePipeline,
+}
+impl Pipelines {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        layouts: &BindGroupLayouts,
+        config: &SmaaConfig,
+    ) -> Self {
+        let source = include_str!("cmaa2.wgsl")
+            .replace("OUTPUT_FORMAT", storage_format_name(format).unwrap());
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.shader.cmaa2.comp"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        // Edge thresholds and the longest lines that are traced for each preset.
+        let (edge_threshold, max_line_length) = match config.preset {
+            SmaaPreset::Low => (0.15, 16.0),
+            SmaaPreset::Medium => (0.10, 32.0),
+            SmaaPreset::High => (0.07, 64.0),
+            SmaaPreset::Ultra => (0.05, 128.0),
+        };
+        let color_format = config.color_format.unwrap_or(format);
+        let read_linear = color_format.is_srgb() || is_float_format(color_format);
+        let constants = HashMap::from([
+            ("EDGE_THRESHOLD".to_owned(), edge_threshold),
+            ("MAX_LINE_LENGTH".to_owned(), max_line_length),
+            ("READ_LINEAR".to_owned(), read_linear as u32 as f64),
+        ]);
+
+        let create_pipeline = |label, entry_point, bind_group_layout| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &constants,
+                    ..Default::default()
+                },
+                cache: None,
+            })
+        };
+        Self {
+            detect_edges: create_pipeline(
+                "smaa.cmaa2.pipeline.detect_edges",
+                "detect_edges",
+                &layouts.detect_edges,
+            ),
+            process_candidates: create_pipeline(
+                "smaa.cmaa2.pipeline.process_candidates",
+                "process_candidates",
+                &layouts.process_candidates,
+            ),
+            apply_blends: create_pipeline(
+                "smaa.cmaa2.pipeline.apply_blends",
+                "apply_blends",
+                &layouts.apply_blends,
+            ),
+        }
+    }
+}
+
+/// The texture that the scene is rendered into and the buffers the passes communicate through,
+/// which all depend on the size of the target.
+struct Targets {
+    width: u32,
+    height: u32,
+    color_target: wgpu::TextureView,
+    edges: wgpu::Buffer,
+    candidates: wgpu::Buffer,
+    heads: wgpu::Buffer,
+    blends: wgpu::Buffer,
+}
+impl Targets {
+    fn new(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        let pixels = width as u64 * height as u64;
+        let create_buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | usage,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            width,
+            height,
+            color_target: device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("smaa.cmaa2.texture.color_target"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default()),
+            edges: create_buffer(
+                "smaa.cmaa2.buffer.edges",
+                4 * pixels,
+                wgpu::BufferUsages::empty(),
+            ),
+            candidates: create_buffer(
+                "smaa.cmaa2.buffer.candidates",
+                CANDIDATES_HEADER_SIZE + 4 * pixels,
+                wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+            ),
+            // The heads start out cleared and the last pass clears them again as it reads them.
+            heads: create_buffer(
+                "smaa.cmaa2.buffer.heads",
+                4 * pixels,
+                wgpu::BufferUsages::empty(),
+            ),
+            // Room for a blend for every other pixel, which is plenty unless most of the image is
+            // made of edges. A counter precedes the blends, which take 12 bytes each.
+            blends: create_buffer(
+                "smaa.cmaa2.buffer.blends",
+                4 + 12 * (pixels / 2).max(1),
+                wgpu::BufferUsages::COPY_DST,
+            ),
+        }
+    }
+}
+
+pub(crate) struct Cmaa2TargetInner {
+    layouts: BindGroupLayouts,
+    pipelines: Pipelines,
+    targets: Targets,
+    // Copied over the header of the candidates buffer to reset it before each image.
+    initial_args: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+}
+impl Cmaa2TargetInner {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        config: &SmaaConfig,
+    ) -> Self {
+        assert!(
+            storage_format_name(format).is_some(),
+            "SmaaMode::Cmaa2 doesn't support writing to {:?} outputs",
+            format
+        );
+        assert!(
+            config.background_depth.is_none(),
+            "SmaaMode::Cmaa2 doesn't support SmaaConfig::background_depth"
+        );
+
+        let color_format = config.color_format.unwrap_or(format);
+        let layouts = BindGroupLayouts::new(device, format);
+        Self {
+            pipelines: Pipelines::new(device, format, &layouts, config),
+            layouts,
+            targets: Targets::new(device, width, height, color_format),
+            initial_args: create_args_buffer(device, &[0, 1, 1, 0]),
+            format: color_format,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.targets = Targets::new(device, width, height, self.format);
+    }
+
+    pub fn rebuild_pipelines(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        config: &SmaaConfig,
+    ) {
+        self.pipelines = Pipelines::new(device, format, &self.layouts, config);
+    }
+
+    pub fn color_target(&self) -> &wgpu::TextureView {
+        &self.targets.color_target
+    }
+
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        timestamps: Option<&wgpu::QuerySet>,
+    ) {
+        self.encode_passes(
+            device,
+            encoder,
+            &self.targets.color_target,
+            output_view,
+            timestamps,
+        );
+    }
+
+    /// Antialias each `(input, output)` pair in turn, reusing the buffers.
+    pub fn encode_batch(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &[(&wgpu::TextureView, &wgpu::TextureView)],
+    ) {
+        for &(input, output) in images {
+            self.encode_passes(device, encoder, input, output, None);
+        }
+    }
+
+    fn encode_passes(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        output_view: &wgpu::TextureView,
+        timestamps: Option<&wgpu::QuerySet>,
+    ) {
+        let targets = &self.targets;
+        let create_bind_group = |label, layout, resources: &[(u32, wgpu::BindingResource)]| {
+            let mut entries = vec![wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(color_view),
+            }];
+            entries.extend(
+                resources
+                    .iter()
+                    .map(|(binding, resource)| wgpu::BindGroupEntry {
+                        binding: *binding,
+                        resource: resource.clone(),
+                    }),
+            );
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &entries,
+            })
+        };
+        let detect_edges_bind_group = create_bind_group(
+            "smaa.cmaa2.bind_group.detect_edges",
+            &self.layouts.detect_edges,
+            &[
+                (1, targets.edges.as_entire_binding()),
+                (2, targets.candidates.as_entire_binding()),
+            ],
+        );
+        let process_candidates_bind_group = create_bind_group(
+            "smaa.cmaa2.bind_group.process_candidates",
+            &self.layouts.process_candidates,
+            &[
+                (3, targets.edges.as_entire_binding()),
+                (4, targets.candidates.as_entire_binding()),
+                (5, targets.heads.as_entire_binding()),
+                (6, targets.blends.as_entire_binding()),
+            ],
+        );
+        let apply_blends_bind_group = create_bind_group(
+            "smaa.cmaa2.bind_group.apply_blends",
+            &self.layouts.apply_blends,
+            &[
+                (5, targets.heads.as_entire_binding()),
+                (6, targets.blends.as_entire_binding()),
+                (7, wgpu::BindingResource::TextureView(output_view)),
+            ],
+        );
+
+        encoder.copy_buffer_to_buffer(
+            &self.initial_args,
+            0,
+            &targets.candidates,
+            0,
+            CANDIDATES_HEADER_SIZE,
+        );
+        encoder.clear_buffer(&targets.blends, 0, Some(4));
+
+        let workgroups_x = targets.width.div_ceil(WORKGROUP_SIZE);
+        let workgroups_y = targets.height.div_ceil(WORKGROUP_SIZE);
+        // The passes are timed like the SMAA passes they correspond to.
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.cmaa2.compute_pass.detect_edges"),
+                timestamp_writes: timestamps.map(|query_set| wgpu::ComputePassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: Some(timing::EDGE_DETECTION_BEGIN),
+                    end_of_pass_write_index: Some(timing::EDGE_DETECTION_END),
+                }),
+            });
+            cpass.set_pipeline(&self.pipelines.detect_edges);
+            cpass.set_bind_group(0, &detect_edges_bind_group, &[]);
+            cpass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+        }
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.cmaa2.compute_pass.process_candidates"),
+                timestamp_writes: timestamps.map(|query_set| wgpu::ComputePassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: None,
+                    end_of_pass_write_index: Some(timing::BLEND_WEIGHTS_END),
+                }),
+            });
+            cpass.set_pipeline(&self.pipelines.process_candidates);
+            cpass.set_bind_group(0, &process_candidates_bind_group, &[]);
+            cpass.dispatch_workgroups_indirect(&targets.candidates, 0);
+        }
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("smaa.cmaa2.compute_pass.apply_blends"),
+            timestamp_writes: timestamps.map(|query_set| wgpu::ComputePassTimestampWrites {
+                query_set,
+                beginning_of_pass_write_index: None,
+                end_of_pass_write_index: Some(timing::NEIGHBORHOOD_BLENDING_END),
+            }),
+        });
+        cpass.set_pipeline(&self.pipelines.apply_blends);
+        cpass.set_bind_group(0, &apply_blends_bind_group, &[]);
+        cpass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+    }
+}
@@ -0,0 +1,345 @@
+// Conservative Morphological Anti-Aliasing 2.0, after Intel's reference implementation.
+//
+// The first pass finds the edges between pixels and appends every pixel that has any to a list of
+// candidates. The second pass runs once per candidate, blurs simple shapes like corners and
+// isolated pixels, and traces the lines that start at the candidate to blend the pixels along
+// them. Since a pixel can be touched by several shapes, blended colors are pushed onto a list per
+// pixel, which the last pass averages while copying the image to the output.
+//
+// OUTPUT_FORMAT is replaced with the format of the output when the pipelines are created.
+
+override EDGE_THRESHOLD: f32 = 0.07;
+override MAX_LINE_LENGTH: i32 = 64;
+// Whether colors are read as linear values, which are gamma encoded before detecting edges.
+override READ_LINEAR: bool = false;
+
+// Edges that are much weaker than a parallel edge right next to them are dropped, like SMAA's
+// local contrast adaptation. This avoids blurring the soft side of strong gradients.
+const LOCAL_CONTRAST_ADAPTATION: f32 = 2.0;
+// How much a pixel with two or more edges is blurred with each neighbor across them.
+const SIMPLE_SHAPE_BLURRINESS: f32 = 0.1;
+// Blends that change a pixel by less than this aren't written at all.
+const MIN_BLEND: f32 = 1.0 / 256.0;
+
+const EDGE_LEFT: u32 = 1u;
+const EDGE_TOP: u32 = 2u;
+const EDGE_RIGHT: u32 = 4u;
+const EDGE_BOTTOM: u32 = 8u;
+
+struct Candidates {
+    // Indirect dispatch arguments of the second pass.
+    dispatch_x: atomic<u32>,
+    dispatch_y: u32,
+    dispatch_z: u32,
+    count: atomic<u32>,
+    // Pixel coordinates packed as `y << 16 | x`.
+    pixels: array<u32>,
+}
+
+struct CandidatesIn {
+    dispatch_x: u32,
+    dispatch_y: u32,
+    dispatch_z: u32,
+    count: u32,
+    pixels: array<u32>,
+}
+
+struct Blend {
+    // Color packed as four halves.
+    rg: u32,
+    ba: u32,
+    // One more than the index of the next blend of the same pixel, or 0 for the last one.
+    next: u32,
+}
+
+struct Blends {
+    count: atomic<u32>,
+    entries: array<Blend>,
+}
+
+@group(0) @binding(0) var color_tex: texture_2d<f32>;
+// Edges of each pixel, as a combination of the EDGE_ bits.
+@group(0) @binding(1) var<storage, read_write> edges: array<u32>;
+@group(0) @binding(2) var<storage, read_write> candidates: Candidates;
+@group(0) @binding(3) var<storage, read> edges_in: array<u32>;
+@group(0) @binding(4) var<storage, read> candidates_in: CandidatesIn;
+// One more than the index of the first blend of each pixel, or 0 if it has none.
+@group(0) @binding(5) var<storage, read_write> heads: array<atomic<u32>>;
+@group(0) @binding(6) var<storage, read_write> blends: Blends;
+@group(0) @binding(7) var output_tex: texture_storage_2d<OUTPUT_FORMAT, write>;
+
+fn image_size() -> vec2<i32> {
+    return vec2<i32>(textureDimensions(color_tex));
+}
+
+fn load_color(pixel: vec2<i32>) -> vec4<f32> {
+    return textureLoad(color_tex, clamp(pixel, vec2<i32>(0), image_size() - 1), 0);
+}
+
+fn srgb_encode(c: vec3<f32>) -> vec3<f32> {
+    let x = clamp(c, vec3<f32>(0.0), vec3<f32>(1.0));
+    return select(1.055 * pow(x, vec3<f32>(1.0 / 2.4)) - 0.055, 12.92 * x, x <= vec3<f32>(0.0031308));
+}
+
+fn luma(color: vec4<f32>) -> f32 {
+    var rgb = color.rgb;
+    if READ_LINEAR {
+        rgb = srgb_encode(rgb);
+    }
+    return dot(rgb, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+// Edge detection
+
+const TILE: i32 = 8;
+// The edges between two pixels depend on the contrast of the pixels two steps away.
+const HALO: i32 = 2;
+const TILE_WITH_HALO: i32 = 12;
+
+var<workgroup> lumas: array<f32, 144>;
+
+fn tile_luma(p: vec2<i32>) -> f32 {
+    return lumas[(p.y + HALO) * TILE_WITH_HALO + p.x + HALO];
+}
+
+// Whether there is an edge between the pixels at `p` and `p + step` in the tile, where `step` is
+// one pixel to the right or down.
+fn tile_edge(p: vec2<i32>, step: vec2<i32>) -> bool {
+    let delta = abs(tile_luma(p) - tile_luma(p + step));
+    let before = abs(tile_luma(p - step) - tile_luma(p));
+    let after = abs(tile_luma(p + step) - tile_luma(p + 2 * step));
+    return delta > EDGE_THRESHOLD && delta * LOCAL_CONTRAST_ADAPTATION >= max(before, after);
+}
+
+@compute @workgroup_size(8, 8)
+fn detect_edges(
+    @builtin(workgroup_id) workgroup: vec3<u32>,
+    @builtin(local_invocation_id) local: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    let origin = vec2<i32>(workgroup.xy) * TILE - HALO;
+    for (var i = i32(index); i < TILE_WITH_HALO * TILE_WITH_HALO; i += TILE * TILE) {
+        let p = vec2<i32>(i % TILE_WITH_HALO, i / TILE_WITH_HALO);
+        lumas[i] = luma(load_color(origin + p));
+    }
+    workgroupBarrier();
+
+    let p = vec2<i32>(local.xy);
+    let pixel = vec2<i32>(workgroup.xy) * TILE + p;
+    let size = image_size();
+    if any(pixel >= size) {
+        return;
+    }
+    var e = 0u;
+    if pixel.x > 0 && tile_edge(p - vec2<i32>(1, 0), vec2<i32>(1, 0)) {
+        e |= EDGE_LEFT;
+    }
+    if pixel.y > 0 && tile_edge(p - vec2<i32>(0, 1), vec2<i32>(0, 1)) {
+        e |= EDGE_TOP;
+    }
+    if pixel.x < size.x - 1 && tile_edge(p, vec2<i32>(1, 0)) {
+        e |= EDGE_RIGHT;
+    }
+    if pixel.y < size.y - 1 && tile_edge(p, vec2<i32>(0, 1)) {
+        e |= EDGE_BOTTOM;
+    }
+    edges[pixel.y * size.x + pixel.x] = e;
+
+    if e != 0u {
+        let i = atomicAdd(&candidates.count, 1u);
+        candidates.pixels[i] = (u32(pixel.y) << 16u) | u32(pixel.x);
+        // Every 64th candidate starts another workgroup of the next pass, up to the largest
+        // dispatch that is always allowed. Candidates beyond that are left untouched.
+        if i % 64u == 0u && i < 65535u * 64u {
+            atomicAdd(&candidates.dispatch_x, 1u);
+        }
+    }
+}
+
+// Shape processing
+
+fn edges_at(pixel: vec2<i32>) -> u32 {
+    let size = image_size();
+    if any(pixel < vec2<i32>(0)) || any(pixel >= size) {
+        return 0u;
+    }
+    return edges_in[pixel.y * size.x + pixel.x];
+}
+
+fn push_blend(pixel: vec2<i32>, color: vec4<f32>) {
+    let i = atomicAdd(&blends.count, 1u);
+    // Blends that don't fit are dropped, leaving the pixel with the blends that did.
+    if i >= arrayLength(&blends.entries) {
+        return;
+    }
+    blends.entries[i].rg = pack2x16float(color.rg);
+    blends.entries[i].ba = pack2x16float(color.ba);
+    blends.entries[i].next = atomicExchange(&heads[pixel.y * image_size().x + pixel.x], i + 1u);
+}
+
+// Mix `color` towards `other` by `amount` and push the result if it changes anything.
+fn blend_towards(pixel: vec2<i32>, color: vec4<f32>, other: vec4<f32>, amount: f32) {
+    if amount >= MIN_BLEND {
+        push_blend(pixel, mix(color, other, amount));
+    }
+}
+
+// Area above and below zero of a line going from `h0` to `h1` over the width of a pixel.
+fn line_areas(h0: f32, h1: f32) -> vec2<f32> {
+    if h0 >= 0.0 && h1 >= 0.0 {
+        return vec2<f32>(0.5 * (h0 + h1), 0.0);
+    }
+    if h0 <= 0.0 && h1 <= 0.0 {
+        return vec2<f32>(0.0, -0.5 * (h0 + h1));
+    }
+    let crossing = h0 / (h0 - h1);
+    let first = 0.5 * abs(h0) * crossing;
+    let second = 0.5 * abs(h1) * (1.0 - crossing);
+    return select(vec2<f32>(second, first), vec2<f32>(first, second), h0 > 0.0);
+}
+
+// Height of the silhouette that the line is reconstructed as, at distance `t` along a line of
+// `length` pixels whose ends step to the heights `start` and `end`.
+fn line_height(t: f32, length: f32, start: f32, end: f32) -> f32 {
+    // Steps to the same side at both ends form a U, with the line touching the edge halfway.
+    if start * end > 0.0 {
+        let half = 0.5 * length;
+        return select(end * (t - half) / half, start * (half - t) / half, t < half);
+    }
+    // Otherwise the line goes straight across, forming a Z with steps to opposite sides or an L
+    // when only one end steps.
+    return mix(start, end, t / length);
+}
+
+// Height that a line steps to at one of its ends, given whether the edges crossing the end lie on
+// the positive or the negative side. Ends where both or neither do are ambiguous, so the line
+// keeps to the edge there.
+fn end_height(positive: bool, negative: bool) -> f32 {
+    if positive == negative {
+        return 0.0;
+    }
+    return select(-0.5, 0.5, positive);
+}
+
+// Trace the line of edges between the pixels at `pixel` and `pixel + across` that starts at
+// `pixel`, heading along `along`. `start_bit` and `end_bit` are the edges of the pixels on either
+// side that cross the line at its start and end.
+fn process_line(
+    pixel: vec2<i32>,
+    along: vec2<i32>,
+    across: vec2<i32>,
+    line_bit: u32,
+    start_bit: u32,
+    end_bit: u32,
+) {
+    var length = 1;
+    while length < MAX_LINE_LENGTH && (edges_at(pixel + length * along) & line_bit) != 0u {
+        length += 1;
+    }
+    let last = pixel + (length - 1) * along;
+    let start = end_height(
+        (edges_at(pixel) & start_bit) != 0u,
+        (edges_at(pixel + across) & start_bit) != 0u,
+    );
+    // A line cut off by the maximum length has no known end.
+    var end = 0.0;
+    if length < MAX_LINE_LENGTH {
+        end = end_height(
+            (edges_at(last) & end_bit) != 0u,
+            (edges_at(last + across) & end_bit) != 0u,
+        );
+    }
+    if start == 0.0 && end == 0.0 {
+        return;
+    }
+
+    let len = f32(length);
+    for (var i = 0; i < length; i++) {
+        let t = f32(i);
+        // Split each pixel in half so that the middle of a U is integrated exactly.
+        let h0 = line_height(t, len, start, end);
+        let h1 = line_height(t + 0.5, len, start, end);
+        let h2 = line_height(t + 1.0, len, start, end);
+        let areas = line_areas(h0, h1) * 0.5 + line_areas(h1, h2) * 0.5;
+        let near = pixel + i * along;
+        let far = near + across;
+        let near_color = load_color(near);
+        let far_color = load_color(far);
+        // Where the silhouette bulges to the positive side, the near pixel is partly covered by
+        // the far one, and the other way around.
+        blend_towards(near, near_color, far_color, areas.x);
+        blend_towards(far, far_color, near_color, areas.y);
+    }
+}
+
+@compute @workgroup_size(64)
+fn process_candidates(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= candidates_in.count {
+        return;
+    }
+    let packed = candidates_in.pixels[id.x];
+    let pixel = vec2<i32>(i32(packed & 0xffffu), i32(packed >> 16u));
+    let e = edges_at(pixel);
+
+    // Corners, pixels sticking out of a line and isolated pixels are blurred with the pixels
+    // across their edges. Two opposite edges are part of a thin line, which is left alone.
+    let count = countOneBits(e);
+    if count >= 2u && e != (EDGE_LEFT | EDGE_RIGHT) && e != (EDGE_TOP | EDGE_BOTTOM) {
+        let color = load_color(pixel);
+        var sum = color;
+        var weight = 1.0;
+        let amount = SIMPLE_SHAPE_BLURRINESS * f32(count);
+        var neighbors = array<vec2<i32>, 4>(
+            vec2<i32>(-1, 0),
+            vec2<i32>(0, -1),
+            vec2<i32>(1, 0),
+            vec2<i32>(0, 1),
+        );
+        for (var i = 0u; i < 4u; i++) {
+            if (e & (1u << i)) != 0u {
+                sum += load_color(pixel + neighbors[i]) * amount;
+                weight += amount;
+            }
+        }
+        push_blend(pixel, sum / weight);
+    }
+
+    // Each line is traced from its first pixel, on the side above or left of it. Positive heights
+    // are towards the pixels on that side.
+    if (e & EDGE_BOTTOM) != 0u && (edges_at(pixel - vec2<i32>(1, 0)) & EDGE_BOTTOM) == 0u {
+        process_line(pixel, vec2<i32>(1, 0), vec2<i32>(0, 1), EDGE_BOTTOM, EDGE_LEFT, EDGE_RIGHT);
+    }
+    if (e & EDGE_RIGHT) != 0u && (edges_at(pixel - vec2<i32>(0, 1)) & EDGE_RIGHT) == 0u {
+        process_line(pixel, vec2<i32>(0, 1), vec2<i32>(1, 0), EDGE_RIGHT, EDGE_TOP, EDGE_BOTTOM);
+    }
+}
+
+// Output
+
+// Blends of a single pixel that are averaged, any further ones are ignored.
+const MAX_BLENDS: u32 = 16u;
+
+@compute @workgroup_size(8, 8)
+fn apply_blends(@builtin(global_invocation_id) id: vec3<u32>) {
+    let pixel = vec2<i32>(id.xy);
+    let size = image_size();
+    if any(pixel >= size) {
+        return;
+    }
+    // Taking the head also clears it for the next frame.
+    var next = atomicExchange(&heads[pixel.y * size.x + pixel.x], 0u);
+    var color = vec4<f32>(0.0);
+    var count = 0u;
+    while next != 0u && count < MAX_BLENDS {
+        let blend = blends.entries[next - 1u];
+        color += vec4<f32>(unpack2x16float(blend.rg), unpack2x16float(blend.ba));
+        count += 1u;
+        next = blend.next;
+    }
+    if count == 0u {
+        color = textureLoad(color_tex, pixel, 0);
+    } else {
+        color /= f32(count);
+    }
+    textureStore(output_tex, pixel, color);
+}
@@ -0,0 +1,781 @@
+//! Implementation of the SMAA passes as compute dispatches, used by [`SmaaBackend::Compute`] and
+//! [`SmaaBackend::ComputeFused`].
+//!
+//! [`SmaaBackend::Compute`]: crate::SmaaBackend::Compute
+//! [`SmaaBackend::ComputeFused`]: crate::SmaaBackend::ComputeFused
+
+use crate::shader::{self, ShaderStage, WORKGROUP_SIZE};
+use crate::timing;
+use crate::{
+    create_rt_uniforms, depth_texture_entry, fits_with_slack, shader_source, Resources,
+    SmaaBackend, SmaaConfig, SmaaPrecision, SmaaResources, MISSING_DEPTH_VIEW,
+};
+use std::sync::Arc;
+
+// With the fused variant there is no separate blend weight pass or blend target, and the
+// neighborhood blending pass reads the edges to calculate the weights itself.
+struct BindGroupLayouts {
+    color_filterable: bool,
+    depth_early_out: bool,
+    edge_detect_bind_group_layout: wgpu::BindGroupLayout,
+    blend_weight_bind_group_layout: Option<wgpu::BindGroupLayout>,
+    neighborhood_blending_bind_group_layout: wgpu::BindGroupLayout,
+}
+struct Pipelines {
+    edge_detect: wgpu::ComputePipeline,
+    blend_weight: Option<wgpu::ComputePipeline>,
+    neighborhood_blending: wgpu::ComputePipeline,
+}
+struct Targets {
+    width: u32,
+    height: u32,
+    rt_uniforms: wgpu::Buffer,
+    // Two when overlapping frames, in which case the scene is rendered into them in turn.
+    color_targets: Vec<wgpu::TextureView>,
+    current: usize,
+}
+// Like with the render passes, these can be larger than the color target, in which case the edges
+// are repeated beyond it and the blend weights calculated for the entire textures.
+struct Intermediates {
+    width: u32,
+    height: u32,
+    rt_uniforms: wgpu::Buffer,
+    edges_target: wgpu::TextureView,
+    blend_target: Option<wgpu::TextureView>,
+}
+struct BindGroups {
+    // One for each color target, or none if they are created for each frame to read the depth.
+    edge_detect_bind_groups: Vec<wgpu::BindGroup>,
+    blend_weight_bind_group: Option<wgpu::BindGroup>,
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+fn sampler_entry(binding: u32, filtering: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Sampler(if filtering {
+            wgpu::SamplerBindingType::Filtering
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        }),
+        count: None,
+    }
+}
+fn texture_entry(binding: u32, filterable: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+fn storage_entry(binding: u32, format: wgpu::TextureFormat) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access: wgpu::StorageTextureAccess::WriteOnly,
+            format,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
+
+impl BindGroupLayouts {
+    fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        storage_format: wgpu::TextureFormat,
+        output_format: wgpu::TextureFormat,
+        fused: bool,
+        depth_early_out: bool,
+    ) -> Self {
+        let color_filterable = matches!(
+            color_format.sample_type(None, Some(device.features())),
+            Some(wgpu::TextureSampleType::Float { filterable: true })
+        );
+
+        let mut edge_detect_entries = vec![
+            sampler_entry(0, color_filterable),
+            uniform_entry(1),
+            texture_entry(2, color_filterable),
+            storage_entry(3, storage_format),
+        ];
+        if depth_early_out {
+            edge_detect_entries.push(depth_texture_entry(4, wgpu::ShaderStages::COMPUTE));
+        }
+
+        Self {
+            color_filterable,
+            depth_early_out,
+            edge_detect_bind_group_layout: device.create_bind_group_layout(
+                &wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.compute.bind_group_layout.edge_detect"),
+                    entries: &edge_detect_entries,
+                },
+            ),
+            blend_weight_bind_group_layout: (!fused).then(|| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.compute.bind_group_layout.blend_weight"),
+                    entries: &[
+                        sampler_entry(0, true),
+                        uniform_entry(1),
+                        texture_entry(2, true),
+                        texture_entry(3, true),
+                        texture_entry(4, true),
+                        storage_entry(5, storage_format),
+                    ],
+                })
+            }),
+            neighborhood_blending_bind_group_layout: if fused {
+                // The sampler is needed to filter the edges, so the color texture is only ever
+                // read with texelFetch if it isn't filterable.
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.compute.bind_group_layout.neighborhood_blending"),
+                    entries: &[
+                        sampler_entry(0, true),
+                        uniform_entry(1),
+                        texture_entry(2, color_filterable),
+                        texture_entry(3, true),
+                        texture_entry(4, true),
+                        texture_entry(5, true),
+                        storage_entry(6, output_format),
+                    ],
+                })
+            } else {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.compute.bind_group_layout.neighborhood_blending"),
+                    entries: &[
+                        sampler_entry(0, color_filterable),
+                        uniform_entry(1),
+                        texture_entry(2, color_filterable),
+                        texture_entry(3, true),
+                        storage_entry(4, output_format),
+                    ],
+                })
+            },
+        }
+    }
+}
+
+impl Pipelines {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        layouts: &BindGroupLayouts,
+        config: &SmaaConfig,
+    ) -> Self {
+        let source = shader_source(format, config, !layouts.color_filterable);
+        let storage_format = config.intermediate_precision().storage_format();
+        let create_pipeline = |label, stage, name, bind_group_layout| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &source.get_compute_shader(
+                    device,
+                    stage,
+                    name,
+                    storage_format,
+                    storage_format,
+                    format,
+                ),
+                entry_point: "main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            edge_detect: create_pipeline(
+                "smaa.compute.pipeline.edge_detect",
+                ShaderStage::EdgeDetectionCS,
+                "smaa.shader.edge_detect.comp",
+                &layouts.edge_detect_bind_group_layout,
+            ),
+            blend_weight: layouts.blend_weight_bind_group_layout.as_ref().map(
+                |bind_group_layout| {
+                    create_pipeline(
+                        "smaa.compute.pipeline.blend_weight",
+                        ShaderStage::BlendingWeightCS,
+                        "smaa.shader.blending_weight.comp",
+                        bind_group_layout,
+                    )
+                },
+            ),
+            neighborhood_blending: if layouts.blend_weight_bind_group_layout.is_some() {
+                create_pipeline(
+                    "smaa.compute.pipeline.neighborhood_blending",
+                    ShaderStage::NeighborhoodBlendingCS,
+                    "smaa.shader.neighborhood_blending.comp",
+                    &layouts.neighborhood_blending_bind_group_layout,
+                )
+            } else {
+                create_pipeline(
+                    "smaa.compute.pipeline.neighborhood_blending",
+                    ShaderStage::FusedNeighborhoodBlendingCS,
+                    "smaa.shader.neighborhood_blending.fused.comp",
+                    &layouts.neighborhood_blending_bind_group_layout,
+                )
+            },
+        }
+    }
+}
+
+impl Targets {
+    fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        overlap_frames: bool,
+    ) -> Self {
+        let create_color_target = || {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    label: None,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("smaa.color_target.view"),
+                    ..Default::default()
+                })
+        };
+
+        Self {
+            width,
+            height,
+            rt_uniforms: create_rt_uniforms(device, width, height),
+            color_targets: (0..1 + overlap_frames as usize)
+                .map(|_| create_color_target())
+                .collect(),
+            current: 0,
+        }
+    }
+
+    fn color_target(&self) -> &wgpu::TextureView {
+        &self.color_targets[self.current]
+    }
+}
+impl Intermediates {
+    fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        precision: SmaaPrecision,
+        fused: bool,
+    ) -> Self {
+        let texture_desc = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: precision.storage_format(),
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: None,
+            view_formats: &[],
+        };
+
+        Self {
+            width,
+            height,
+            rt_uniforms: create_rt_uniforms(device, width, height),
+            edges_target: device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("smaa.compute.texture.edge_target"),
+                    ..texture_desc
+                })
+                .create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("smaa.compute.texture_view.edge_target"),
+                    ..Default::default()
+                }),
+            blend_target: (!fused).then(|| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some("smaa.compute.texture.blend_target"),
+                        ..texture_desc
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("smaa.compute.texture_view.blend_target"),
+                        ..Default::default()
+                    })
+            }),
+        }
+    }
+}
+
+impl BindGroups {
+    fn edge_detect(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        resources: &Resources,
+        targets: &Targets,
+        intermediates: &Intermediates,
+        color_target: &wgpu::TextureView,
+        depth_view: Option<&wgpu::TextureView>,
+    ) -> wgpu::BindGroup {
+        let color_sampler = if layouts.color_filterable {
+            &resources.linear_sampler
+        } else {
+            &resources.nearest_sampler
+        };
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Sampler(color_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: targets.rt_uniforms.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(color_target),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&intermediates.edges_target),
+            },
+        ];
+        if let Some(depth_view) = depth_view {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            });
+        }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.compute.bind_group.edge_detect"),
+            layout: &layouts.edge_detect_bind_group_layout,
+            entries: &entries,
+        })
+    }
+
+    fn new(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        resources: &Resources,
+        targets: &Targets,
+        intermediates: &Intermediates,
+    ) -> Self {
+        Self {
+            edge_detect_bind_groups: match layouts.depth_early_out {
+                true => Vec::new(),
+                false => targets
+                    .color_targets
+                    .iter()
+                    .map(|color_target| {
+                        Self::edge_detect(
+                            device,
+                            layouts,
+                            resources,
+                            targets,
+                            intermediates,
+                            color_target,
+                            None,
+                        )
+                    })
+                    .collect(),
+            },
+            blend_weight_bind_group: layouts
+                .blend_weight_bind_group_layout
+                .as_ref()
+                .zip(intermediates.blend_target.as_ref())
+                .map(|(layout, blend_target)| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("smaa.compute.bind_group.blend_weight"),
+                        layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::Sampler(&resources.linear_sampler),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: intermediates.rt_uniforms.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &intermediates.edges_target,
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &resources.area_texture.create_view(&Default::default()),
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 4,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &resources.search_texture.create_view(&Default::default()),
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 5,
+                                resource: wgpu::BindingResource::TextureView(blend_target),
+                            },
+                        ],
+                    })
+                }),
+        }
+    }
+}
+
+pub(crate) struct ComputeTargetInner {
+    pipelines: Pipelines,
+    layouts: BindGroupLayouts,
+    resources: Arc<Resources>,
+    targets: Targets,
+    intermediates: Intermediates,
+    bind_groups: BindGroups,
+    format: wgpu::TextureFormat,
+    precision: SmaaPrecision,
+    fused: bool,
+    overlap_frames: bool,
+}
+impl ComputeTargetInner {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        config: &SmaaConfig,
+    ) -> Self {
+        assert!(
+            shader::storage_format_qualifier(format).is_some(),
+            "SmaaBackend::Compute doesn't support writing to {:?} outputs",
+            format
+        );
+
+        let fused = config.backend == SmaaBackend::ComputeFused;
+        let color_format = config.color_format.unwrap_or(format);
+        let layouts = BindGroupLayouts::new(
+            device,
+            color_format,
+            config.intermediate_precision().storage_format(),
+            format,
+            fused,
+            config.background_depth.is_some(),
+        );
+        let (resources, pipelines) = SmaaResources::for_config(device, queue, config, || {
+            Pipelines::new(device, format, &layouts, config)
+        });
+        let targets = Targets::new(device, width, height, color_format, config.overlap_frames);
+        let intermediates = Intermediates::new(
+            device,
+            width,
+            height,
+            config.intermediate_precision(),
+            fused,
+        );
+        let bind_groups = BindGroups::new(device, &layouts, &resources, &targets, &intermediates);
+
+        Self {
+            pipelines,
+            layouts,
+            resources,
+            targets,
+            intermediates,
+            bind_groups,
+            format: color_format,
+            precision: config.intermediate_precision(),
+            fused,
+            overlap_frames: config.overlap_frames,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32, slack: f32) {
+        self.targets = Targets::new(device, width, height, self.format, self.overlap_frames);
+        // The fused kernel reads the edges with the target's RT metrics.
+        let intermediates = &self.intermediates;
+        if self.fused
+            || !fits_with_slack(
+                (intermediates.width, intermediates.height),
+                (width, height),
+                slack,
+            )
+        {
+            self.intermediates =
+                Intermediates::new(device, width, height, self.precision, self.fused);
+        }
+        self.bind_groups = BindGroups::new(
+            device,
+            &self.layouts,
+            &self.resources,
+            &self.targets,
+            &self.intermediates,
+        );
+    }
+
+    pub fn rebuild_pipelines(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        config: &SmaaConfig,
+    ) {
+        self.pipelines = Pipelines::new(device, format, &self.layouts, config);
+    }
+
+    pub fn color_target(&self) -> &wgpu::TextureView {
+        self.targets.color_target()
+    }
+
+    /// Switch to the next color target once the current frame has been submitted.
+    pub fn finish_frame(&mut self) {
+        self.targets.current = (self.targets.current + 1) % self.targets.color_targets.len();
+    }
+
+    pub fn resources(&self) -> Arc<Resources> {
+        self.resources.clone()
+    }
+
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        depth_view: Option<&wgpu::TextureView>,
+        update_weights: bool,
+        timestamps: Option<&wgpu::QuerySet>,
+    ) {
+        let depth_bind_group;
+        let edge_detect_bind_group = match self
+            .bind_groups
+            .edge_detect_bind_groups
+            .get(self.targets.current)
+        {
+            _ if !update_weights => None,
+            Some(bind_group) => Some(bind_group),
+            None => {
+                depth_bind_group = BindGroups::edge_detect(
+                    device,
+                    &self.layouts,
+                    &self.resources,
+                    &self.targets,
+                    &self.intermediates,
+                    self.targets.color_target(),
+                    Some(depth_view.expect(MISSING_DEPTH_VIEW)),
+                );
+                Some(&depth_bind_group)
+            }
+        };
+        self.encode_passes(
+            device,
+            encoder,
+            self.targets.color_target(),
+            edge_detect_bind_group,
+            output_view,
+            timestamps,
+        );
+    }
+
+    /// Antialias each `(input, output)` pair in turn, reusing the intermediate textures.
+    pub fn encode_batch(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &[(&wgpu::TextureView, &wgpu::TextureView)],
+    ) {
+        for &(input, output) in images {
+            let edge_detect_bind_group = BindGroups::edge_detect(
+                device,
+                &self.layouts,
+                &self.resources,
+                &self.targets,
+                &self.intermediates,
+                input,
+                None,
+            );
+            self.encode_passes(
+                device,
+                encoder,
+                input,
+                Some(&edge_detect_bind_group),
+                output,
+                None,
+            );
+        }
+    }
+
+    /// Record the passes for one image. The edge detection and blend weight passes only run if
+    /// `edge_detect_bind_group` is given.
+    fn encode_passes(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        edge_detect_bind_group: Option<&wgpu::BindGroup>,
+        output_view: &wgpu::TextureView,
+        timestamps: Option<&wgpu::QuerySet>,
+    ) {
+        // The output view changes from frame to frame, so the last pass' bind group can't be
+        // created up front.
+        let area_view = self.resources.area_texture.create_view(&Default::default());
+        let search_view = self
+            .resources
+            .search_texture
+            .create_view(&Default::default());
+        let neighborhood_blending_bind_group = match self.intermediates.blend_target {
+            Some(ref blend_target) => {
+                let color_sampler = if self.layouts.color_filterable {
+                    &self.resources.linear_sampler
+                } else {
+                    &self.resources.nearest_sampler
+                };
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("smaa.compute.bind_group.neighborhood_blending"),
+                    layout: &self.layouts.neighborhood_blending_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::Sampler(color_sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: self.targets.rt_uniforms.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(color_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: wgpu::BindingResource::TextureView(blend_target),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: wgpu::BindingResource::TextureView(output_view),
+                        },
+                    ],
+                })
+            }
+            None => device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("smaa.compute.bind_group.neighborhood_blending"),
+                layout: &self.layouts.neighborhood_blending_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&self.resources.linear_sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: self.targets.rt_uniforms.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(color_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(
+                            &self.intermediates.edges_target,
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(&area_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::TextureView(&search_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: wgpu::BindingResource::TextureView(output_view),
+                    },
+                ],
+            }),
+        };
+
+        let workgroups = |width: u32, height: u32| {
+            (
+                width.div_ceil(WORKGROUP_SIZE),
+                height.div_ceil(WORKGROUP_SIZE),
+            )
+        };
+        let (workgroups_x, workgroups_y) = workgroups(self.targets.width, self.targets.height);
+        let (intermediate_workgroups_x, intermediate_workgroups_y) =
+            workgroups(self.intermediates.width, self.intermediates.height);
+        // Each step gets its own pass so that it can be timed separately.
+        let fused = self.pipelines.blend_weight.is_none();
+        if let Some(edge_detect_bind_group) = edge_detect_bind_group {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.compute_pass.edge_detect"),
+                timestamp_writes: timestamps.map(|query_set| wgpu::ComputePassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: Some(timing::EDGE_DETECTION_BEGIN),
+                    end_of_pass_write_index: Some(timing::EDGE_DETECTION_END),
+                }),
+            });
+            cpass.set_pipeline(&self.pipelines.edge_detect);
+            cpass.set_bind_group(0, edge_detect_bind_group, &[]);
+            cpass.dispatch_workgroups(intermediate_workgroups_x, intermediate_workgroups_y, 1);
+        }
+        if let (true, Some(pipeline), Some(bind_group)) = (
+            edge_detect_bind_group.is_some(),
+            &self.pipelines.blend_weight,
+            &self.bind_groups.blend_weight_bind_group,
+        ) {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.compute_pass.blend_weight"),
+                timestamp_writes: timestamps.map(|query_set| wgpu::ComputePassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: None,
+                    end_of_pass_write_index: Some(timing::BLEND_WEIGHTS_END),
+                }),
+            });
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.dispatch_workgroups(intermediate_workgroups_x, intermediate_workgroups_y, 1);
+        }
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("smaa.compute_pass.neighborhood_blending"),
+            timestamp_writes: timestamps.map(|query_set| wgpu::ComputePassTimestampWrites {
+                query_set,
+                beginning_of_pass_write_index: fused.then_some(timing::BLEND_WEIGHTS_END),
+                end_of_pass_write_index: Some(timing::NEIGHBORHOOD_BLENDING_END),
+            }),
+        });
+        cpass.set_pipeline(&self.pipelines.neighborhood_blending);
+        cpass.set_bind_group(0, &neighborhood_blending_bind_group, &[]);
+        cpass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+    }
+}
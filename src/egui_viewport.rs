@@ -0,0 +1,348 @@
+//! Rendering 3D viewports embedded in an [egui](https://github.com/emilk/egui) user interface
+//! through SMAA, with the `egui` feature.
+//!
+//! Call [`register`] once with the egui-wgpu render state, then add a [`paint_callback`] to a
+//! painter for every viewport in each frame:
+//!
+//! ```no_run
+//! # use egui_wgpu::wgpu;
+//! struct Scene;
+//!
+//! impl smaa::egui_viewport::SmaaScene for Scene {
+//!     fn render(
+//!         &self,
+//!         device: &wgpu::Device,
+//!         queue: &wgpu::Queue,
+//!         encoder: &mut wgpu::CommandEncoder,
+//!         view: &wgpu::TextureView,
+//!         size: (u32, u32),
+//!     ) {
+//!         // Record the render passes drawing the scene into `view`.
+//!     }
+//! }
+//!
+//! fn ui(ui: &mut egui::Ui) {
+//!     let (rect, _) = ui.allocate_exact_size(egui::vec2(400.0, 300.0), egui::Sense::drag());
+//!     ui.painter()
+//!         .add(smaa::egui_viewport::paint_callback(ui.id().with("viewport"), rect, Scene));
+//! }
+//! ```
+//!
+//! Each viewport gets its own [`SmaaTarget`], which is resized along with the viewport and
+//! dropped once the viewport isn't painted in a frame.
+
+use std::collections::HashMap;
+
+use egui_wgpu::{CallbackResources, CallbackTrait, ScreenDescriptor};
+
+use crate::{SmaaBackend, SmaaConfig, SmaaMode, SmaaTarget};
+
+/// Draws the scene shown in a viewport created with [`paint_callback`].
+pub trait SmaaScene: Send + Sync + 'static {
+    /// Record the commands that draw the scene into `view`, which is `size` pixels large and has
+    /// the format egui renders to.
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: (u32, u32),
+    );
+}
+
+/// Prepare the egui renderer for painting viewports, which will be antialiased with the given
+/// mode and settings. Calling it again replaces the settings and drops the existing targets.
+pub fn register(render_state: &egui_wgpu::RenderState, mode: SmaaMode, config: SmaaConfig) {
+    let viewports = Viewports::new(
+        &render_state.device,
+        render_state.target_format,
+        mode,
+        config,
+    );
+    render_state
+        .renderer
+        .write()
+        .callback_resources
+        .insert(viewports);
+}
+
+/// Paint callback that renders `scene` through SMAA into `rect`. `id` must stay the same from
+/// frame to frame and be unique among the viewports painted in a frame, so that each viewport
+/// keeps its target.
+///
+/// Panics when painted if [`register`] wasn't called.
+pub fn paint_callback(
+    id: egui::Id,
+    rect: egui::Rect,
+    scene: impl SmaaScene,
+) -> egui::PaintCallback {
+    egui_wgpu::Callback::new_paint_callback(rect, SmaaCallback { id, rect, scene })
+}
+
+struct Viewport {
+    size: (u32, u32),
+    target: SmaaTarget,
+    output: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    // Whether the viewport was prepared during the current frame.
+    used: bool,
+}
+
+struct Viewports {
+    format: wgpu::TextureFormat,
+    mode: SmaaMode,
+    config: SmaaConfig,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    viewports: HashMap<egui::Id, Viewport>,
+    // Callbacks prepared during the current frame that haven't reached `finish_prepare` yet.
+    pending: usize,
+}
+
+impl Viewports {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+        config: SmaaConfig,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smaa.egui.bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.egui.shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("egui_viewport.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("smaa.egui.pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            format,
+            mode,
+            config,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("smaa.egui.sampler"),
+                ..Default::default()
+            }),
+            bind_group_layout,
+            pipeline,
+            viewports: HashMap::new(),
+            pending: 0,
+        }
+    }
+
+    /// Create the texture that SMAA resolves into and that gets drawn into the viewport.
+    fn create_output(
+        &self,
+        device: &wgpu::Device,
+        (width, height): (u32, u32),
+    ) -> (wgpu::TextureView, wgpu::BindGroup) {
+        let usage = match (self.mode, self.config.backend) {
+            (SmaaMode::Cmaa2, _) => {
+                wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING
+            }
+            (_, SmaaBackend::Render) => {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+            }
+            _ => wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+        };
+        let output = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("smaa.egui.output"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage,
+                view_formats: &[],
+            })
+            .create_view(&Default::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.egui.bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&output),
+                },
+            ],
+        });
+        (output, bind_group)
+    }
+
+    /// The viewport with the given id, created or resized to `size` if needed.
+    fn get_or_resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: egui::Id,
+        size: (u32, u32),
+    ) -> &mut Viewport {
+        if !self.viewports.contains_key(&id) {
+            let (output, bind_group) = self.create_output(device, size);
+            let target = SmaaTarget::with_config(
+                device,
+                queue,
+                size.0,
+                size.1,
+                self.format,
+                self.mode,
+                self.config.clone(),
+            );
+            // Later viewports share the lookup textures of the first one.
+            if self.config.resources.is_none() {
+                self.config.resources = target.resources();
+            }
+            let viewport = Viewport {
+                size,
+                target,
+                output,
+                bind_group,
+                used: false,
+            };
+            self.viewports.insert(id, viewport);
+        } else if self.viewports[&id].size != size {
+            let (output, bind_group) = self.create_output(device, size);
+            let viewport = self.viewports.get_mut(&id).unwrap();
+            viewport.target.resize(device, size.0, size.1);
+            viewport.size = size;
+            viewport.output = output;
+            viewport.bind_group = bind_group;
+        }
+        self.viewports.get_mut(&id).unwrap()
+    }
+}
+
+struct SmaaCallback<S> {
+    id: egui::Id,
+    rect: egui::Rect,
+    scene: S,
+}
+
+impl<S: SmaaScene> CallbackTrait for SmaaCallback<S> {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        screen_descriptor: &ScreenDescriptor,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let viewports: &mut Viewports = callback_resources
+            .get_mut()
+            .expect("smaa::egui_viewport::register wasn't called");
+        viewports.pending += 1;
+
+        // Rounded the same way as the viewport egui sets before painting.
+        let viewport_px = egui::PaintCallbackInfo {
+            viewport: self.rect,
+            clip_rect: self.rect,
+            pixels_per_point: screen_descriptor.pixels_per_point,
+            screen_size_px: screen_descriptor.size_in_pixels,
+        }
+        .viewport_in_pixels();
+        if viewport_px.width_px <= 0 || viewport_px.height_px <= 0 {
+            return Vec::new();
+        }
+        let size = (viewport_px.width_px as u32, viewport_px.height_px as u32);
+
+        let viewport = viewports.get_or_resize(device, queue, self.id, size);
+        viewport.used = true;
+        let frame = viewport.target.start_frame(device, queue, &viewport.output);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.egui.command_encoder"),
+        });
+        self.scene.render(device, queue, &mut encoder, &frame, size);
+        // The scene has to be submitted before the frame is resolved, which submits the SMAA
+        // passes right away, so it can't be returned to egui.
+        queue.submit(Some(encoder.finish()));
+        frame.resolve();
+        Vec::new()
+    }
+
+    fn finish_prepare(
+        &self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let viewports: &mut Viewports = callback_resources.get_mut().unwrap();
+        viewports.pending -= 1;
+        // Once every callback of the frame is prepared, drop the viewports that weren't painted.
+        if viewports.pending == 0 {
+            viewports
+                .viewports
+                .retain(|_, viewport| std::mem::take(&mut viewport.used));
+        }
+        Vec::new()
+    }
+
+    fn paint(
+        &self,
+        _info: egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'static>,
+        callback_resources: &CallbackResources,
+    ) {
+        let viewports: &Viewports = callback_resources.get().unwrap();
+        if let Some(viewport) = viewports.viewports.get(&self.id) {
+            render_pass.set_pipeline(&viewports.pipeline);
+            render_pass.set_bind_group(0, &viewport.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+// Draws the antialiased viewport over the whole of the viewport that egui sets before painting.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32(i32(vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(vertex_index & 2u) * 2 - 1);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>(x + 1.0, 1.0 - y) * 0.5;
+    return out;
+}
+
+@group(0) @binding(0) var nearest_sampler: sampler;
+@group(0) @binding(1) var viewport: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(viewport, nearest_sampler, in.uv);
+}
@@ -0,0 +1,100 @@
+//! Errors that [`SmaaTarget::try_start_frame`] and [`SmaaFrame::try_resolve`] return instead of
+//! recording commands that wgpu would reject.
+//!
+//! [`SmaaTarget::try_start_frame`]: crate::SmaaTarget::try_start_frame
+//! [`SmaaFrame::try_resolve`]: crate::SmaaFrame::try_resolve
+
+use crate::{MISSING_DEPTH_VIEW, MISSING_LUMA_VIEW, MISSING_SECONDARY_VIEW};
+
+/// Why a frame can't be resolved into an output with the settings of its target.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SmaaError {
+    /// The output isn't a 2D texture.
+    OutputDimension(wgpu::TextureDimension),
+    /// The output's format isn't the format of the target.
+    OutputFormat {
+        /// Format of the target.
+        expected: wgpu::TextureFormat,
+        /// Format of the output.
+        found: wgpu::TextureFormat,
+    },
+    /// The output lacks usages that the final pass needs to write it.
+    OutputUsage {
+        /// Usages that the output must also have been created with.
+        missing: wgpu::TextureUsages,
+    },
+    /// The output is smaller than [`crate::SmaaTarget::output_size`].
+    OutputSize {
+        /// Size of the outputs of the target.
+        expected: (u32, u32),
+        /// Size of the output.
+        found: (u32, u32),
+    },
+    /// The rectangle of [`crate::SmaaTarget::output_size`] at the origin set with
+    /// [`crate::SmaaFrame::set_output_origin`] doesn't fit within the output.
+    OutputOrigin {
+        /// Origin of the rectangle.
+        origin: (u32, u32),
+        /// Size of the rectangle.
+        size: (u32, u32),
+        /// Size of the output.
+        found: (u32, u32),
+    },
+    /// The output doesn't have the number of samples of
+    /// [`crate::SmaaConfig::output_sample_count`].
+    OutputSampleCount {
+        /// Samples of the outputs of the target.
+        expected: u32,
+        /// Samples of the output.
+        found: u32,
+    },
+    /// The settings need the scene's depth, but the frame wasn't started with one.
+    MissingDepthView,
+    /// [`crate::SmaaConfig::luma_input`] is set, but the frame wasn't given a luma view.
+    MissingLumaView,
+    /// [`crate::SmaaConfig::secondary_output`] is set, but the frame wasn't given a view for it.
+    MissingSecondaryView,
+}
+
+impl std::fmt::Display for SmaaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            SmaaError::OutputDimension(dimension) => {
+                write!(f, "outputs must be 2D textures, not {dimension:?}")
+            }
+            SmaaError::OutputFormat { expected, found } => write!(
+                f,
+                "output has format {found:?}, but the target was created for {expected:?}"
+            ),
+            SmaaError::OutputUsage { missing } => {
+                write!(f, "output is missing the usages {missing:?}")
+            }
+            SmaaError::OutputSize { expected, found } => write!(
+                f,
+                "output is {}x{} pixels, too small for the frames of {}x{} that the target \
+                resolves",
+                found.0, found.1, expected.0, expected.1
+            ),
+            SmaaError::OutputOrigin {
+                origin,
+                size,
+                found,
+            } => write!(
+                f,
+                "frames of {}x{} at ({}, {}) don't fit within an output of {}x{}",
+                size.0, size.1, origin.0, origin.1, found.0, found.1
+            ),
+            SmaaError::OutputSampleCount { expected, found } => write!(
+                f,
+                "output has {found} samples, but SmaaConfig::output_sample_count asks for \
+                {expected}"
+            ),
+            SmaaError::MissingDepthView => f.write_str(MISSING_DEPTH_VIEW),
+            SmaaError::MissingLumaView => f.write_str(MISSING_LUMA_VIEW),
+            SmaaError::MissingSecondaryView => f.write_str(MISSING_SECONDARY_VIEW),
+        }
+    }
+}
+
+impl std::error::Error for SmaaError {}
@@ -0,0 +1,183 @@
+//! Histogram-based auto-exposure, which meters the scene of a `PostChain` and writes the
+//! exposure of its tonemap stage on the GPU.
+
+use wgpu::util::DeviceExt;
+
+use crate::PostAutoExposure;
+
+/// Number of bins of the histogram, which must match `BINS` in `exposure.wgsl`.
+const BINS: u64 = 128;
+/// Width and height of the workgroups of `cs_histogram`.
+const WORKGROUP_SIZE: u32 = 16;
+
+pub(crate) struct AutoExposure {
+    bind_group_layout: wgpu::BindGroupLayout,
+    histogram_pipeline: wgpu::ComputePipeline,
+    adapt_pipeline: wgpu::ComputePipeline,
+    settings: wgpu::Buffer,
+    histogram: wgpu::Buffer,
+    // Kept across resizes, so that the exposure doesn't jump when the window is resized.
+    state: wgpu::Buffer,
+    bind_group: Option<wgpu::BindGroup>,
+    scene_size: (u32, u32),
+}
+
+impl AutoExposure {
+    /// Create the passes for `settings`, with `exposure` scaling the metered exposure.
+    pub fn new(device: &wgpu::Device, settings: &PostAutoExposure, exposure: f32) -> Self {
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smaa.bind_group_layout.auto_exposure"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(2),
+                storage_entry(3),
+                storage_entry(4),
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.shader.auto_exposure.comp"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("exposure.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("smaa.pipeline_layout.auto_exposure"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point,
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let contents: Vec<u8> = [
+            settings.min_log_luminance,
+            settings.max_log_luminance - settings.min_log_luminance,
+            settings.low_fraction,
+            settings.high_fraction,
+            settings.key,
+            settings.adaptation.clamp(0.0, 1.0),
+            exposure,
+            0.0,
+        ]
+        .iter()
+        .flat_map(|f| f.to_ne_bytes())
+        .collect();
+        Self {
+            histogram_pipeline: create_pipeline(
+                "smaa.pipeline.auto_exposure_histogram",
+                "cs_histogram",
+            ),
+            adapt_pipeline: create_pipeline("smaa.pipeline.auto_exposure_adapt", "cs_adapt"),
+            bind_group_layout,
+            settings: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("smaa.buffer.auto_exposure_settings"),
+                contents: &contents,
+                usage: wgpu::BufferUsages::UNIFORM,
+            }),
+            histogram: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.auto_exposure_histogram"),
+                size: BINS * 4,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }),
+            state: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.auto_exposure_state"),
+                size: 8,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }),
+            bind_group: None,
+            scene_size: (0, 0),
+        }
+    }
+
+    /// Meter `scene` of the given size and write the exposure into `tonemap_params`, which must
+    /// have been created with [`wgpu::BufferUsages::STORAGE`].
+    pub fn bind(
+        &mut self,
+        device: &wgpu::Device,
+        scene: &wgpu::TextureView,
+        scene_size: (u32, u32),
+        tonemap_params: &wgpu::Buffer,
+    ) {
+        self.scene_size = scene_size;
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.auto_exposure"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.settings.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.histogram.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.state.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: tonemap_params.as_entire_binding(),
+                },
+            ],
+        }));
+    }
+
+    /// Record the passes building the histogram and adapting the exposure to it.
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder) {
+        let (width, height) = self.scene_size;
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("smaa.compute_pass.auto_exposure"),
+            timestamp_writes: None,
+        });
+        cpass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        cpass.set_pipeline(&self.histogram_pipeline);
+        cpass.dispatch_workgroups(
+            width.div_ceil(WORKGROUP_SIZE),
+            height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+        cpass.set_pipeline(&self.adapt_pipeline);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+}
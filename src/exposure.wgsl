@@ -0,0 +1,100 @@
+// Auto-exposure of a PostChain: a histogram of the log2 luminance of the scene, and a single
+// workgroup that averages it and adapts the exposure of the tonemap stage towards it.
+
+struct Settings {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    low_fraction: f32,
+    high_fraction: f32,
+    key: f32,
+    adaptation: f32,
+    exposure: f32,
+    _padding: f32,
+}
+
+// Bin 0 counts black pixels, which are left out of the average.
+const BINS: u32 = 128u;
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> settings: Settings;
+@group(0) @binding(2) var<storage, read_write> histogram: array<atomic<u32>, BINS>;
+// (adapted log2 luminance, 1 once it has been initialized)
+@group(0) @binding(3) var<storage, read_write> state: vec2<f32>;
+// Parameters of the tonemap stage, with the exposure in x.
+@group(0) @binding(4) var<storage, read_write> tonemap_params: vec4<f32>;
+
+var<workgroup> local_bins: array<atomic<u32>, BINS>;
+var<workgroup> counts: array<u32, BINS>;
+
+fn bin_of(luminance: f32) -> u32 {
+    if luminance < 1e-6 {
+        return 0u;
+    }
+    let t = clamp((log2(luminance) - settings.min_log_luminance) / settings.log_luminance_range, 0.0, 1.0);
+    return 1u + min(u32(t * f32(BINS - 1u)), BINS - 2u);
+}
+
+fn log_luminance_of(bin: u32) -> f32 {
+    let t = (f32(bin - 1u) + 0.5) / f32(BINS - 1u);
+    return settings.min_log_luminance + t * settings.log_luminance_range;
+}
+
+@compute @workgroup_size(16, 16)
+fn cs_histogram(
+    @builtin(global_invocation_id) pixel: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    if index < BINS {
+        atomicStore(&local_bins[index], 0u);
+    }
+    workgroupBarrier();
+    if all(pixel.xy < textureDimensions(scene_tex)) {
+        let rgb = max(textureLoad(scene_tex, pixel.xy, 0).rgb, vec3<f32>(0.0));
+        atomicAdd(&local_bins[bin_of(dot(rgb, LUMA))], 1u);
+    }
+    workgroupBarrier();
+    if index < BINS {
+        atomicAdd(&histogram[index], atomicLoad(&local_bins[index]));
+    }
+}
+
+@compute @workgroup_size(128)
+fn cs_adapt(@builtin(local_invocation_index) index: u32) {
+    // Clear the histogram for the next frame while reading it.
+    counts[index] = atomicExchange(&histogram[index], 0u);
+    workgroupBarrier();
+    if index != 0u {
+        return;
+    }
+
+    var total = 0u;
+    for (var i = 1u; i < BINS; i++) {
+        total += counts[i];
+    }
+    // Average the bins between the darkest and brightest fractions that are ignored.
+    let low = f32(total) * settings.low_fraction;
+    let high = f32(total) * settings.high_fraction;
+    var seen = 0.0;
+    var sum = 0.0;
+    var weight = 0.0;
+    for (var i = 1u; i < BINS; i++) {
+        let count = f32(counts[i]);
+        let inside = max(min(seen + count, high) - max(seen, low), 0.0);
+        sum += inside * log_luminance_of(i);
+        weight += inside;
+        seen += count;
+    }
+
+    var adapted = state.x;
+    if weight > 0.0 {
+        let measured = sum / weight;
+        if state.y == 0.0 {
+            adapted = measured;
+        } else {
+            adapted = mix(adapted, measured, settings.adaptation);
+        }
+        state = vec2<f32>(adapted, 1.0);
+    }
+    tonemap_params.x = settings.exposure * settings.key / exp2(adapted);
+}
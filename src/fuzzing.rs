@@ -0,0 +1,197 @@
+//! Entry points for the fuzz targets in `fuzz/`, with the `fuzzing` feature. They aren't part
+//! of the stable API and may change with any release.
+
+use wgpu::naga;
+
+use crate::shader::{
+    parse_workgroup_shader, ColorTransform, ShaderQuality, ShaderSource, ShaderStage,
+};
+use crate::{
+    SmaaDebugOutput, SmaaDepthProjection, SmaaEdgeDetection, SmaaOutputEncoding, SmaaPreTonemap,
+    SmaaSecondaryOutput,
+};
+
+/// Stages that the render backend compiles.
+const RENDER_STAGES: [ShaderStage; 8] = [
+    ShaderStage::EdgeDetectionVS,
+    ShaderStage::LumaEdgeDetectionPS,
+    ShaderStage::BlendingWeightVS,
+    ShaderStage::BlendingWeightTiledVS,
+    ShaderStage::BlendingWeightPS,
+    ShaderStage::NeighborhoodBlendingVS,
+    ShaderStage::NeighborhoodBlendingPS,
+    ShaderStage::NeighborhoodBlendingCopyPS,
+];
+
+/// Stages that the compute backend compiles.
+const COMPUTE_STAGES: [ShaderStage; 4] = [
+    ShaderStage::EdgeDetectionCS,
+    ShaderStage::BlendingWeightCS,
+    ShaderStage::NeighborhoodBlendingCS,
+    ShaderStage::FusedNeighborhoodBlendingCS,
+];
+
+/// Formats that the compute stages can write.
+const STORAGE_FORMATS: [wgpu::TextureFormat; 4] = [
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Rgba8Snorm,
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgba32Float,
+];
+
+const COLOR_TRANSFORMS: [ColorTransform; 3] = [
+    ColorTransform::None,
+    ColorTransform::SrgbToLinear,
+    ColorTransform::LinearToSrgb,
+];
+
+/// Settings read from the fuzzer's input, which is treated as ending in zeros once it runs out.
+struct Input<'a>(&'a [u8]);
+impl Input<'_> {
+    fn byte(&mut self) -> u8 {
+        match self.0.split_first() {
+            Some((&byte, rest)) => {
+                self.0 = rest;
+                byte
+            }
+            None => 0,
+        }
+    }
+
+    fn bool(&mut self) -> bool {
+        self.byte() & 1 != 0
+    }
+
+    fn pick<T: Copy>(&mut self, options: &[T]) -> T {
+        options[self.byte() as usize % options.len()]
+    }
+
+    /// A positive value, like the distances, thresholds and brightnesses that settings take.
+    fn positive(&mut self) -> f32 {
+        0.25 + self.byte() as f32 / 8.0
+    }
+
+    fn shader_source(&mut self) -> ShaderSource {
+        ShaderSource {
+            quality: match self.byte() % 4 {
+                0 => ShaderQuality::Low,
+                1 => ShaderQuality::Medium,
+                2 => ShaderQuality::High,
+                _ => ShaderQuality::Ultra,
+            },
+            blend_transform: self.pick(&COLOR_TRANSFORMS),
+            edge_transform: self.pick(&COLOR_TRANSFORMS),
+            pre_tonemap: match self.byte() % 5 {
+                0 => SmaaPreTonemap::None,
+                1 => SmaaPreTonemap::Karis,
+                2 => SmaaPreTonemap::Reinhard,
+                3 => SmaaPreTonemap::Aces,
+                _ => SmaaPreTonemap::Custom {
+                    tonemap: "return c / (1.0 + c);",
+                    inverse: "return c / max(1.0 - c, 1e-5);",
+                },
+            },
+            manual_filtering: self.bool(),
+            output_transform: self.pick(&COLOR_TRANSFORMS),
+            output_encoding: match self.byte() % 3 {
+                0 => SmaaOutputEncoding::None,
+                1 => SmaaOutputEncoding::Hdr10 {
+                    paper_white_nits: self.positive(),
+                    peak_nits: self.positive(),
+                },
+                _ => SmaaOutputEncoding::ScRgb {
+                    paper_white_nits: self.positive(),
+                },
+            },
+            background_depth: self.bool().then(|| self.byte() as f32 / 255.0),
+            rgba_edges: self.bool(),
+            luma_output: self.bool(),
+            luma_input: self.bool(),
+            secondary_output: self.pick(&[
+                SmaaSecondaryOutput::None,
+                SmaaSecondaryOutput::EdgeMask,
+                SmaaSecondaryOutput::Luma,
+            ]),
+            deterministic: self.bool(),
+            debug_output: match self.bool() {
+                false => SmaaDebugOutput::None,
+                true => SmaaDebugOutput::Difference {
+                    amplification: self.positive(),
+                },
+            },
+            edge_detection: match self.byte() % 5 {
+                0 => SmaaEdgeDetection::Luma,
+                1 => SmaaEdgeDetection::Alpha,
+                2 => SmaaEdgeDetection::LumaAndAlpha,
+                3 => SmaaEdgeDetection::Depth(SmaaDepthProjection::Perspective {
+                    near: self.positive(),
+                    far: self.positive() + 256.0,
+                    reversed_z: self.bool(),
+                }),
+                _ => SmaaEdgeDetection::Depth(SmaaDepthProjection::Orthographic {
+                    near: self.positive(),
+                    far: self.positive() + 256.0,
+                    reversed_z: self.bool(),
+                    threshold: self.positive(),
+                }),
+            },
+            premultiplied_alpha: self.bool(),
+            composite_alpha_mode: self.pick(&[
+                wgpu::CompositeAlphaMode::Auto,
+                wgpu::CompositeAlphaMode::Opaque,
+                wgpu::CompositeAlphaMode::PreMultiplied,
+                wgpu::CompositeAlphaMode::PostMultiplied,
+                wgpu::CompositeAlphaMode::Inherit,
+            ]),
+            single_channel: self.bool(),
+            cropped_input: self.bool(),
+        }
+    }
+}
+
+/// Assemble the GLSL of a stage with settings, stage and storage formats picked from `data`,
+/// translate it with naga's GLSL frontend and validate the result. Panics if the source doesn't
+/// parse or the module isn't valid.
+pub fn assemble_shader(data: &[u8]) {
+    let mut input = Input(data);
+    let shader_source = input.shader_source();
+    let (stage, source) = match input.bool() {
+        false => {
+            let stage = input.pick(&RENDER_STAGES);
+            (stage, shader_source.source(stage))
+        }
+        true => {
+            let stage = input.pick(&COMPUTE_STAGES);
+            let source = shader_source.compute_source(
+                stage,
+                input.pick(&STORAGE_FORMATS),
+                input.pick(&STORAGE_FORMATS),
+                input.pick(&STORAGE_FORMATS),
+            );
+            (stage, source)
+        }
+    };
+
+    let module = match stage {
+        ShaderStage::FusedNeighborhoodBlendingCS => {
+            parse_workgroup_shader(stage, "fuzzed shader", &source)
+        }
+        _ => naga::front::glsl::Frontend::default()
+            .parse(
+                &naga::front::glsl::Options::from(stage.naga_stage()),
+                &source,
+            )
+            .unwrap_or_else(|errors| panic!("failed to parse fuzzed shader: {errors}")),
+    };
+    if let Err(error) = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    {
+        panic!(
+            "fuzzed shader is invalid: {}",
+            error.emit_to_string(&source)
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Generators for the area and search lookup textures, ported from `AreaTex.py` and
+//! `SearchTex.py` of the reference implementation. With the default smoothing distance they
+//! produce exactly the same bytes as the embedded textures.
+
+use std::sync::OnceLock;
+
+use crate::lookup::{AREATEX_HEIGHT, AREATEX_WIDTH, SEARCHTEX_HEIGHT, SEARCHTEX_WIDTH};
+
+/// Size of a pattern block in the orthogonal half of the area texture, which the shaders know as
+/// `SMAA_AREATEX_MAX_DISTANCE`.
+const SIZE_ORTHO: usize = 16;
+/// Size of a pattern block in the diagonal half of the area texture, which the shaders know as
+/// `SMAA_AREATEX_MAX_DISTANCE_DIAG`.
+const SIZE_DIAG: usize = 20;
+/// Height of the area texture used by each subsample offset.
+const SUBTEX_SIZE: usize = 80;
+/// Number of samples per axis used to estimate the coverage of diagonal patterns.
+const SAMPLES_DIAG: usize = 30;
+
+/// Offsets of the line end points for SMAA S2x and 4x, with 0 used by SMAA 1x.
+const SUBSAMPLE_OFFSETS_ORTHO: [f64; 7] = [0.0, -0.25, 0.25, -0.125, 0.125, -0.375, 0.375];
+const SUBSAMPLE_OFFSETS_DIAG: [(f64, f64); 5] = [
+    (0.0, 0.0),
+    (0.25, -0.25),
+    (-0.25, 0.25),
+    (0.125, -0.125),
+    (-0.125, 0.125),
+];
+
+/// Position of the block of each pattern, indexed by the edges at both of its ends.
+const EDGES_ORTHO: [(usize, usize); 16] = [
+    (0, 0),
+    (3, 0),
+    (0, 3),
+    (3, 3),
+    (1, 0),
+    (4, 0),
+    (1, 3),
+    (4, 3),
+    (0, 1),
+    (3, 1),
+    (0, 4),
+    (3, 4),
+    (1, 1),
+    (4, 1),
+    (1, 4),
+    (4, 4),
+];
+const EDGES_DIAG: [(usize, usize); 16] = [
+    (0, 0),
+    (1, 0),
+    (0, 2),
+    (1, 2),
+    (2, 0),
+    (3, 0),
+    (2, 2),
+    (3, 2),
+    (0, 1),
+    (1, 1),
+    (0, 3),
+    (1, 3),
+    (2, 1),
+    (3, 1),
+    (2, 3),
+    (3, 3),
+];
+
+type Point = (f64, f64);
+
+/// Default distance in pixels beyond which U shaped patterns are no longer rounded.
+pub const SMOOTH_MAX_DISTANCE: f32 = 32.0;
+
+/// Area texture and search texture generated with the default settings, computed once.
+pub fn default_textures() -> &'static (Vec<u8>, Vec<u8>) {
+    static TEXTURES: OnceLock<(Vec<u8>, Vec<u8>)> = OnceLock::new();
+    TEXTURES.get_or_init(|| (area_texture(SMOOTH_MAX_DISTANCE), search_texture()))
+}
+
+/// Contents of the Rg8Unorm area texture.
+pub fn area_texture(smooth_max_distance: f32) -> Vec<u8> {
+    let smooth_max_distance = smooth_max_distance as f64;
+    let width = AREATEX_WIDTH as usize;
+    let mut bytes = vec![0; width * AREATEX_HEIGHT as usize * 2];
+    let mut store = |x: usize, y: usize, area: [f64; 2]| {
+        let i = (y * width + x) * 2;
+        bytes[i] = (255.0 * area[0]) as u8;
+        bytes[i + 1] = (255.0 * area[1]) as u8;
+    };
+
+    // The orthogonal patterns take the left half. Distances are stored quadratically, since the
+    // shaders look them up by their square root.
+    for (i, &offset) in SUBSAMPLE_OFFSETS_ORTHO.iter().enumerate() {
+        for (pattern, &(e1, e2)) in EDGES_ORTHO.iter().enumerate() {
+            for right in 0..SIZE_ORTHO {
+                for left in 0..SIZE_ORTHO {
+                    let area = area_ortho(
+                        pattern,
+                        (left * left) as f64,
+                        (right * right) as f64,
+                        offset,
+                        smooth_max_distance,
+                    );
+                    store(
+                        e1 * SIZE_ORTHO + left,
+                        i * SUBTEX_SIZE + e2 * SIZE_ORTHO + right,
+                        area,
+                    );
+                }
+            }
+        }
+    }
+
+    // The diagonal patterns take the right half, leaving the subtextures of the two offsets that
+    // only the orthogonal patterns use empty.
+    for (i, &offset) in SUBSAMPLE_OFFSETS_DIAG.iter().enumerate() {
+        for (pattern, &(e1, e2)) in EDGES_DIAG.iter().enumerate() {
+            for right in 0..SIZE_DIAG {
+                for left in 0..SIZE_DIAG {
+                    let area = area_diag(pattern, left as f64, right as f64, offset);
+                    store(
+                        width / 2 + e1 * SIZE_DIAG + left,
+                        i * SUBTEX_SIZE + e2 * SIZE_DIAG + right,
+                        area,
+                    );
+                }
+            }
+        }
+    }
+
+    bytes
+}
+
+/// Area below and above the line from `p1` to `p2` covered by the pixel starting at `x`.
+fn area_line(p1: Point, p2: Point, x: f64) -> [f64; 2] {
+    let d = (p2.0 - p1.0, p2.1 - p1.1);
+    let x1 = x;
+    let x2 = x + 1.0;
+    let y1 = p1.1 + d.1 * (x1 - p1.0) / d.0;
+    let y2 = p1.1 + d.1 * (x2 - p1.0) / d.0;
+
+    let inside = (x1 >= p1.0 && x1 < p2.0) || (x2 > p1.0 && x2 <= p2.0);
+    if !inside {
+        return [0.0, 0.0];
+    }
+
+    let is_trapezoid = y1.signum() == y2.signum() || y1.abs() < 1e-4 || y2.abs() < 1e-4;
+    if is_trapezoid {
+        let a = (y1 + y2) / 2.0;
+        if a < 0.0 {
+            [a.abs(), 0.0]
+        } else {
+            [0.0, a.abs()]
+        }
+    } else {
+        // The line crosses the middle of the pixel, splitting it into two triangles.
+        let x = -p1.1 * d.0 / d.1 + p1.0;
+        let a1 = if x > p1.0 { y1 * x.fract() / 2.0 } else { 0.0 };
+        let a2 = if x < p2.0 {
+            y2 * (1.0 - x.fract()) / 2.0
+        } else {
+            0.0
+        };
+        let a = if a1.abs() > a2.abs() { a1 } else { -a2 };
+        if a < 0.0 {
+            [a1.abs(), a2.abs()]
+        } else {
+            [a2.abs(), a1.abs()]
+        }
+    }
+}
+
+fn add(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn average(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]
+}
+
+/// Round the corners of short U shaped patterns, which would otherwise be cut off sharply.
+fn smooth_area(d: f64, a1: [f64; 2], a2: [f64; 2], smooth_max_distance: f64) -> [f64; 2] {
+    let p = (d / smooth_max_distance).clamp(0.0, 1.0);
+    let smooth = |a: f64| {
+        let b = (a * 2.0).sqrt() * 0.5;
+        b + (a - b) * p
+    };
+    [smooth(a1[0]) + smooth(a2[0]), smooth(a1[1]) + smooth(a2[1])]
+}
+
+/// Area for an orthogonal pattern with the given distances to its left and right ends.
+fn area_ortho(
+    pattern: usize,
+    left: f64,
+    right: f64,
+    offset: f64,
+    smooth_max_distance: f64,
+) -> [f64; 2] {
+    let d = left + right + 1.0;
+    let o1 = 0.5 + offset;
+    let o2 = 0.5 + offset - 1.0;
+    let center = d / 2.0;
+
+    // L patterns are only offset on the side of the crossing edge, so that they converge with
+    // the unfiltered pattern 0. Z patterns blend the full offset line with two offset L patterns,
+    // since depending on the search distance their ends may only be detected as L patterns.
+    match pattern {
+        //   .------
+        //   |
+        1 if left <= right => area_line((0.0, o2), (center, 0.0), left),
+        //    ------.
+        //          |
+        2 if left >= right => area_line((center, 0.0), (d, o2), left),
+        //   .------.
+        //   |      |
+        3 => smooth_area(
+            d,
+            area_line((0.0, o2), (center, 0.0), left),
+            area_line((center, 0.0), (d, o2), left),
+            smooth_max_distance,
+        ),
+        //   |
+        //   `------
+        4 if left <= right => area_line((0.0, o1), (center, 0.0), left),
+        //   |
+        //   `------.
+        //          |
+        6 if offset != 0.0 => average(
+            area_line((0.0, o1), (d, o2), left),
+            add(
+                area_line((0.0, o1), (center, 0.0), left),
+                area_line((center, 0.0), (d, o2), left),
+            ),
+        ),
+        //   |              |
+        //   `------.  or   +------.
+        //          |       |      |
+        6 | 7 | 14 => area_line((0.0, o1), (d, o2), left),
+        //          |
+        //    ------´
+        8 if left >= right => area_line((center, 0.0), (d, o1), left),
+        //          |
+        //   .------´
+        //   |
+        9 if offset != 0.0 => average(
+            area_line((0.0, o2), (d, o1), left),
+            add(
+                area_line((0.0, o2), (center, 0.0), left),
+                area_line((center, 0.0), (d, o1), left),
+            ),
+        ),
+        //          |              |
+        //   .------´  or   .------+
+        //   |              |      |
+        9 | 11 | 13 => area_line((0.0, o2), (d, o1), left),
+        //   |      |
+        //   `------´
+        12 => smooth_area(
+            d,
+            area_line((0.0, o1), (center, 0.0), left),
+            area_line((center, 0.0), (d, o1), left),
+            smooth_max_distance,
+        ),
+        // Patterns without any crossing edges, or with crossing edges on both sides at one end.
+        _ => [0.0, 0.0],
+    }
+}
+
+/// Fraction of the pixel at `p` above the line through `p1` and `p2`, estimated by sampling.
+fn area_sampled(p1: Point, p2: Point, p: Point) -> f64 {
+    if p1 == p2 {
+        return 1.0;
+    }
+
+    let (xm, ym) = ((p1.0 + p2.0) / 2.0, (p1.1 + p2.1) / 2.0);
+    let a = p2.1 - p1.1;
+    let b = p1.0 - p2.0;
+    let mut inside = 0;
+    for x in 0..SAMPLES_DIAG {
+        for y in 0..SAMPLES_DIAG {
+            let sx = p.0 + x as f64 / (SAMPLES_DIAG - 1) as f64;
+            let sy = p.1 + y as f64 / (SAMPLES_DIAG - 1) as f64;
+            if a * (sx - xm) + b * (sy - ym) > 0.0 {
+                inside += 1;
+            }
+        }
+    }
+    inside as f64 / (SAMPLES_DIAG * SAMPLES_DIAG) as f64
+}
+
+/// Area of the pixel and the one opposite to it for the diagonal line from `p1` to `p2`.
+fn area_diag_line(pattern: usize, p1: Point, p2: Point, left: f64, offset: Point) -> [f64; 2] {
+    let (e1, e2) = EDGES_DIAG[pattern];
+    let p1 = if e1 > 0 {
+        (p1.0 + offset.0, p1.1 + offset.1)
+    } else {
+        p1
+    };
+    let p2 = if e2 > 0 {
+        (p2.0 + offset.0, p2.1 + offset.1)
+    } else {
+        p2
+    };
+    let a1 = area_sampled(p1, p2, (1.0 + left, left));
+    let a2 = area_sampled(p1, p2, (1.0 + left, 1.0 + left));
+    [1.0 - a1, a2]
+}
+
+/// Area for a diagonal pattern with the given distances to its left and right ends.
+fn area_diag(pattern: usize, left: f64, right: f64, offset: Point) -> [f64; 2] {
+    let d = left + right + 1.0;
+    let line =
+        |p1: Point, p2: Point| area_diag_line(pattern, p1, (p2.0 + d, p2.1 + d), left, offset);
+
+    // Unlike orthogonal patterns, the pattern without crossing edges must be filtered too. And
+    // since the searches can't tell how the ends of the lines continue, most patterns are a
+    // blend of the two possible endings.
+    match pattern {
+        0 | 15 => average(line((1.0, 1.0), (1.0, 1.0)), line((1.0, 0.0), (1.0, 0.0))),
+        1 => average(line((1.0, 0.0), (0.0, 0.0)), line((1.0, 0.0), (1.0, 0.0))),
+        2 => average(line((0.0, 0.0), (1.0, 0.0)), line((1.0, 0.0), (1.0, 0.0))),
+        3 => line((1.0, 0.0), (1.0, 0.0)),
+        4 => average(line((1.0, 1.0), (0.0, 0.0)), line((1.0, 1.0), (1.0, 0.0))),
+        5 => average(line((1.0, 1.0), (0.0, 0.0)), line((1.0, 0.0), (1.0, 0.0))),
+        6 => line((1.0, 1.0), (1.0, 0.0)),
+        7 => average(line((1.0, 1.0), (1.0, 0.0)), line((1.0, 0.0), (1.0, 0.0))),
+        8 => average(line((0.0, 0.0), (1.0, 1.0)), line((1.0, 0.0), (1.0, 1.0))),
+        9 => line((1.0, 0.0), (1.0, 1.0)),
+        10 => average(line((0.0, 0.0), (1.0, 1.0)), line((1.0, 0.0), (1.0, 0.0))),
+        11 => average(line((1.0, 0.0), (1.0, 1.0)), line((1.0, 0.0), (1.0, 0.0))),
+        12 => line((1.0, 1.0), (1.0, 1.0)),
+        13 => average(line((1.0, 1.0), (1.0, 1.0)), line((1.0, 0.0), (1.0, 1.0))),
+        _ => average(line((1.0, 1.0), (1.0, 1.0)), line((1.0, 1.0), (1.0, 0.0))),
+    }
+}
+
+/// Contents of the R8Unorm search texture.
+///
+/// The shaders fetch the edges of the last two pixels of each search with a single bilinear
+/// sample, and look up in this texture how far to move the end of the search based on it.
+pub fn search_texture() -> Vec<u8> {
+    // Decode the edges of the four pixels in the bilinear footprint from the fetched value.
+    let edges = |value: f64| {
+        (0..16u8)
+            .map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1, (i >> 3) & 1])
+            .find(|e| {
+                let a = lerp(e[0] as f64, e[1] as f64, 0.75);
+                let b = lerp(e[2] as f64, e[3] as f64, 0.75);
+                lerp(a, b, 0.875) == value
+            })
+    };
+
+    // The left and right searches each take half of the texture, which only contains the upper
+    // half of the possible vertical values, flipped.
+    let width = SEARCHTEX_WIDTH as usize;
+    let size = (width / 2 + 1) as u32;
+    let mut bytes = vec![0; width * SEARCHTEX_HEIGHT as usize];
+    for row in 0..SEARCHTEX_HEIGHT {
+        let top = edges((size - 1 - row) as f64 / (size - 1) as f64);
+        for column in 0..width as u32 {
+            let x = if column < size { column } else { column - size };
+            if let (Some(left), Some(top)) = (edges(x as f64 / (size - 1) as f64), top) {
+                let delta = if column < size {
+                    delta_left(left, top)
+                } else {
+                    delta_right(left, top)
+                };
+                bytes[row as usize * width + column as usize] = 127 * delta;
+            }
+        }
+    }
+    bytes
+}
+
+fn lerp(a: f64, b: f64, p: f64) -> f64 {
+    a + (b - a) * p
+}
+
+/// Distance to add to the end of a search to the left.
+fn delta_left(left: [u8; 4], top: [u8; 4]) -> u8 {
+    let mut d = 0;
+    // If there is an edge, continue.
+    if top[3] == 1 {
+        d += 1;
+    }
+    // If an edge was found before, and there is another one without crossing edges, continue.
+    if d == 1 && top[2] == 1 && left[1] != 1 && left[3] != 1 {
+        d += 1;
+    }
+    d
+}
+
+/// Distance to add to the end of a search to the right.
+fn delta_right(left: [u8; 4], top: [u8; 4]) -> u8 {
+    let mut d = 0;
+    // If there is an edge without crossing edges, continue.
+    if top[3] == 1 && left[1] != 1 && left[3] != 1 {
+        d += 1;
+    }
+    // If an edge was found before, and there is another one without crossing edges, continue.
+    if d == 1 && top[2] == 1 && left[0] != 1 && left[2] != 1 {
+        d += 1;
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::{area_tex::AREATEX_BYTES, search_tex::SEARCHTEX_BYTES};
+
+    #[test]
+    fn generated_textures_match_embedded() {
+        assert!(area_texture(SMOOTH_MAX_DISTANCE) == AREATEX_BYTES);
+        assert!(search_texture() == SEARCHTEX_BYTES);
+    }
+}
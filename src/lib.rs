@@ -69,18 +69,58 @@
 
 #![deny(missing_docs)]
 
+mod cmaa2;
+mod compute;
+#[cfg(feature = "egui")]
+pub mod egui_viewport;
+mod error;
+mod exposure;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+mod generate;
+mod lookup;
+mod metrics;
+mod non_finite;
+mod offscreen;
+mod post_chain;
+mod post_process;
+mod readback;
+#[cfg(feature = "test-utils")]
+mod reference;
+mod renderer;
+mod resolver;
 mod shader;
-use shader::{ShaderQuality, ShaderSource, ShaderStage};
+mod shared;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+mod timing;
+mod upscale;
+mod video;
+#[cfg(feature = "winit")]
+pub mod winit_surface;
+pub use error::SmaaError;
+use lookup::{AREATEX_HEIGHT, AREATEX_WIDTH, SEARCHTEX_HEIGHT, SEARCHTEX_WIDTH};
+pub use metrics::{SmaaImageMetrics, SmaaMetrics, SmaaQualityReport, SmaaReportEntry};
+use non_finite::NonFiniteScan;
+#[cfg(not(target_arch = "wasm32"))]
+pub use offscreen::OffscreenSmaa;
+pub use post_chain::{
+    PostAutoExposure, PostChain, PostChainConfig, PostChainFrame, PostTonemap, PostUpscale,
+};
+pub use post_process::PostProcess;
+pub use readback::SmaaReadback;
+pub use renderer::{SmaaCrop, SmaaRenderer, SmaaRendererViews};
+pub use resolver::AaResolver;
+use shader::{ColorTransform, ShaderQuality, ShaderSource, ShaderStage, TILE_SIZE};
+pub use shared::{SmaaOwnedFrame, SmaaSharedTarget};
+use timing::{AdaptiveQuality, PassTimer};
+pub use video::{antialias_rgba8, VideoSmaa};
 
-#[path = "../third_party/smaa/Textures/AreaTex.rs"]
-mod area_tex;
-use area_tex::*;
-
-#[path = "../third_party/smaa/Textures/SearchTex.rs"]
-mod search_tex;
-use search_tex::*;
-
-use wgpu::util::DeviceExt;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+use wgpu::util::{DeviceExt, RenderEncoder};
 
 /// Anti-aliasing mode. Higher values produce nicer results but run slower.
 #[non_exhaustive]
@@ -90,69 +130,1387 @@ pub enum SmaaMode {
     Disabled,
     /// Use SMAA 1x.
     Smaa1X,
+    /// Use Conservative Morphological Anti-Aliasing 2.0 (CMAA2) instead of SMAA.
+    ///
+    /// CMAA2 runs as compute dispatches that only process the pixels along edges, which makes
+    /// it noticeably cheaper than SMAA at similar quality on desktop GPUs. It is conservative
+    /// about which shapes it smooths, so thin lines and text stay sharper, but long edges at
+    /// shallow angles get less of a gradient.
+    ///
+    /// Requires an adapter with [`wgpu::DownlevelFlags::COMPUTE_SHADERS`] and
+    /// [`wgpu::DownlevelFlags::INDIRECT_EXECUTION`]. Like with [`SmaaBackend::Compute`], the
+    /// output must be created with [`wgpu::TextureUsages::STORAGE_BINDING`] and use one of the
+    /// `Rgba8Unorm`, `Rgba8Snorm`, `Rgba16Float` or `Rgba32Float` formats. Of the settings in
+    /// [`SmaaConfig`], only `color_format`, `preset`, `time_budget`, `profile` and `resources` have
+    /// an effect, and `background_depth` isn't supported.
+    Cmaa2,
+    /// Render the scene at twice the width and height of the target, antialias it there with
+    /// SMAA 1x, and downsample the result to the output with [`SmaaUpscaleFilter::Area`].
+    ///
+    /// Supersampling resolves the geometric and shading detail that SMAA alone can't, like
+    /// subpixel triangles and specular aliasing, while SMAA smooths the edges that 4 samples
+    /// per pixel leave stepped. It costs four times the raster work and memory of
+    /// [`SmaaMode::Smaa1X`]. The scene must be rendered at [`SmaaTarget::render_size`], and the
+    /// downsample blends in linear space for all formats, like [`SmaaUpscaleFilter::Area`] does.
+    ///
+    /// Only supported by [`SmaaBackend::Render`], and sets [`SmaaConfig::output_size`] and
+    /// [`SmaaConfig::upscale_filter`] itself, so it panics with `output_size` or
+    /// `dynamic_resolution` set.
+    Ssaa2x,
+}
+
+/// Color space that the final neighborhood blending pass mixes pixels in.
+///
+/// SMAA produces the best results when blending happens in linear space. For sRGB formats the
+/// hardware decodes colors when they are read, and floating point formats are assumed to hold
+/// linear colors, so this is what happens by default. Other formats are blended using the stored
+/// values as-is, which for sRGB encoded data means blending in gamma space.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaBlendSpace {
+    /// Blend in linear space for sRGB and floating point formats, and on the stored values
+    /// otherwise.
+    #[default]
+    Auto,
+    /// Always blend in linear space, treating colors stored in other formats as sRGB encoded.
+    Linear,
+    /// Always blend sRGB encoded colors directly, encoding linear colors first if needed.
+    Gamma,
+}
+
+/// Conversion applied to colors before the edge detection pass computes their luma.
+///
+/// SMAA's thresholds assume perceptual (gamma encoded) values. Reading from sRGB formats or
+/// rendering linear data into other formats will cause edges in dark regions to be missed unless
+/// the colors are gamma encoded first.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaEdgeConversion {
+    /// Use the colors as they are read from the target.
+    #[default]
+    None,
+    /// Decode sRGB encoded colors into linear space.
+    Linearize,
+    /// Encode linear colors into sRGB (gamma) space.
+    GammaEncode,
+}
+
+/// Reversible tonemapping operator applied to colors while they are being antialiased.
+///
+/// Very bright pixels in HDR targets dominate SMAA's thresholds and blending. Compressing the
+/// range before edge detection and undoing it after blending avoids this, which is important when
+/// SMAA runs before the application's own tonemapping.
+///
+/// Using the same curve as the application's later tonemapping makes SMAA see the colors the way
+/// they will be displayed, so edges in bright regions are detected like they would be after it.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaPreTonemap {
+    /// Operate on the colors directly.
+    #[default]
+    None,
+    /// Apply `c / (1 + max(r, g, b))` before antialiasing and invert it afterwards.
+    Karis,
+    /// Apply `c / (1 + c)` to each channel before antialiasing and invert it afterwards.
+    Reinhard,
+    /// Apply Krzysztof Narkowicz's fit of the ACES filmic curve to each channel before
+    /// antialiasing and invert it afterwards. Unlike a display tonemap the curve isn't clamped
+    /// to 1, so that very bright colors survive the round trip.
+    Aces,
+    /// Apply a curve supplied by the application and its inverse.
+    ///
+    /// Both are bodies of GLSL functions taking a linear `vec3 c` and returning a `vec3`, like
+    /// `"return c / (1.0 + c);"` and `"return c / max(1.0 - c, 1e-5);"`. Negative values are
+    /// passed in for extended range colors. Creating the target panics if they don't compile.
+    Custom {
+        /// Body of the function applied before edge detection.
+        tonemap: &'static str,
+        /// Body of the function applied after blending, which has to undo `tonemap`.
+        inverse: &'static str,
+    },
+}
+
+/// What the edge detection pass looks for differences in.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SmaaEdgeDetection {
+    /// Differences in the luma of the colors.
+    #[default]
+    Luma,
+    /// Differences in the alpha channel, for surfaces with transparent backgrounds like
+    /// rendered UI or overlays, whose important edges are the boundaries of their coverage
+    /// rather than contrasts in color.
+    Alpha,
+    /// The larger of the differences in luma and in alpha, which also finds the edges between
+    /// opaque shapes of the same transparent surface.
+    LumaAndAlpha,
+    /// Differences in the depth of the scene, read from the depth buffer that frames are started
+    /// with through [`SmaaTarget::start_frame_with_depth`].
+    ///
+    /// This finds the silhouettes of objects however they are shaded, but not edges within a
+    /// surface like those of textures or shadows. Depth is linearized with the projection first,
+    /// so that distant edges are found as reliably as nearby ones: neighbors are separated by an
+    /// edge where their distances differ by more than a tenth of the [`SmaaConfig::preset`]'s
+    /// threshold relative to the nearer one, 1% with [`SmaaPreset::High`], or by more than a
+    /// fixed distance with [`SmaaDepthProjection::Orthographic`]. Only supported by
+    /// [`SmaaBackend::Render`]; the other backends and [`SmaaMode::Cmaa2`] panic if it is set,
+    /// as do [`SmaaTarget::process_batch`] and the other methods processing existing images.
+    Depth(SmaaDepthProjection),
+}
+
+/// Projection that the scene's depth buffer was rendered with, used to linearize it for
+/// [`SmaaEdgeDetection::Depth`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SmaaDepthProjection {
+    /// Perspective projection storing a depth of 0 at the near plane and 1 at the far plane, or
+    /// the other way around with `reversed_z`. Creating the target panics unless
+    /// `0 < near < far`.
+    Perspective {
+        /// Distance of the near plane.
+        near: f32,
+        /// Distance of the far plane, which can be [`f32::INFINITY`] for projections with an
+        /// infinite far plane.
+        far: f32,
+        /// Whether the near plane is at a depth of 1 and the far plane at 0.
+        reversed_z: bool,
+    },
+    /// Orthographic projection storing a depth of 0 at the near plane and 1 at the far plane, or
+    /// the other way around with `reversed_z`, like those of CAD applications and 2.5D games.
+    ///
+    /// Depth is then linear in the distance, and a relative threshold would find no edges
+    /// between distant surfaces while treating everything near the camera plane as edges, so
+    /// neighbors are instead separated by an edge where their distances differ by more than
+    /// `threshold`. Creating the target panics unless `near < far`, both are finite and
+    /// `threshold` is finite and positive.
+    Orthographic {
+        /// Distance of the near plane, which can be negative.
+        near: f32,
+        /// Distance of the far plane.
+        far: f32,
+        /// Whether the near plane is at a depth of 1 and the far plane at 0.
+        reversed_z: bool,
+        /// Difference in distance between neighbors that is considered an edge, in the units of
+        /// `near` and `far`.
+        threshold: f32,
+    },
+}
+
+/// Encoding applied to antialiased colors as the final pass writes them to the output.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SmaaOutputEncoding {
+    /// Write colors without any additional encoding.
+    #[default]
+    None,
+    /// Convert linear Rec.709 colors to Rec.2020 primaries and apply the SMPTE ST 2084 (PQ)
+    /// transfer function, as expected by HDR10 outputs such as an `Rgb10a2Unorm` swapchain.
+    Hdr10 {
+        /// Luminance in nits that a color value of 1.0 corresponds to. Creating the target
+        /// panics unless it is finite and positive.
+        paper_white_nits: f32,
+        /// Peak luminance of the display in nits. Brighter colors are clamped. Creating the
+        /// target panics unless it is finite and positive.
+        peak_nits: f32,
+    },
+    /// Scale linear colors for an extended range `Rgba16Float` (scRGB) output, where 1.0
+    /// corresponds to 80 nits. Negative and out of range values are passed through.
+    ScRgb {
+        /// Luminance in nits that a color value of 1.0 corresponds to. Creating the target
+        /// panics unless it is finite and positive.
+        paper_white_nits: f32,
+    },
+}
+
+/// Data that the final pass writes to a second render target next to the output, see
+/// [`SmaaConfig::secondary_output`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaSecondaryOutput {
+    /// Only write the output.
+    #[default]
+    None,
+    /// The largest blend weight of each pixel, which is 0 for pixels that were left untouched
+    /// and grows with how much of a pixel's color was blended from its neighbors.
+    EdgeMask,
+    /// The luma of the antialiased colors as written to the output, clamped to `[0, 1]`.
+    Luma,
+}
+
+/// What the final pass writes instead of the antialiased frame, for inspecting the effect of
+/// the settings.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SmaaDebugOutput {
+    /// Write the antialiased frame.
+    #[default]
+    None,
+    /// Write how much antialiasing changed each pixel, `abs(output - input)` for every color
+    /// channel with an alpha of 1, where the input is what the output would have been without
+    /// antialiasing. Pixels that were left untouched are black, which makes over-blurring easy
+    /// to spot while tuning the settings.
+    Difference {
+        /// Factor that the differences are multiplied by to make small changes visible. Creating
+        /// the target panics unless it is finite and positive.
+        amplification: f32,
+    },
+}
+
+/// Filter that the final pass scales the antialiased image to the output with, see
+/// [`SmaaConfig::output_size`] and [`SmaaConfig::dynamic_resolution`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaUpscaleFilter {
+    /// Interpolate linearly between the four nearest pixels.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom interpolation between the sixteen nearest pixels, which keeps edges and
+    /// detail sharper at the cost of slight halos around high contrast edges.
+    Bicubic,
+    /// Average of all pixels that each output pixel covers, weighted by how much of them it
+    /// covers, for outputs smaller than the target.
+    ///
+    /// Unlike the interpolating filters, this takes every pixel into account however much the
+    /// image is shrunk, and colors stored with the sRGB curve in formats that don't decode it
+    /// are averaged in linear light. This suits UI applications on displays with fractional
+    /// scaling, which can create the target at the physical size of the window, for instance
+    /// `1.5` times the logical size, and set [`SmaaConfig::output_size`] to the logical size to
+    /// antialias at the higher resolution and resolve straight into the smaller output.
+    Area,
+}
+
+/// Rotation that the final pass applies to frames, for surfaces that expect their content to be
+/// pre-rotated to the orientation of the display. The rotations match the corresponding
+/// `VkSurfaceTransformFlagBitsKHR`, which Android reports as the surface's current transform.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaOutputTransform {
+    /// Write frames to the output as they are.
+    #[default]
+    Identity,
+    /// Rotate frames 90 degrees clockwise, swapping their width and height.
+    Rotate90,
+    /// Rotate frames 180 degrees.
+    Rotate180,
+    /// Rotate frames 270 degrees clockwise, swapping their width and height.
+    Rotate270,
+}
+
+/// Field of interlaced frames, made of every other row of the output, that a frame antialiases
+/// with [`SmaaConfig::interlaced`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaField {
+    /// The even rows, starting with the first row of the output.
+    #[default]
+    Top,
+    /// The odd rows.
+    Bottom,
+}
+
+/// Pass of SMAA after which the hook set with [`SmaaTarget::set_stage_hook`] is called.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SmaaStage {
+    /// Edge detection wrote [`SmaaTarget::edges_view`].
+    EdgeDetection,
+    /// The blend weight calculation wrote [`SmaaTarget::blend_weights_view`].
+    BlendWeights,
+    /// Neighborhood blending wrote the frame to the outputs.
+    NeighborhoodBlending,
+}
+
+/// Callback recording work of the application between the passes of a frame.
+type StageHook = dyn FnMut(&mut wgpu::CommandEncoder, SmaaStage) + Send + Sync;
+
+/// What happens to a [`SmaaFrame`] that is dropped without calling [`SmaaFrame::resolve`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaDropPolicy {
+    /// Resolve the frame into the output, as if `resolve` had been called.
+    #[default]
+    Resolve,
+    /// Panic in debug builds, to catch frames that are dropped before the commands rendering
+    /// the scene were submitted. Release builds resolve the frame.
+    DebugAssert,
+    /// Log a warning and leave the output untouched.
+    Skip,
+}
+
+/// Precision of the intermediate edges and blend weight targets.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaPrecision {
+    /// Store edges in `Rg8Unorm` and blend weights in `Rgba8Unorm`.
+    #[default]
+    Unorm8,
+    /// Store edges in `Rg16Float` and blend weights in `Rgba16Float`. This avoids visibly
+    /// quantized gradients along near-horizontal and near-vertical edges at the cost of
+    /// additional memory bandwidth.
+    Float16,
+}
+impl SmaaPrecision {
+    /// Format of the intermediate targets when they're written as storage textures, which
+    /// don't support two channel 8-bit and 16-bit formats.
+    fn storage_format(self) -> wgpu::TextureFormat {
+        match self {
+            SmaaPrecision::Unorm8 => wgpu::TextureFormat::Rgba8Unorm,
+            SmaaPrecision::Float16 => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+    /// Whether the adapter can render to the two channel format that edges are stored in at
+    /// this precision. Some GL and WebGL targets can't, and need [`SmaaConfig::rgba_edges`].
+    pub fn edges_renderable(self, adapter: &wgpu::Adapter) -> bool {
+        adapter
+            .get_texture_format_features(self.edges_format())
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    }
+    fn edges_format(self) -> wgpu::TextureFormat {
+        match self {
+            SmaaPrecision::Unorm8 => wgpu::TextureFormat::Rg8Unorm,
+            SmaaPrecision::Float16 => wgpu::TextureFormat::Rg16Float,
+        }
+    }
+    fn blend_format(self) -> wgpu::TextureFormat {
+        match self {
+            SmaaPrecision::Unorm8 => wgpu::TextureFormat::Rgba8Unorm,
+            SmaaPrecision::Float16 => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+/// Formats of the edges, blend weight and luma targets of the render backend.
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct IntermediateFormats {
+    edges: wgpu::TextureFormat,
+    blend: wgpu::TextureFormat,
+    // Written by the edge detection pass next to the edges, if `SmaaConfig::luma_output` is set.
+    luma: Option<wgpu::TextureFormat>,
+    // Of the view written by the neighborhood blending pass next to the output, if
+    // `SmaaConfig::secondary_output` is set.
+    secondary: Option<wgpu::TextureFormat>,
+    // Samples of the outputs that the neighborhood blending pass renders to.
+    output_samples: u32,
+}
+impl IntermediateFormats {
+    fn new(config: &SmaaConfig) -> Self {
+        let blend = config.intermediate_precision().blend_format();
+        let edges = match config.rgba_edges {
+            true => blend,
+            false => config.intermediate_precision().edges_format(),
+        };
+        Self {
+            edges,
+            blend,
+            luma: config.luma_output.then_some(wgpu::TextureFormat::R8Unorm),
+            secondary: (config.secondary_output != SmaaSecondaryOutput::None)
+                .then_some(SECONDARY_FORMAT),
+            output_samples: config.output_sample_count.max(1),
+        }
+    }
+}
+
+/// Quality preset of the SMAA shaders, which trades the edge detection threshold and search
+/// distances off against speed.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SmaaPreset {
+    /// Threshold of 0.15 with 4 search steps, without diagonal or corner detection.
+    Low,
+    /// Threshold of 0.1 with 8 search steps, without diagonal or corner detection.
+    Medium,
+    /// Threshold of 0.1 with 16 search steps and 8 diagonal search steps, with corner detection.
+    #[default]
+    High,
+    /// Threshold of 0.05 with 32 search steps and 16 diagonal search steps, with corner
+    /// detection.
+    Ultra,
+}
+impl SmaaPreset {
+    /// A preset suited to the given adapter: `Ultra` on discrete GPUs, `Medium` on integrated
+    /// and virtual ones, which includes most mobile GPUs, and `Low` when rendering on the CPU.
+    /// Discrete GPUs only get `High` through OpenGL, which is usually an older driver path.
+    pub fn for_adapter(info: &wgpu::AdapterInfo) -> Self {
+        match (info.device_type, info.backend) {
+            (wgpu::DeviceType::DiscreteGpu, wgpu::Backend::Gl) => SmaaPreset::High,
+            (wgpu::DeviceType::DiscreteGpu, _) => SmaaPreset::Ultra,
+            (wgpu::DeviceType::Cpu, _) => SmaaPreset::Low,
+            (
+                wgpu::DeviceType::IntegratedGpu
+                | wgpu::DeviceType::VirtualGpu
+                | wgpu::DeviceType::Other,
+                _,
+            ) => SmaaPreset::Medium,
+        }
+    }
+
+    fn lower(self) -> Option<Self> {
+        match self {
+            SmaaPreset::Low => None,
+            SmaaPreset::Medium => Some(SmaaPreset::Low),
+            SmaaPreset::High => Some(SmaaPreset::Medium),
+            SmaaPreset::Ultra => Some(SmaaPreset::High),
+        }
+    }
+    fn higher(self) -> Option<Self> {
+        match self {
+            SmaaPreset::Low => Some(SmaaPreset::Medium),
+            SmaaPreset::Medium => Some(SmaaPreset::High),
+            SmaaPreset::High => Some(SmaaPreset::Ultra),
+            SmaaPreset::Ultra => None,
+        }
+    }
+}
+
+/// NaN and infinite values found in the color target of a frame, as detected with
+/// [`SmaaConfig::detect_non_finite`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SmaaNonFinitePixels {
+    /// Number of pixels with a NaN in any channel.
+    pub nan: u32,
+    /// Number of pixels with an infinite value in any channel.
+    pub infinite: u32,
+    /// Coordinates of the first pixel in row-major order holding either, if there are any.
+    pub first: Option<(u32, u32)>,
+}
+
+/// GPU time taken by the SMAA passes of a frame, as measured with [`SmaaConfig::profile`].
+///
+/// Each pass is timed from the end of the previous one, so small passes in between, like the
+/// ones for [`SmaaConfig::tile_classification`], count towards the following pass.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SmaaPassTimes {
+    /// Time taken by edge detection.
+    pub edge_detection: Duration,
+    /// Time taken by the blend weight calculation. Close to zero with
+    /// [`SmaaBackend::ComputeFused`], which calculates the weights during neighborhood blending.
+    pub blend_weights: Duration,
+    /// Time taken by neighborhood blending.
+    pub neighborhood_blending: Duration,
+}
+impl SmaaPassTimes {
+    /// Time taken by all passes together.
+    pub fn total(&self) -> Duration {
+        self.edge_detection + self.blend_weights + self.neighborhood_blending
+    }
+}
+
+/// Statistics of the GPU time taken by one pass, or all passes together, over recent frames.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SmaaPassStats {
+    /// Average time.
+    pub average: Duration,
+    /// Shortest time.
+    pub min: Duration,
+    /// Longest time.
+    pub max: Duration,
+}
+
+/// Statistics of the GPU time taken by the SMAA passes over the most recently measured frames,
+/// as returned by [`SmaaTarget::stats`].
+///
+/// The `Display` implementation gives a short summary like `SMAA: 0.42 ms`, followed by the
+/// average time of each pass, for showing in a debug overlay.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SmaaStats {
+    /// Statistics of the edge detection pass.
+    pub edge_detection: SmaaPassStats,
+    /// Statistics of the blend weight calculation.
+    pub blend_weights: SmaaPassStats,
+    /// Statistics of the neighborhood blending pass.
+    pub neighborhood_blending: SmaaPassStats,
+    /// Statistics of the time taken by all passes together.
+    pub total: SmaaPassStats,
+    /// Number of frames that the statistics were calculated over.
+    pub frames: usize,
+}
+impl std::fmt::Display for SmaaStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "SMAA: {:.2} ms (edges {:.2} ms, weights {:.2} ms, blending {:.2} ms)",
+            ms(self.total.average),
+            ms(self.edge_detection.average),
+            ms(self.blend_weights.average),
+            ms(self.neighborhood_blending.average),
+        )
+    }
+}
+
+/// How the SMAA passes are executed.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SmaaBackend {
+    /// Run each pass as a render pass drawing a fullscreen triangle.
+    #[default]
+    Render,
+    /// Run each pass as a compute dispatch that writes to storage textures.
+    ///
+    /// This avoids render pass overhead and doesn't need the intermediate formats to be
+    /// renderable, but the output texture must be created with
+    /// [`wgpu::TextureUsages::STORAGE_BINDING`] and use one of the `Rgba8Unorm`, `Rgba8Snorm`,
+    /// `Rgba16Float` or `Rgba32Float` formats. [`SmaaConfig::tile_classification`] has no effect.
+    Compute,
+    /// Like [`SmaaBackend::Compute`], but calculate the blend weights for each 8x8 block of
+    /// pixels in workgroup memory and blend the neighborhoods in the same dispatch.
+    ///
+    /// This eliminates the intermediate blend weight texture and the memory bandwidth spent on
+    /// it, which matters most on mobile GPUs, at the cost of recalculating the weights along the
+    /// right and bottom border of each block. Since the weights are never rounded to the
+    /// [`SmaaPrecision`] format, the output can differ very slightly from the other backends.
+    ComputeFused,
+}
+
+/// Additional settings for constructing a [`SmaaTarget`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SmaaConfig {
+    /// Color space that the final pass blends pixels in.
+    pub blend_space: SmaaBlendSpace,
+    /// Conversion applied to colors before edge detection.
+    pub edge_conversion: SmaaEdgeConversion,
+    /// Reversible tonemap applied while antialiasing HDR colors.
+    pub pre_tonemap: SmaaPreTonemap,
+    /// What edges are detected in.
+    pub edge_detection: SmaaEdgeDetection,
+    /// Whether the colors of the scene have premultiplied alpha.
+    ///
+    /// Blending mixes the channels of neighboring pixels independently, which keeps premultiplied
+    /// colors premultiplied. The conversions into and out of the blend space, like decoding sRGB
+    /// or [`SmaaConfig::pre_tonemap`], aren't linear though, so applying them to premultiplied
+    /// colors darkens partially transparent pixels, which shows up as dark fringes when the
+    /// output is composited over other layers. With this set, they are applied to the
+    /// unpremultiplied colors instead, and the output is premultiplied again.
+    pub premultiplied_alpha: bool,
+    /// Alpha mode of the surface that the output is presented to, which decides the alpha the
+    /// final pass writes.
+    ///
+    /// With [`wgpu::CompositeAlphaMode::Opaque`] alpha is written as 1, with
+    /// [`wgpu::CompositeAlphaMode::PreMultiplied`] colors are written premultiplied and with
+    /// [`wgpu::CompositeAlphaMode::PostMultiplied`] they are written with straight alpha,
+    /// converting from the scene's alpha as given by [`SmaaConfig::premultiplied_alpha`]. Colors
+    /// with straight alpha are then also blended premultiplied, so that the colors of transparent
+    /// pixels don't bleed into their neighbors. This lets transparent windows composite
+    /// correctly. The default of
+    /// [`wgpu::CompositeAlphaMode::Auto`], like [`wgpu::CompositeAlphaMode::Inherit`], writes
+    /// alpha the way the scene had it.
+    pub composite_alpha_mode: wgpu::CompositeAlphaMode,
+    /// Blend state of the final pass, which replaces the output's contents by default.
+    ///
+    /// With a blend state the output is loaded instead of cleared, so that the antialiased frame
+    /// can be alpha blended or added onto what is already there, like one layer of several drawn
+    /// into a surface. The output format must then be blendable. Only supported by
+    /// [`SmaaBackend::Render`] with outputs that are rendered to directly, so not together with
+    /// [`SmaaConfig::dynamic_resolution`], [`SmaaConfig::output_size`] or
+    /// [`SmaaConfig::output_transform`].
+    pub output_blend_state: Option<wgpu::BlendState>,
+    /// Write debugging information instead of the antialiased frame. Only supported by
+    /// [`SmaaBackend::Render`].
+    pub debug_output: SmaaDebugOutput,
+    /// Encoding applied to colors written to the output.
+    pub output_encoding: SmaaOutputEncoding,
+    /// Format of the texture that the scene is rendered into, if it should differ from the
+    /// output format. For instance, an HDR scene can be rendered into `Rgba16Float` and written
+    /// to an `Rgb10a2Unorm` output with [`SmaaOutputEncoding::Hdr10`].
+    ///
+    /// Single-channel formats like `R8Unorm` suit documents and masks: edges are detected in the
+    /// channel itself rather than in a luma that would only weight it by 0.2126, and outputs with
+    /// more channels get it as gray.
+    pub color_format: Option<wgpu::TextureFormat>,
+    /// Precision of the intermediate targets.
+    pub precision: SmaaPrecision,
+    /// Store edges in a four channel texture with the format of the blend weights, for GL and
+    /// WebGL targets that can't render to the two channel format of [`SmaaConfig::precision`].
+    /// [`SmaaTarget::new_auto`] sets this if [`SmaaPrecision::edges_renderable`] is false for
+    /// the adapter. Has no effect with [`SmaaBackend::Compute`] or
+    /// [`SmaaBackend::ComputeFused`], which always use four channel formats.
+    pub rgba_edges: bool,
+    /// Also write the luma that edge detection calculates for every pixel to an `R8Unorm`
+    /// texture, available through [`SmaaTarget::luma_view`] for other passes like bloom
+    /// thresholds to reuse.
+    ///
+    /// The luma is that of the colors edge detection sees, after
+    /// [`SmaaConfig::edge_conversion`] and [`SmaaConfig::pre_tonemap`], clamped to `[0, 1]`.
+    /// Since every pixel is then written, the blend weight pass can no longer skip the pixels
+    /// without edges through the stencil buffer. Only supported by [`SmaaBackend::Render`]; the
+    /// other backends and [`SmaaMode::Cmaa2`] panic if it is set.
+    pub luma_output: bool,
+    /// Detect edges in a luma texture that the application supplies with every frame through
+    /// [`SmaaFrame::set_luma_input`], instead of in the colors of the scene.
+    ///
+    /// This saves converting the colors when the application already has a luma or lightness
+    /// buffer, and lets edges be detected in a perceptual space of its choice, like the L of
+    /// OKLab. The red channel is used as is: [`SmaaConfig::edge_conversion`] and
+    /// [`SmaaConfig::pre_tonemap`] no longer apply to edge detection, and the thresholds of the
+    /// [`SmaaConfig::preset`] assume values between 0 and 1. [`SmaaConfig::edge_detection`] must
+    /// be [`SmaaEdgeDetection::Luma`]. Only supported by
+    /// [`SmaaBackend::Render`]; the other backends and [`SmaaMode::Cmaa2`] panic if it is set,
+    /// as do [`SmaaTarget::process_batch`] and the other methods processing existing images.
+    pub luma_input: bool,
+    /// Also write data like an edge mask from the final pass, through a second render target
+    /// that frames are given with [`SmaaFrame::set_secondary_output`], or a [`SmaaRenderer`]
+    /// with [`SmaaRendererViews::secondary`].
+    ///
+    /// This saves downstream passes a full screen pass of their own. Only supported by
+    /// [`SmaaBackend::Render`] with outputs that are rendered to directly, and not by
+    /// [`SmaaTarget::process_batch`] and the other methods processing existing images.
+    pub secondary_output: SmaaSecondaryOutput,
+    /// Number of samples of the output views, for pipelines whose swapchain path is
+    /// multisampled for other reasons, like UI drawn with MSAA after the frame.
+    ///
+    /// The final pass then writes every sample of the outputs, and resolves the first output
+    /// into the view given with [`SmaaFrame::set_resolve_target`], if any. The output format
+    /// must support multisampling with this many samples. Values of 0 and 1 write outputs that
+    /// aren't multisampled. When antialiasing is disabled, the scene is rendered straight into
+    /// the output. Only supported by [`SmaaBackend::Render`] with outputs that are rendered to
+    /// directly and without [`SmaaConfig::secondary_output`], and not by [`SmaaRenderer`],
+    /// [`SmaaTarget::process_batch`] and the other methods processing existing images.
+    pub output_sample_count: u32,
+    /// Skip the blend weight calculation for 16x16 pixel tiles that contain no edges.
+    ///
+    /// A compute pass finds the tiles with edges after edge detection and the blend weight pass
+    /// is then drawn indirectly over just those tiles. This greatly reduces the cost for content
+    /// that is mostly smooth, like sky or UI, but requires an adapter with
+    /// [`wgpu::DownlevelFlags::COMPUTE_SHADERS`] and [`wgpu::DownlevelFlags::INDIRECT_EXECUTION`].
+    /// The target is created without it on devices that lack compute shaders or storage buffers.
+    pub tile_classification: bool,
+    /// Skip the blend weight and neighborhood blending passes for frames without any edges,
+    /// copying the colors to the output instead.
+    ///
+    /// A compute pass checks for edges after edge detection and the remaining passes are drawn
+    /// indirectly, so the decision is made without waiting on the GPU. This makes frames of
+    /// flat content nearly free, but requires an adapter with
+    /// [`wgpu::DownlevelFlags::COMPUTE_SHADERS`] and [`wgpu::DownlevelFlags::INDIRECT_EXECUTION`],
+    /// and is disabled on devices that lack compute shaders or storage buffers. Has no effect
+    /// with [`SmaaBackend::Compute`] or [`SmaaBackend::ComputeFused`].
+    pub skip_without_edges: bool,
+    /// Whether the passes run as render passes or compute dispatches.
+    pub backend: SmaaBackend,
+    /// Only detect edges and calculate blend weights every `frame_interval` frames, reusing the
+    /// previous blend weights for the frames in between.
+    ///
+    /// This is a power saving mode for battery constrained devices showing mostly static
+    /// content, since the remaining neighborhood blending pass costs about as much as a copy.
+    /// The frames in between are still blended from the new scene rather than copied from the
+    /// last output, so where content moves the stale weights blend across pixels that are no
+    /// longer edges, smearing them, while the new edges stay aliased until the next update.
+    /// Values of 0 and 1 update the blend weights every frame.
+    pub frame_interval: u32,
+    /// Depth that the scene leaves background pixels like the sky at, usually `1.0` or `0.0`
+    /// with reversed Z.
+    ///
+    /// When set, frames must be started with [`SmaaTarget::start_frame_with_depth`] and edge
+    /// detection is skipped for pixels whose depth and that of their left and top neighbors is
+    /// exactly this value. Those pixels then also skip the blend weight calculation.
+    pub background_depth: Option<f32>,
+    /// Quality preset of the shaders, or the initial one when using
+    /// [`SmaaConfig::time_budget`].
+    pub preset: SmaaPreset,
+    /// GPU time that antialiasing each frame should stay within.
+    ///
+    /// When set, the passes are timed and the preset is lowered while their average time
+    /// exceeds the budget, then raised again once it drops well below it. Timing requires the
+    /// device to have [`wgpu::Features::TIMESTAMP_QUERY`] enabled, otherwise the preset stays
+    /// fixed. Use [`SmaaTarget::preset`] to find out which preset is currently in use.
+    pub time_budget: Option<Duration>,
+    /// Time the passes on the GPU, making the measurements available through
+    /// [`SmaaTarget::pass_times`] and [`SmaaTarget::stats`].
+    ///
+    /// Requires the device to have [`wgpu::Features::TIMESTAMP_QUERY`] enabled, otherwise
+    /// nothing is measured. Only frames that run every pass are timed, and while one
+    /// measurement is being read back the following frames aren't timed.
+    pub profile: bool,
+    /// Scan the color target for NaN and infinite values before antialiasing each frame,
+    /// making the results available through [`SmaaTarget::non_finite_pixels`] and logging a
+    /// warning when any are found.
+    ///
+    /// SMAA's filtering spreads such values into large black areas of the output, which makes
+    /// them hard to attribute to the pass that actually wrote them. Only float color formats
+    /// can hold them. Requires an adapter with [`wgpu::DownlevelFlags::COMPUTE_SHADERS`]. The
+    /// results are read back asynchronously, and while one scan is being read back the
+    /// following frames aren't scanned.
+    pub detect_non_finite: bool,
+    /// Lookup textures and samplers to use instead of creating new ones. Must have been created
+    /// on the same device as the target. If the `embedded-textures` feature is disabled and this
+    /// isn't set, the lookup textures are generated with [`SmaaResources::generate`].
+    pub resources: Option<SmaaResources>,
+    /// How much larger than the target the intermediate textures may stay when it is resized to
+    /// a smaller size, as a fraction of the new size.
+    ///
+    /// For instance `0.5` keeps them until the width or height drops below two thirds of the size
+    /// they were created for, and `f32::INFINITY` only ever grows them. This avoids reallocating
+    /// them while a window is being resized. The color target that the scene is rendered into is
+    /// always reallocated, and [`SmaaBackend::ComputeFused`] ignores this setting. The default of
+    /// `0.0` reallocates them on every resize.
+    pub resize_slack: f32,
+    /// Alternate between two textures for the scene to be rendered into, so that rendering the
+    /// next frame doesn't have to wait for the passes reading the previous one.
+    ///
+    /// wgpu only provides a single queue, but without the dependency between the frames the GPU
+    /// can overlap the antialiasing of one frame with the raster work of the next. In exchange
+    /// the texture returned by [`SmaaFrame`] changes from frame to frame, so views of it must not
+    /// be kept around, and twice the memory is needed for it. Only has an effect with
+    /// [`SmaaBackend::Compute`] or [`SmaaBackend::ComputeFused`].
+    pub overlap_frames: bool,
+    /// Allow [`SmaaTarget::set_render_scale`] to antialias frames at a lower resolution, which a
+    /// final pass then upscales to the output with [`SmaaConfig::upscale_filter`].
+    ///
+    /// The textures keep the size of the target and the passes are restricted to the part of
+    /// them that the frame was rendered into, so the scale can change from one frame to the next
+    /// without the reallocations of [`SmaaTarget::resize`]. This needs another texture of the
+    /// target's size for the upscaling pass to read. Has no effect with [`SmaaBackend::Compute`],
+    /// [`SmaaBackend::ComputeFused`] or [`SmaaMode::Cmaa2`].
+    pub dynamic_resolution: bool,
+    /// Size of the output views passed to [`SmaaTarget::start_frame`], if it differs from the
+    /// size of the target.
+    ///
+    /// Frames are then antialiased at the size of the target, and a final pass scales the result
+    /// to the output with [`SmaaConfig::upscale_filter`]. This lets an application render and
+    /// antialias at half resolution and present the result full screen without a blit of its
+    /// own, at the cost of another texture of the target's size. When antialiasing is disabled,
+    /// the scene is rendered straight into the output at this size instead. Only supported by
+    /// [`SmaaBackend::Render`]; the other backends and [`SmaaMode::Cmaa2`] panic if it is set.
+    pub output_size: Option<(u32, u32)>,
+    /// Filter of the final pass that scales frames to the output, if
+    /// [`SmaaConfig::output_size`] or [`SmaaConfig::dynamic_resolution`] are set.
+    pub upscale_filter: SmaaUpscaleFilter,
+    /// Rotation of frames in the output, so that they can be resolved straight into the
+    /// swapchain of a pre-rotated surface.
+    ///
+    /// The scene is rendered and antialiased upright at the size of the target, and the final
+    /// pass writes it rotated into the output. Unless [`SmaaConfig::output_size`] is set, the
+    /// output views must be the size of the target with its width and height swapped for
+    /// quarter turns. When antialiasing is disabled, the scene is rendered straight into the
+    /// output and has to be rotated by the application itself. Only supported by
+    /// [`SmaaBackend::Render`]; the other backends and [`SmaaMode::Cmaa2`] panic if it is set.
+    pub output_transform: SmaaOutputTransform,
+    /// Antialias interlaced frames one field at a time, for video pipelines with interlaced
+    /// sources or to halve the work of each frame on embedded displays.
+    ///
+    /// Each frame then only renders and antialiases the rows of [`SmaaTarget::field`], as an
+    /// image of half the height of the target rounded up, and the final pass writes it to those
+    /// rows of the output. The rows of the other field keep what an earlier frame wrote, so the
+    /// outputs must keep their contents between frames. The pixels of a field are twice as tall
+    /// as they are wide, which SMAA handles without any change to its metrics. When antialiasing
+    /// is disabled, the scene is rendered straight into the output at its full height. Only
+    /// supported by [`SmaaBackend::Render`] with [`SmaaOutputTransform::Identity`], since the
+    /// rows of rotated outputs are columns of the frame. Sets [`SmaaConfig::output_size`]
+    /// itself, so it panics if that is set.
+    pub interlaced: bool,
+    /// What happens to frames that are dropped without being resolved.
+    ///
+    /// Resolving frames on drop submits the antialiasing passes right away, so a frame that
+    /// goes out of scope before the commands rendering the scene were submitted antialiases
+    /// whatever the color target contained before. The other policies make that mistake
+    /// visible instead of silently producing a stale frame. Frames dropped while a panic unwinds
+    /// are never resolved.
+    pub drop_policy: SmaaDropPolicy,
+    /// Make the output as reproducible as possible, for comparing it against golden images.
+    ///
+    /// Colors are then filtered in the shader with 32-bit floats instead of by the sampler,
+    /// whose precision differs between GPUs, and every texture is sampled at its base level
+    /// without relying on implicit derivatives. The intermediate targets use
+    /// [`SmaaPrecision::Unorm8`] whatever [`SmaaConfig::precision`] is, so that small differences
+    /// in the blend weights round away, and [`SmaaConfig::time_budget`] no longer changes the
+    /// preset. Frames are then bit-identical between runs on the same adapter and driver, and
+    /// differ by at most a few steps of the output format between backends and drivers. This
+    /// makes the passes somewhat slower.
+    pub deterministic: bool,
+}
+impl SmaaConfig {
+    /// Precision of the intermediate targets, which [`SmaaConfig::deterministic`] fixes.
+    fn intermediate_precision(&self) -> SmaaPrecision {
+        match self.deterministic {
+            true => SmaaPrecision::Unorm8,
+            false => self.precision,
+        }
+    }
+}
+
+/// The area and search lookup textures and the samplers that every [`SmaaTarget`] needs.
+///
+/// These are the same for all targets, so applications creating many targets on one device can
+/// create them once and pass a clone through [`SmaaConfig::resources`] to skip uploading the
+/// textures again. Cloning is cheap.
+#[derive(Clone)]
+pub struct SmaaResources(Arc<Resources>);
+
+impl SmaaResources {
+    /// Distance in pixels up to which [`SmaaResources::generate_area_texture`] rounds the
+    /// corners of U shaped patterns by default.
+    pub const SMOOTH_MAX_DISTANCE: f32 = generate::SMOOTH_MAX_DISTANCE;
+
+    /// Create and upload the lookup textures embedded in the crate.
+    #[cfg(feature = "embedded-textures")]
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::from_bytes(
+            device,
+            queue,
+            &lookup::area_texture_bytes(),
+            &lookup::search_texture_bytes(),
+        )
+    }
+
+    /// Create and upload the lookup textures from their contents, for instance as loaded from an
+    /// application's asset files.
+    ///
+    /// `area_texture` must contain the 160x560 pixel `Rg8Unorm` area texture and
+    /// `search_texture` the 64x16 pixel `R8Unorm` search texture, both stored row by row.
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        area_texture: &[u8],
+        search_texture: &[u8],
+    ) -> Self {
+        Self(Arc::new(Resources::upload(
+            device,
+            queue,
+            area_texture,
+            search_texture,
+        )))
+    }
+
+    /// Create and upload lookup textures computed with [`SmaaResources::generate_area_texture`]
+    /// and [`SmaaResources::generate_search_texture`] using the default settings, which match
+    /// the embedded ones.
+    ///
+    /// Computing them takes a moment, so the result is kept for the rest of the process and
+    /// only uploaded again for later calls.
+    pub fn generate(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let (area_texture, search_texture) = generate::default_textures();
+        Self::from_bytes(device, queue, area_texture, search_texture)
+    }
+
+    /// Compute the contents of the area texture in the layout expected by
+    /// [`SmaaResources::from_bytes`], for instance to cache it in an application's asset files.
+    ///
+    /// The corners of U shaped patterns shorter than `smooth_max_distance` pixels are rounded,
+    /// with [`SmaaResources::SMOOTH_MAX_DISTANCE`] giving the embedded texture. The maximum
+    /// distances of the searches are fixed by the layout the shaders expect.
+    pub fn generate_area_texture(smooth_max_distance: f32) -> Vec<u8> {
+        generate::area_texture(smooth_max_distance)
+    }
+
+    /// Compute the contents of the search texture in the layout expected by
+    /// [`SmaaResources::from_bytes`].
+    pub fn generate_search_texture() -> Vec<u8> {
+        generate::search_texture()
+    }
+
+    /// Use lookup textures that were already uploaded, which must have the same sizes and
+    /// formats as described for [`SmaaResources::from_bytes`] and be usable as
+    /// [`wgpu::TextureUsages::TEXTURE_BINDING`].
+    pub fn from_textures(
+        device: &wgpu::Device,
+        area_texture: wgpu::Texture,
+        search_texture: wgpu::Texture,
+    ) -> Self {
+        Self(Arc::new(Resources::new(
+            device,
+            area_texture,
+            search_texture,
+        )))
+    }
+
+    /// Resources to use for a target created with the given config, along with the result of
+    /// `create_pipelines`. Decompressing or generating the lookup textures happens on another
+    /// thread while the pipelines are created, instead of one after the other.
+    fn for_config<P>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &SmaaConfig,
+        create_pipelines: impl FnOnce() -> P,
+    ) -> (Arc<Resources>, P) {
+        match config.resources {
+            Some(ref resources) => (resources.0.clone(), create_pipelines()),
+            None => {
+                let ((area_texture, search_texture), pipelines) =
+                    join(Self::default_bytes, create_pipelines);
+                let resources = Resources::upload(device, queue, &area_texture, &search_texture);
+                (Arc::new(resources), pipelines)
+            }
+        }
+    }
+
+    /// Contents of the lookup textures used when none are supplied through
+    /// [`SmaaConfig::resources`].
+    fn default_bytes() -> (Cow<'static, [u8]>, Cow<'static, [u8]>) {
+        #[cfg(feature = "embedded-textures")]
+        return (lookup::area_texture_bytes(), lookup::search_texture_bytes());
+        #[cfg(not(feature = "embedded-textures"))]
+        {
+            let (area_texture, search_texture) = generate::default_textures();
+            (Cow::Borrowed(area_texture), Cow::Borrowed(search_texture))
+        }
+    }
+}
+impl std::fmt::Debug for SmaaResources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmaaResources").finish_non_exhaustive()
+    }
+}
+impl PartialEq for SmaaResources {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+const MISSING_DEPTH_VIEW: &str = "SmaaConfig::background_depth and SmaaEdgeDetection::Depth \
+    require frames to be started with start_frame_with_depth";
+const MISSING_LUMA_VIEW: &str =
+    "SmaaConfig::luma_input requires every frame to be given a luma view with set_luma_input";
+const MISSING_SECONDARY_VIEW: &str = "SmaaConfig::secondary_output requires every frame to be \
+    given a view with set_secondary_output";
+
+/// Views of a frame besides the color target and outputs: those that edge detection reads, and
+/// those that the final pass writes next to the outputs.
+#[derive(Copy, Clone, Default)]
+struct FrameViews<'a> {
+    depth: Option<&'a wgpu::TextureView>,
+    luma: Option<&'a wgpu::TextureView>,
+    secondary: Option<&'a wgpu::TextureView>,
+    // Resolve target of the first output, if it is multisampled.
+    resolve: Option<&'a wgpu::TextureView>,
+}
+
+/// Check that the backend can write outputs of a different size or orientation than the target.
+fn assert_output_supported(
+    backend: Option<&Backend>,
+    output_size: Option<(u32, u32)>,
+    output_transform: SmaaOutputTransform,
+) {
+    let supported = matches!(backend, None | Some(Backend::Render(_)));
+    assert!(
+        supported || output_size.is_none(),
+        "SmaaConfig::output_size is only supported by SmaaBackend::Render"
+    );
+    assert!(
+        supported || output_transform == SmaaOutputTransform::Identity,
+        "SmaaConfig::output_transform is only supported by SmaaBackend::Render"
+    );
+}
+
+/// Point `config` at outputs of the given size for [`SmaaMode::Ssaa2x`] and
+/// [`SmaaConfig::interlaced`], which antialias frames at a different size than the output, and
+/// return the size of the target.
+fn target_size(
+    config: &mut SmaaConfig,
+    supersampled: bool,
+    (width, height): (u32, u32),
+) -> (u32, u32) {
+    if !supersampled && !config.interlaced {
+        return (width, height);
+    }
+    config.output_size = Some(match config.output_transform {
+        SmaaOutputTransform::Rotate90 | SmaaOutputTransform::Rotate270 => (height, width),
+        _ => (width, height),
+    });
+    let height = match config.interlaced {
+        true => height.div_ceil(2),
+        false => height,
+    };
+    match supersampled {
+        true => {
+            config.upscale_filter = SmaaUpscaleFilter::Area;
+            (width * 2, height * 2)
+        }
+        false => (width, height),
+    }
+}
+
+/// Format of the stencil buffer that marks pixels containing edges. The edge detection shader
+/// discards pixels without edges, so the blend weight pass only has to run where it was written.
+/// Edges are marked by incrementing the cleared stencil rather than writing a reference value,
+/// because the passes are recorded as render bundles, which can't set the stencil reference.
+const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+/// Format of the view that [`SmaaConfig::secondary_output`] is written to.
+const SECONDARY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Pixels beyond the rendered part of the color target that edges are still detected in with
+/// [`SmaaConfig::dynamic_resolution`]. This covers the longest search of the blend weight pass,
+/// so lines running into the border are treated the same as at the border of the texture.
+const SEARCH_MARGIN: u32 = 80;
+
+/// Whether textures of the `allocated` size can be used for a target of the given `size`.
+fn fits_with_slack(allocated: (u32, u32), size: (u32, u32), slack: f32) -> bool {
+    let fits = |allocated: u32, size: u32| {
+        size <= allocated && allocated as f32 <= size as f32 * (1.0 + slack)
+    };
+    fits(allocated.0, size.0) && fits(allocated.1, size.1)
+}
+
+/// WGSL name of a storage texture format, if the output can be written as one.
+fn storage_format_name(format: wgpu::TextureFormat) -> Option<&'static str> {
+    Some(match format {
+        wgpu::TextureFormat::Rgba8Unorm => "rgba8unorm",
+        wgpu::TextureFormat::Rgba8Snorm => "rgba8snorm",
+        wgpu::TextureFormat::Rgba16Float => "rgba16float",
+        wgpu::TextureFormat::Rgba32Float => "rgba32float",
+        _ => return None,
+    })
+}
+
+/// Whether the render backend can draw into outputs of the given format.
+fn renderable(device: &wgpu::Device, format: wgpu::TextureFormat) -> bool {
+    format
+        .guaranteed_format_features(device.features())
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+}
+
+/// Whether the device can run the compute passes that later passes are drawn indirectly from.
+/// The device doesn't report its downlevel flags, but the limits of adapters without compute
+/// shaders or storage buffers are zero, and every backend with them supports indirect draws.
+fn supports_indirect_compute(device: &wgpu::Device) -> bool {
+    let limits = device.limits();
+    limits.max_compute_invocations_per_workgroup > 0
+        && limits.max_storage_buffers_per_shader_stage > 0
+}
+
+/// Format that the render backend blends frames into for outputs of the given format. Outputs
+/// that can't be rendered to, like `Rgba8Snorm`, are written by a final compute pass from a
+/// texture of this format instead.
+fn blended_format(device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+    match renderable(device, format) {
+        true => format,
+        false => wgpu::TextureFormat::Rgba16Float,
+    }
+}
+
+/// Blend state for render targets of the given format. Blending only replaces the target's
+/// contents, but formats like `Rgba32Float` don't allow a blend state at all.
+fn replace_blend_state(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Option<wgpu::BlendState> {
+    format
+        .guaranteed_format_features(device.features())
+        .flags
+        .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE)
+        .then_some(wgpu::BlendState::REPLACE)
+}
+
+fn is_float_format(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::R16Float
+            | wgpu::TextureFormat::Rg16Float
+            | wgpu::TextureFormat::Rgba16Float
+            | wgpu::TextureFormat::R32Float
+            | wgpu::TextureFormat::Rg32Float
+            | wgpu::TextureFormat::Rgba32Float
+            | wgpu::TextureFormat::Rg11b10Float
+    )
+}
+
+/// Contents of the uniform buffer holding `SMAA_RT_METRICS` for textures of the given size.
+///
+/// The passes are restricted to the top left `region` of the textures. The shaders get the
+/// texel size of the whole textures from the first two components, and the size of the region
+/// from the last two, which are only the reciprocals of each other when it covers them.
+fn rt_metrics(size: (u32, u32), region: (u32, u32)) -> Vec<u8> {
+    let mut uniform_data = Vec::new();
+    for f in &[
+        1.0 / size.0 as f32,
+        1.0 / size.1 as f32,
+        region.0 as f32,
+        region.1 as f32,
+    ] {
+        uniform_data.extend_from_slice(&f.to_ne_bytes());
+    }
+    uniform_data
+}
+
+/// Create the uniform buffer holding `SMAA_RT_METRICS` for a target of the given size.
+fn create_rt_uniforms(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("smaa.uniforms"),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        contents: &rt_metrics((width, height), (width, height)),
+    })
+}
+
+/// Run `a` on another thread while `b` runs on this one. Only CPU work should go into `a`: wgpu
+/// objects are shared between threads through locks, so using the device from both would mostly
+/// make them wait for each other.
+#[cfg(not(target_arch = "wasm32"))]
+fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB,
+    RA: Send,
+{
+    std::thread::scope(|scope| {
+        let a = scope.spawn(a);
+        let b = b();
+        let a = a
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload));
+        (a, b)
+    })
+}
+/// Threads aren't available on the web, so run `a` and `b` one after the other.
+#[cfg(target_arch = "wasm32")]
+fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA,
+    B: FnOnce() -> RB,
+{
+    (a(), b())
+}
+
+/// Create a buffer holding indirect arguments that are copied over the ones filled in by a compute
+/// pass, to reset them before each frame.
+fn create_args_buffer(device: &wgpu::Device, args: &[u32]) -> wgpu::Buffer {
+    let mut data = Vec::new();
+    for arg in args {
+        data.extend_from_slice(&arg.to_ne_bytes());
+    }
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("smaa.buffer.initial_args"),
+        usage: wgpu::BufferUsages::COPY_SRC,
+        contents: &data,
+    })
+}
+
+/// Work out which conversions the shaders need to apply for the given formats and settings.
+fn shader_source(
+    format: wgpu::TextureFormat,
+    config: &SmaaConfig,
+    manual_filtering: bool,
+) -> ShaderSource {
+    let color_format = config.color_format.unwrap_or(format);
+
+    // Colors read from sRGB and floating point formats are already linear, so only a mismatch
+    // between the format and the requested blend space needs a conversion in the shader.
+    let read_linear = color_format.is_srgb() || is_float_format(color_format);
+    let blend_linear = match config.blend_space {
+        SmaaBlendSpace::Auto => read_linear,
+        SmaaBlendSpace::Linear => true,
+        SmaaBlendSpace::Gamma => false,
+    };
+    let blend_transform = match (read_linear, blend_linear) {
+        (false, true) => ColorTransform::SrgbToLinear,
+        (true, false) => ColorTransform::LinearToSrgb,
+        _ => ColorTransform::None,
+    };
+
+    // Writes to sRGB formats are encoded by the hardware and HDR encodings start from linear
+    // colors, otherwise the output uses the same representation the colors were read in.
+    let output_linear =
+        read_linear || format.is_srgb() || config.output_encoding != SmaaOutputEncoding::None;
+    let output_transform = match (read_linear, output_linear) {
+        (false, true) => ColorTransform::SrgbToLinear,
+        _ => ColorTransform::None,
+    };
+
+    let edge_transform = match config.edge_conversion {
+        SmaaEdgeConversion::None => ColorTransform::None,
+        SmaaEdgeConversion::Linearize => ColorTransform::SrgbToLinear,
+        SmaaEdgeConversion::GammaEncode => ColorTransform::LinearToSrgb,
+    };
+    ShaderSource {
+        quality: match config.preset {
+            SmaaPreset::Low => ShaderQuality::Low,
+            SmaaPreset::Medium => ShaderQuality::Medium,
+            SmaaPreset::High => ShaderQuality::High,
+            SmaaPreset::Ultra => ShaderQuality::Ultra,
+        },
+        blend_transform,
+        edge_transform,
+        pre_tonemap: config.pre_tonemap,
+        manual_filtering: manual_filtering || config.deterministic,
+        output_transform,
+        output_encoding: config.output_encoding,
+        background_depth: config.background_depth,
+        rgba_edges: config.rgba_edges,
+        luma_output: config.luma_output,
+        luma_input: config.luma_input,
+        secondary_output: config.secondary_output,
+        deterministic: config.deterministic,
+        debug_output: config.debug_output,
+        edge_detection: config.edge_detection,
+        premultiplied_alpha: config.premultiplied_alpha,
+        composite_alpha_mode: config.composite_alpha_mode,
+        single_channel: color_format.components() == 1,
+        cropped_input: false,
+    }
+}
+
+/// Layout entry for the scene depth texture read by the edge detection pass. Depth formats can
+/// only be bound as non-filterable float textures.
+fn depth_texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
 }
 
 struct BindGroupLayouts {
+    color_filterable: bool,
+    // Edge detection reads the frame's depth view, for the early out or for depth edges.
+    depth_input: bool,
+    // Edge detection reads a luma view of the frame instead of the color target.
+    luma_input: bool,
+    // The color, depth and luma views are larger than the intermediate targets, and the uniforms
+    // hold the region of them that is read. Only `SmaaRenderer` crops its input.
+    cropped_input: bool,
     edge_detect_bind_group_layout: wgpu::BindGroupLayout,
+    tile_classification_bind_group_layout: Option<wgpu::BindGroupLayout>,
+    edge_presence_bind_group_layout: Option<wgpu::BindGroupLayout>,
     blend_weight_bind_group_layout: wgpu::BindGroupLayout,
     neighborhood_blending_bind_group_layout: wgpu::BindGroupLayout,
 }
 struct Pipelines {
     edge_detect: wgpu::RenderPipeline,
+    tile_classification: Option<wgpu::ComputePipeline>,
+    edge_presence: Option<wgpu::ComputePipeline>,
     blend_weight: wgpu::RenderPipeline,
     neighborhood_blending: wgpu::RenderPipeline,
+    neighborhood_blending_copy: Option<wgpu::RenderPipeline>,
 }
 struct Resources {
     area_texture: wgpu::Texture,
     search_texture: wgpu::Texture,
     linear_sampler: wgpu::Sampler,
+    nearest_sampler: wgpu::Sampler,
 }
 struct Targets {
+    // Size of the color target, and of the part of it that `rt_uniforms` restricts the passes to.
+    size: (u32, u32),
+    region: (u32, u32),
     rt_uniforms: wgpu::Buffer,
     color_target: wgpu::TextureView,
+    tiles: Option<TileBuffers>,
+    edge_presence: Option<EdgePresenceBuffers>,
+}
+// Only the passes access these, so they can stay larger than the color target after it shrinks.
+// The edges of the pixels along the color target's right and bottom border are then repeated
+// beyond it and the blend weight pass runs over the entire textures, using their own RT metrics.
+// None of these can be transient (memoryless) attachments on tile-based GPUs: each pass samples
+// what the previous one rendered, and wgpu has no subpasses that would keep the data in tile
+// memory between them. The stencil is also loaded by the blend weight pass. wgpu doesn't expose
+// transient texture usages either.
+struct Intermediates {
+    width: u32,
+    height: u32,
+    rt_uniforms: wgpu::Buffer,
     edges_target: wgpu::TextureView,
     blend_target: wgpu::TextureView,
+    stencil_target: wgpu::TextureView,
+    luma_target: Option<wgpu::TextureView>,
+}
+// Output of the neighborhood blending for frames that the final pass scales to the output, because
+// they were rendered at a lower resolution or the output has a different size.
+struct Upscaling {
+    output: wgpu::TextureView,
+    pass: upscale::UpscalePass,
+}
+struct TileBuffers {
+    tile_counts: (u32, u32),
+    draw_args: wgpu::Buffer,
+    // Copied into `draw_args` before each classification.
+    initial_draw_args: wgpu::Buffer,
+    tile_list: wgpu::Buffer,
+}
+struct EdgePresenceBuffers {
+    tile_counts: (u32, u32),
+    // Indirect draw arguments for the blend weight pass, the neighborhood blending and the copy
+    // that replaces it when there are no edges.
+    pass_args: wgpu::Buffer,
+    // Copied into `pass_args` before each frame.
+    initial_pass_args: wgpu::Buffer,
 }
 struct BindGroups {
-    edge_detect_bind_group: wgpu::BindGroup,
+    // Created for each frame instead when the edge detection pass reads the scene's depth or a
+    // luma view supplied with the frame.
+    edge_detect_bind_group: Option<wgpu::BindGroup>,
+    tile_classification_bind_group: Option<wgpu::BindGroup>,
+    edge_presence_bind_group: Option<wgpu::BindGroup>,
     blend_weight_bind_group: wgpu::BindGroup,
     neighborhood_blending_bind_group: wgpu::BindGroup,
 }
+// Draws of each pass recorded ahead of time, so that encoding a frame only has to execute them.
+struct Bundles {
+    // Not recorded when the edge detection pass reads the scene's depth or a luma view.
+    edge_detect: Option<wgpu::RenderBundle>,
+    blend_weight: wgpu::RenderBundle,
+    neighborhood_blending: wgpu::RenderBundle,
+}
 
 impl BindGroupLayouts {
-    pub fn new(device: &wgpu::Device) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        tile_classification: bool,
+        skip_without_edges: bool,
+        depth_input: bool,
+        luma_input: bool,
+        cropped_input: bool,
+    ) -> Self {
+        // Formats like `Rgba32Float` may not support filtering. The passes that read the color
+        // target then use a non-filtering sampler and filter in the shader where needed.
+        let color_filterable = matches!(
+            color_format.sample_type(None, Some(device.features())),
+            Some(wgpu::TextureSampleType::Float { filterable: true })
+        );
+        let color_sampler_type = if color_filterable {
+            wgpu::SamplerBindingType::Filtering
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        };
+
+        let mut edge_detect_entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(color_sampler_type),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float {
+                        filterable: color_filterable,
+                    },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+        ];
+        if depth_input {
+            edge_detect_entries.push(depth_texture_entry(3, wgpu::ShaderStages::FRAGMENT));
+        }
+
         Self {
+            color_filterable,
+            depth_input,
+            luma_input,
+            cropped_input,
             edge_detect_bind_group_layout: device.create_bind_group_layout(
                 &wgpu::BindGroupLayoutDescriptor {
                     label: Some("smaa.bind_group_layout.edge_detect"),
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: false,
-                                min_binding_size: None,
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 2,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                multisampled: false,
-                            },
-                            count: None,
-                        },
-                    ],
+                    entries: &edge_detect_entries,
                 },
             ),
             blend_weight_bind_group_layout: device.create_bind_group_layout(
@@ -208,19 +1566,13 @@ impl BindGroupLayouts {
                     ],
                 },
             ),
-            neighborhood_blending_bind_group_layout: device.create_bind_group_layout(
-                &wgpu::BindGroupLayoutDescriptor {
-                    label: Some("smaa.bind_group_layout.neighborhood_blending"),
+            tile_classification_bind_group_layout: tile_classification.then(|| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.bind_group_layout.tile_classification"),
                     entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
                         wgpu::BindGroupLayoutEntry {
                             binding: 1,
-                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                            visibility: wgpu::ShaderStages::COMPUTE,
                             ty: wgpu::BindingType::Buffer {
                                 ty: wgpu::BufferBindingType::Uniform,
                                 has_dynamic_offset: false,
@@ -230,7 +1582,7 @@ impl BindGroupLayouts {
                         },
                         wgpu::BindGroupLayoutEntry {
                             binding: 2,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            visibility: wgpu::ShaderStages::COMPUTE,
                             ty: wgpu::BindingType::Texture {
                                 sample_type: wgpu::TextureSampleType::Float { filterable: true },
                                 view_dimension: wgpu::TextureViewDimension::D2,
@@ -240,19 +1592,111 @@ impl BindGroupLayouts {
                         },
                         wgpu::BindGroupLayoutEntry {
                             binding: 3,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                multisampled: false,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
                             },
                             count: None,
                         },
-                    ],
-                },
-            ),
-        }
-    }
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                })
+            }),
+            edge_presence_bind_group_layout: skip_without_edges.then(|| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.bind_group_layout.edge_presence"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                })
+            }),
+            neighborhood_blending_bind_group_layout: device.create_bind_group_layout(
+                &wgpu::BindGroupLayoutDescriptor {
+                    label: Some("smaa.bind_group_layout.neighborhood_blending"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(color_sampler_type),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float {
+                                    filterable: color_filterable,
+                                },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                    ],
+                },
+            ),
+        }
+    }
 }
 
 impl Pipelines {
@@ -260,9 +1704,11 @@ impl Pipelines {
         device: &wgpu::Device,
         format: wgpu::TextureFormat,
         layouts: &BindGroupLayouts,
+        config: &SmaaConfig,
     ) -> Self {
         let source = ShaderSource {
-            quality: ShaderQuality::High,
+            cropped_input: layouts.cropped_input,
+            ..shader_source(format, config, !layouts.color_filterable)
         };
 
         let edge_detect_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -280,6 +1726,22 @@ impl Pipelines {
             buffers: &[],
             compilation_options: wgpu::PipelineCompilationOptions::default(),
         };
+        let formats = IntermediateFormats::new(config);
+        let edge_detect_targets = [
+            Some(wgpu::ColorTargetState {
+                format: formats.edges,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent::REPLACE,
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+            formats.luma.map(|format| format.into()),
+        ];
+        let edge_detect_targets = match formats.luma {
+            Some(_) => &edge_detect_targets[..],
+            None => &edge_detect_targets[..1],
+        };
         let edge_detect_shader_frag = wgpu::FragmentState {
             module: &source.get_shader(
                 device,
@@ -287,14 +1749,7 @@ impl Pipelines {
                 "smaa.shader.edge_detect.frag",
             ),
             entry_point: "main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: wgpu::TextureFormat::Rg8Unorm,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent::REPLACE,
-                    alpha: wgpu::BlendComponent::REPLACE,
-                }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
+            targets: edge_detect_targets,
             compilation_options: wgpu::PipelineCompilationOptions::default(),
         };
         let edge_detect = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -304,7 +1759,28 @@ impl Pipelines {
             fragment: Some(edge_detect_shader_frag),
             primitive: Default::default(),
             multisample: Default::default(),
-            depth_stencil: None,
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: STENCIL_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::Always,
+                        fail_op: wgpu::StencilOperation::Keep,
+                        depth_fail_op: wgpu::StencilOperation::Keep,
+                        pass_op: wgpu::StencilOperation::IncrementClamp,
+                    },
+                    back: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::Always,
+                        fail_op: wgpu::StencilOperation::Keep,
+                        depth_fail_op: wgpu::StencilOperation::Keep,
+                        pass_op: wgpu::StencilOperation::IncrementClamp,
+                    },
+                    read_mask: 0xff,
+                    write_mask: 0xff,
+                },
+                bias: Default::default(),
+            }),
             multiview: None,
             cache: None
         });
@@ -314,14 +1790,74 @@ impl Pipelines {
             bind_group_layouts: &[&layouts.blend_weight_bind_group_layout],
             push_constant_ranges: &[],
         });
-        let blend_weight_shader_vert = wgpu::VertexState {
-            module: &source.get_shader(
+        let tile_classification =
+            layouts
+                .tile_classification_bind_group_layout
+                .as_ref()
+                .map(|bind_group_layout| {
+                    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("smaa.pipeline_layout.tile_classification"),
+                        bind_group_layouts: &[bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+                    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: Some("smaa.pipeline.tile_classification"),
+                        layout: Some(&layout),
+                        module: &shader::tile_classification_shader(device),
+                        entry_point: "main",
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        cache: None,
+                    })
+                });
+
+        let edge_presence =
+            layouts
+                .edge_presence_bind_group_layout
+                .as_ref()
+                .map(|bind_group_layout| {
+                    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("smaa.pipeline_layout.edge_presence"),
+                        bind_group_layouts: &[bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+                    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: Some("smaa.pipeline.edge_presence"),
+                        layout: Some(&layout),
+                        module: &shader::edge_presence_shader(device),
+                        entry_point: "main",
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        cache: None,
+                    })
+                });
+
+        // With tile classification the blend weight pass draws one instance per tile that
+        // contains edges, reading the tile coordinates from the list built by the compute pass.
+        let tile_vertex_buffers = [wgpu::VertexBufferLayout {
+            array_stride: 4,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![0 => Uint32],
+        }];
+        let blend_weight_vert_module = if tile_classification.is_some() {
+            source.get_shader(
+                device,
+                ShaderStage::BlendingWeightTiledVS,
+                "smaa.shader.blending_weight.tiled.vert",
+            )
+        } else {
+            source.get_shader(
                 device,
                 ShaderStage::BlendingWeightVS,
                 "smaa.shader.blending_weight.vert",
-            ),
+            )
+        };
+        let blend_weight_shader_vert = wgpu::VertexState {
+            module: &blend_weight_vert_module,
             entry_point: "main",
-            buffers: &[],
+            buffers: if tile_classification.is_some() {
+                &tile_vertex_buffers
+            } else {
+                &[]
+            },
             compilation_options: wgpu::PipelineCompilationOptions::default(),
         };
         let blend_weight_shader_frag = wgpu::FragmentState {
@@ -332,7 +1868,7 @@ impl Pipelines {
             ),
             entry_point: "main",
             targets: &[Some(wgpu::ColorTargetState {
-                format: wgpu::TextureFormat::Rgba8Unorm,
+                format: formats.blend,
                 blend: Some(wgpu::BlendState {
                     color: wgpu::BlendComponent::REPLACE,
                     alpha: wgpu::BlendComponent::REPLACE,
@@ -348,7 +1884,24 @@ impl Pipelines {
             fragment: Some(blend_weight_shader_frag),
             primitive: Default::default(),
             multisample: Default::default(),
-            depth_stencil: None,
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: STENCIL_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::NotEqual,
+                        ..wgpu::StencilFaceState::IGNORE
+                    },
+                    back: wgpu::StencilFaceState {
+                        compare: wgpu::CompareFunction::NotEqual,
+                        ..wgpu::StencilFaceState::IGNORE
+                    },
+                    read_mask: 0xff,
+                    write_mask: 0,
+                },
+                bias: Default::default(),
+            }),
             multiview: None,
             cache: None
         });
@@ -369,6 +1922,24 @@ impl Pipelines {
             buffers: &[],
             compilation_options: wgpu::PipelineCompilationOptions::default(),
         };
+        let neighborhood_blending_targets = [
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: config
+                    .output_blend_state
+                    .or_else(|| replace_blend_state(device, format)),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+            Some(SECONDARY_FORMAT.into()),
+        ];
+        let neighborhood_blending_targets = match config.secondary_output {
+            SmaaSecondaryOutput::None => &neighborhood_blending_targets[..1],
+            _ => &neighborhood_blending_targets[..],
+        };
+        let output_multisample = wgpu::MultisampleState {
+            count: config.output_sample_count.max(1),
+            ..Default::default()
+        };
         let neighborhood_blending_frag = wgpu::FragmentState {
             module: &source.get_shader(
                 device,
@@ -376,33 +1947,51 @@ impl Pipelines {
                 "smaa.shader.neighborhood_blending.frag",
             ),
             entry_point: "main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent::REPLACE,
-                    alpha: wgpu::BlendComponent::REPLACE,
-                }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
+            targets: neighborhood_blending_targets,
             compilation_options: wgpu::PipelineCompilationOptions::default(),
         };
         let neighborhood_blending =
             device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: Some("smaa.pipeline.neighborhood_blending"),
                 layout: Some(&neighborhood_blending_layout),
-                vertex: neighborhood_blending_vert,
+                vertex: neighborhood_blending_vert.clone(),
                 fragment: Some(neighborhood_blending_frag),
                 primitive: Default::default(),
-                multisample: Default::default(),
+                multisample: output_multisample,
                 depth_stencil: None,
                 multiview: None,
                 cache: None
             });
+        let neighborhood_blending_copy = edge_presence.is_some().then(|| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("smaa.pipeline.neighborhood_blending.copy"),
+                layout: Some(&neighborhood_blending_layout),
+                vertex: neighborhood_blending_vert,
+                fragment: Some(wgpu::FragmentState {
+                    module: &source.get_shader(
+                        device,
+                        ShaderStage::NeighborhoodBlendingCopyPS,
+                        "smaa.shader.neighborhood_blending.copy.frag",
+                    ),
+                    entry_point: "main",
+                    targets: neighborhood_blending_targets,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: Default::default(),
+                multisample: output_multisample,
+                depth_stencil: None,
+                multiview: None,
+                cache: None
+            })
+        });
 
         Self {
             edge_detect,
+            tile_classification,
+            edge_presence,
             blend_weight,
             neighborhood_blending,
+            neighborhood_blending_copy,
         }
     }
 }
@@ -412,52 +2001,117 @@ impl Targets {
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
+        tile_classification: bool,
+        skip_without_edges: bool,
     ) -> Self {
-        let size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-        let texture_desc = wgpu::TextureDescriptor {
-            size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            label: None,
-            view_formats: &[],
-        };
+        let rt_uniforms = create_rt_uniforms(device, width, height);
+
+        let tiles = tile_classification.then(|| {
+            let tile_counts = (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE));
+            TileBuffers {
+                tile_counts,
+                draw_args: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("smaa.buffer.tile_draw_args"),
+                    size: 16,
+                    usage: wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::INDIRECT
+                        | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                // Six vertices per tile, with the instance count accumulated by the compute pass.
+                initial_draw_args: create_args_buffer(device, &[6, 0, 0, 0]),
+                tile_list: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("smaa.buffer.tile_list"),
+                    size: 4 * tile_counts.0 as u64 * tile_counts.1 as u64,
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+                    mapped_at_creation: false,
+                }),
+            }
+        });
 
-        let mut uniform_data = Vec::new();
-        for f in &[
-            1.0 / width as f32,
-            1.0 / height as f32,
-            width as f32,
-            height as f32,
-        ] {
-            uniform_data.extend_from_slice(&f.to_ne_bytes());
-        }
-        let rt_uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("smaa.uniforms"),
-            usage: wgpu::BufferUsages::UNIFORM,
-            contents: &uniform_data,
+        let edge_presence = skip_without_edges.then(|| EdgePresenceBuffers {
+            tile_counts: (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE)),
+            pass_args: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.pass_args"),
+                size: 48,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::INDIRECT
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            // Only the copy is drawn unless the compute pass finds an edge.
+            initial_pass_args: create_args_buffer(device, &[0, 1, 0, 0, 0, 1, 0, 0, 3, 1, 0, 0]),
         });
 
         Self {
+            size: (width, height),
+            region: (width, height),
             rt_uniforms,
             color_target: device
                 .create_texture(&wgpu::TextureDescriptor {
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
                     format,
-                    ..texture_desc
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    label: None,
+                    view_formats: &[],
                 })
                 .create_view(&wgpu::TextureViewDescriptor {
                     label: Some("smaa.color_target.view"),
                     ..Default::default()
                 }),
+            tiles,
+            edge_presence,
+        }
+    }
+
+    /// Size of the scissor rectangle covering the region that the passes are restricted to plus
+    /// `margin` pixels, or `None` if they cover the whole target.
+    fn scissor(&self, margin: u32) -> Option<(u32, u32)> {
+        (self.region != self.size).then(|| {
+            (
+                (self.region.0 + margin).min(self.size.0),
+                (self.region.1 + margin).min(self.size.1),
+            )
+        })
+    }
+}
+impl Intermediates {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        formats: IntermediateFormats,
+    ) -> Self {
+        let texture_desc = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: None,
+            view_formats: &[],
+        };
+
+        Self {
+            width,
+            height,
+            rt_uniforms: create_rt_uniforms(device, width, height),
             edges_target: device
                 .create_texture(&wgpu::TextureDescriptor {
-                    format: wgpu::TextureFormat::Rg8Unorm,
+                    format: formats.edges,
                     label: Some("smaa.texture.edge_target"),
                     ..texture_desc
                 })
@@ -468,7 +2122,7 @@ impl Targets {
 
             blend_target: device
                 .create_texture(&wgpu::TextureDescriptor {
-                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    format: formats.blend,
                     label: Some("smaa.texture.blend_target"),
                     ..texture_desc
                 })
@@ -476,11 +2130,95 @@ impl Targets {
                     label: Some("smaa.texture_view.blend_target"),
                     ..Default::default()
                 }),
+
+            stencil_target: device
+                .create_texture(&wgpu::TextureDescriptor {
+                    format: STENCIL_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    label: Some("smaa.texture.stencil_target"),
+                    ..texture_desc
+                })
+                .create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("smaa.texture_view.stencil_target"),
+                    ..Default::default()
+                }),
+
+            luma_target: formats.luma.map(|format| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        format,
+                        usage: texture_desc.usage | wgpu::TextureUsages::COPY_SRC,
+                        label: Some("smaa.texture.luma_target"),
+                        ..texture_desc
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("smaa.texture_view.luma_target"),
+                        ..Default::default()
+                    })
+            }),
         }
     }
 }
+impl Upscaling {
+    /// Whether frames need a final pass to reach outputs of the given format.
+    fn needed(device: &wgpu::Device, format: wgpu::TextureFormat, config: &SmaaConfig) -> bool {
+        config.dynamic_resolution
+            || config.output_size.is_some()
+            || config.output_transform != SmaaOutputTransform::Identity
+            || !renderable(device, format)
+    }
+
+    /// Create the texture that frames are blended into, of `format`, and the pass writing it to
+    /// outputs of `output_format`.
+    fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        output_format: wgpu::TextureFormat,
+        filter: SmaaUpscaleFilter,
+    ) -> Self {
+        let output = device
+            .create_texture(&wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                label: Some("smaa.texture.scaled_output"),
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("smaa.texture_view.scaled_output"),
+                ..Default::default()
+            });
+        let pass = upscale::UpscalePass::new(device, &output, format, output_format, filter);
+        Self { output, pass }
+    }
+}
 impl Resources {
-    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        area_texture: &[u8],
+        search_texture: &[u8],
+    ) -> Self {
+        assert_eq!(
+            area_texture.len(),
+            (AREATEX_WIDTH * AREATEX_HEIGHT * 2) as usize,
+            "area texture has the wrong size"
+        );
+        assert_eq!(
+            search_texture.len(),
+            (SEARCHTEX_WIDTH * SEARCHTEX_HEIGHT) as usize,
+            "search texture has the wrong size"
+        );
         let area_texture = device.create_texture_with_data(
             queue,
             &wgpu::TextureDescriptor {
@@ -498,7 +2236,7 @@ impl Resources {
                 view_formats: &[],
             },
             wgpu::util::TextureDataOrder::LayerMajor,
-            &AREATEX_BYTES,
+            area_texture,
         );
 
         let search_texture = device.create_texture_with_data(
@@ -518,7 +2256,31 @@ impl Resources {
                 view_formats: &[],
             },
             wgpu::util::TextureDataOrder::LayerMajor,
-            &SEARCHTEX_BYTES,
+            search_texture,
+        );
+        Self::new(device, area_texture, search_texture)
+    }
+
+    fn new(
+        device: &wgpu::Device,
+        area_texture: wgpu::Texture,
+        search_texture: wgpu::Texture,
+    ) -> Self {
+        assert!(
+            area_texture.format() == wgpu::TextureFormat::Rg8Unorm
+                && area_texture.width() == AREATEX_WIDTH
+                && area_texture.height() == AREATEX_HEIGHT,
+            "area texture must be a {}x{} Rg8Unorm texture",
+            AREATEX_WIDTH,
+            AREATEX_HEIGHT
+        );
+        assert!(
+            search_texture.format() == wgpu::TextureFormat::R8Unorm
+                && search_texture.width() == SEARCHTEX_WIDTH
+                && search_texture.height() == SEARCHTEX_HEIGHT,
+            "search texture must be a {}x{} R8Unorm texture",
+            SEARCHTEX_WIDTH,
+            SEARCHTEX_HEIGHT
         );
 
         let linear_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -530,108 +2292,358 @@ impl Resources {
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             ..Default::default()
         });
+        let nearest_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("smaa.sampler.nearest"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
 
         Self {
             area_texture,
             search_texture,
             linear_sampler,
+            nearest_sampler,
         }
     }
 }
 
 impl BindGroups {
-    fn new(
+    fn edge_detect(
         device: &wgpu::Device,
         layouts: &BindGroupLayouts,
         resources: &Resources,
-        targets: &Targets,
-    ) -> Self {
-        Self {
-            edge_detect_bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("smaa.bind_group.edge_detect"),
-                layout: &layouts.edge_detect_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Sampler(&resources.linear_sampler),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &targets.rt_uniforms,
-                            offset: 0,
-                            size: None,
-                        }),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::TextureView(&targets.color_target),
-                    },
-                ],
-            }),
+        rt_uniforms: &wgpu::Buffer,
+        color_view: &wgpu::TextureView,
+        depth_view: Option<&wgpu::TextureView>,
+    ) -> wgpu::BindGroup {
+        let color_sampler = if layouts.color_filterable {
+            &resources.linear_sampler
+        } else {
+            &resources.nearest_sampler
+        };
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Sampler(color_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: rt_uniforms,
+                    offset: 0,
+                    size: None,
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(color_view),
+            },
+        ];
+        if let Some(depth_view) = depth_view {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            });
+        }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.edge_detect"),
+            layout: &layouts.edge_detect_bind_group_layout,
+            entries: &entries,
+        })
+    }
 
-            blend_weight_bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("smaa.bind_group.blend_weight"),
-                layout: &layouts.blend_weight_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Sampler(&resources.linear_sampler),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &targets.rt_uniforms,
-                            offset: 0,
-                            size: None,
-                        }),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::TextureView(&targets.edges_target),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: wgpu::BindingResource::TextureView(
-                            &resources.area_texture.create_view(&Default::default()),
-                        ),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 4,
-                        resource: wgpu::BindingResource::TextureView(
-                            &resources.search_texture.create_view(&Default::default()),
-                        ),
-                    },
-                ],
-            }),
-            neighborhood_blending_bind_group: device.create_bind_group(
-                &wgpu::BindGroupDescriptor {
-                    label: Some("smaa.bind_group.neighborhood_blending"),
-                    layout: &layouts.neighborhood_blending_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::Sampler(&resources.linear_sampler),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                                buffer: &targets.rt_uniforms,
-                                offset: 0,
-                                size: None,
-                            }),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 2,
-                            resource: wgpu::BindingResource::TextureView(&targets.color_target),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 3,
-                            resource: wgpu::BindingResource::TextureView(&targets.blend_target),
-                        },
-                    ],
+    fn blend_weight(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        resources: &Resources,
+        rt_uniforms: &wgpu::Buffer,
+        edges_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.blend_weight"),
+            layout: &layouts.blend_weight_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&resources.linear_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: rt_uniforms,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(edges_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &resources.area_texture.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(
+                        &resources.search_texture.create_view(&Default::default()),
+                    ),
+                },
+            ],
+        })
+    }
+
+    fn neighborhood_blending(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        resources: &Resources,
+        rt_uniforms: &wgpu::Buffer,
+        blend_view: &wgpu::TextureView,
+        color_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        let color_sampler = if layouts.color_filterable {
+            &resources.linear_sampler
+        } else {
+            &resources.nearest_sampler
+        };
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.neighborhood_blending"),
+            layout: &layouts.neighborhood_blending_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(color_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: rt_uniforms,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(color_view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(blend_view),
+                },
+            ],
+        })
+    }
+
+    fn new(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        resources: &Resources,
+        targets: &Targets,
+        intermediates: &Intermediates,
+    ) -> Self {
+        Self {
+            edge_detect_bind_group: (!layouts.depth_input && !layouts.luma_input).then(|| {
+                Self::edge_detect(
+                    device,
+                    layouts,
+                    resources,
+                    &targets.rt_uniforms,
+                    &targets.color_target,
+                    None,
+                )
+            }),
+
+            tile_classification_bind_group: layouts
+                .tile_classification_bind_group_layout
+                .as_ref()
+                .zip(targets.tiles.as_ref())
+                .map(|(layout, tiles)| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("smaa.bind_group.tile_classification"),
+                        layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: targets.rt_uniforms.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &intermediates.edges_target,
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: tiles.draw_args.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 4,
+                                resource: tiles.tile_list.as_entire_binding(),
+                            },
+                        ],
+                    })
+                }),
+
+            edge_presence_bind_group: layouts
+                .edge_presence_bind_group_layout
+                .as_ref()
+                .zip(targets.edge_presence.as_ref())
+                .map(|(layout, edge_presence)| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("smaa.bind_group.edge_presence"),
+                        layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: targets.rt_uniforms.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &intermediates.edges_target,
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: edge_presence.pass_args.as_entire_binding(),
+                            },
+                        ],
+                    })
+                }),
+
+            blend_weight_bind_group: Self::blend_weight(
+                device,
+                layouts,
+                resources,
+                &intermediates.rt_uniforms,
+                &intermediates.edges_target,
             ),
+            neighborhood_blending_bind_group: Self::neighborhood_blending(
+                device,
+                layouts,
+                resources,
+                &targets.rt_uniforms,
+                &intermediates.blend_target,
+                &targets.color_target,
+            ),
+        }
+    }
+}
+
+impl Bundles {
+    fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        formats: IntermediateFormats,
+        pipelines: &Pipelines,
+        targets: &Targets,
+        bind_groups: &BindGroups,
+    ) -> Self {
+        let create_encoder =
+            |color_formats: &[Option<wgpu::TextureFormat>], stencil: bool, sample_count: u32| {
+                device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: None,
+                    color_formats,
+                    depth_stencil: stencil.then_some(wgpu::RenderBundleDepthStencil {
+                        format: STENCIL_FORMAT,
+                        depth_read_only: true,
+                        stencil_read_only: false,
+                    }),
+                    sample_count,
+                    multiview: None,
+                })
+            };
+
+        Self {
+            edge_detect: bind_groups
+                .edge_detect_bind_group
+                .as_ref()
+                .map(|bind_group| {
+                    let color_formats = match formats.luma {
+                        Some(luma) => vec![Some(formats.edges), Some(luma)],
+                        None => vec![Some(formats.edges)],
+                    };
+                    let mut encoder = create_encoder(&color_formats, true, 1);
+                    Self::draw_edge_detect(&mut encoder, pipelines, bind_group);
+                    encoder.finish(&wgpu::RenderBundleDescriptor {
+                        label: Some("smaa.render_bundle.edge_detect"),
+                    })
+                }),
+            blend_weight: {
+                let mut encoder = create_encoder(&[Some(formats.blend)], true, 1);
+                Self::draw_blend_weight(&mut encoder, pipelines, targets, bind_groups);
+                encoder.finish(&wgpu::RenderBundleDescriptor {
+                    label: Some("smaa.render_bundle.blend_weight"),
+                })
+            },
+            neighborhood_blending: {
+                let color_formats = match formats.secondary {
+                    Some(secondary) => vec![Some(output_format), Some(secondary)],
+                    None => vec![Some(output_format)],
+                };
+                let mut encoder = create_encoder(&color_formats, false, formats.output_samples);
+                Self::draw_neighborhood_blending(
+                    &mut encoder,
+                    pipelines,
+                    targets,
+                    &bind_groups.neighborhood_blending_bind_group,
+                );
+                encoder.finish(&wgpu::RenderBundleDescriptor {
+                    label: Some("smaa.render_bundle.neighborhood_blending"),
+                })
+            },
+        }
+    }
+
+    fn draw_edge_detect<'a>(
+        pass: &mut impl RenderEncoder<'a>,
+        pipelines: &'a Pipelines,
+        bind_group: &'a wgpu::BindGroup,
+    ) {
+        pass.set_pipeline(&pipelines.edge_detect);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn draw_blend_weight<'a>(
+        pass: &mut impl RenderEncoder<'a>,
+        pipelines: &'a Pipelines,
+        targets: &'a Targets,
+        bind_groups: &'a BindGroups,
+    ) {
+        pass.set_pipeline(&pipelines.blend_weight);
+        pass.set_bind_group(0, &bind_groups.blend_weight_bind_group, &[]);
+        match (&targets.tiles, &targets.edge_presence) {
+            (Some(tiles), _) => {
+                pass.set_vertex_buffer(0, tiles.tile_list.slice(..));
+                pass.draw_indirect(&tiles.draw_args, 0);
+            }
+            (None, Some(edge_presence)) => pass.draw_indirect(&edge_presence.pass_args, 0),
+            (None, None) => pass.draw(0..3, 0..1),
+        }
+    }
+
+    fn draw_neighborhood_blending<'a>(
+        pass: &mut impl RenderEncoder<'a>,
+        pipelines: &'a Pipelines,
+        targets: &'a Targets,
+        bind_group: &'a wgpu::BindGroup,
+    ) {
+        pass.set_pipeline(&pipelines.neighborhood_blending);
+        pass.set_bind_group(0, bind_group, &[]);
+        match (
+            &pipelines.neighborhood_blending_copy,
+            &targets.edge_presence,
+        ) {
+            (Some(copy), Some(edge_presence)) => {
+                pass.draw_indirect(&edge_presence.pass_args, 16);
+                pass.set_pipeline(copy);
+                pass.draw_indirect(&edge_presence.pass_args, 32);
+            }
+            _ => pass.draw(0..3, 0..1),
         }
     }
 }
@@ -639,71 +2651,1885 @@ impl BindGroups {
 struct SmaaTargetInner {
     pipelines: Pipelines,
     layouts: BindGroupLayouts,
-    resources: Resources,
+    resources: Arc<Resources>,
     targets: Targets,
+    intermediates: Intermediates,
     bind_groups: BindGroups,
+    bundles: Bundles,
     format: wgpu::TextureFormat,
+    // Format that the neighborhood blending pass renders to, see `blended_format`.
+    blended_format: wgpu::TextureFormat,
+    formats: IntermediateFormats,
+    tile_classification: bool,
+    skip_without_edges: bool,
+    // The final pass blends onto the output, which must be loaded rather than cleared.
+    blends_output: bool,
+    upscaling: Option<Upscaling>,
+}
+
+impl SmaaTargetInner {
+    /// Restrict the passes to the top left `region` of the color target.
+    fn set_region(&mut self, queue: &wgpu::Queue, region: (u32, u32)) {
+        if region == self.targets.region {
+            return;
+        }
+        self.targets.region = region;
+        queue.write_buffer(
+            &self.targets.rt_uniforms,
+            0,
+            &rt_metrics(self.targets.size, region),
+        );
+    }
+
+    /// Record the bundles again after the pipelines or bind groups changed.
+    fn rerecord_bundles(&mut self, device: &wgpu::Device) {
+        self.bundles = Bundles::new(
+            device,
+            self.blended_format,
+            self.formats,
+            &self.pipelines,
+            &self.targets,
+            &self.bind_groups,
+        );
+    }
+
+    /// Create the bind groups of the edge detection and neighborhood blending passes reading
+    /// `input`, which an image of a batch is processed from instead of the color target.
+    fn batch_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        input: &wgpu::TextureView,
+    ) -> (wgpu::BindGroup, wgpu::BindGroup) {
+        assert!(
+            !self.layouts.luma_input,
+            "SmaaConfig::luma_input is only supported for frames"
+        );
+        assert!(
+            !self.layouts.depth_input,
+            "SmaaEdgeDetection::Depth is only supported for frames"
+        );
+        assert!(
+            self.formats.secondary.is_none(),
+            "SmaaConfig::secondary_output is only supported for frames"
+        );
+        assert!(
+            self.formats.output_samples == 1,
+            "SmaaConfig::output_sample_count is only supported for frames"
+        );
+        let edge_detect = BindGroups::edge_detect(
+            device,
+            &self.layouts,
+            &self.resources,
+            &self.targets.rt_uniforms,
+            input,
+            None,
+        );
+        let neighborhood_blending = BindGroups::neighborhood_blending(
+            device,
+            &self.layouts,
+            &self.resources,
+            &self.targets.rt_uniforms,
+            &self.intermediates.blend_target,
+            input,
+        );
+        (edge_detect, neighborhood_blending)
+    }
+
+    /// Record the passes antialiasing the image bound by `bind_groups`, created by
+    /// `batch_bind_groups`, into `output`.
+    fn encode_batch_image(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_groups: &(wgpu::BindGroup, wgpu::BindGroup),
+        output: &wgpu::TextureView,
+    ) {
+        self.encode_blend_weights(encoder, Some(&bind_groups.0), None, None);
+        match self.upscaling {
+            Some(ref upscaling) if upscaling.pass.writes_storage() => {
+                self.encode_neighborhood_blending(
+                    encoder,
+                    Some(&bind_groups.1),
+                    &upscaling.output,
+                    FrameViews::default(),
+                    None,
+                    None,
+                );
+                upscaling.pass.encode(device, encoder, output);
+            }
+            _ => self.encode_neighborhood_blending(
+                encoder,
+                Some(&bind_groups.1),
+                output,
+                FrameViews::default(),
+                None,
+                None,
+            ),
+        }
+    }
+
+    /// Record the passes that detect edges and calculate the blend weights. Edges are detected
+    /// in the image bound by `edge_detect_bind_group`, or the color target if it is `None`.
+    fn encode_blend_weights(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        edge_detect_bind_group: Option<&wgpu::BindGroup>,
+        timestamps: Option<&wgpu::QuerySet>,
+        mut stage_hook: Option<&mut StageHook>,
+    ) {
+        {
+            let attachment = |view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            };
+            let color_attachments = match self.intermediates.luma_target {
+                Some(ref luma_target) => vec![
+                    attachment(&self.intermediates.edges_target),
+                    attachment(luma_target),
+                ],
+                None => vec![attachment(&self.intermediates.edges_target)],
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.intermediates.stencil_target,
+                    depth_ops: None,
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                }),
+                label: Some("smaa.render_pass.edge_detect"),
+                occlusion_query_set: None,
+                timestamp_writes: timestamps.map(|query_set| wgpu::RenderPassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: Some(timing::EDGE_DETECTION_BEGIN),
+                    end_of_pass_write_index: Some(timing::EDGE_DETECTION_END),
+                }),
+            });
+            if let Some((width, height)) = self.targets.scissor(SEARCH_MARGIN) {
+                rpass.set_scissor_rect(0, 0, width, height);
+            }
+            match edge_detect_bind_group {
+                Some(bind_group) => {
+                    Bundles::draw_edge_detect(&mut rpass, &self.pipelines, bind_group)
+                }
+                None => rpass.execute_bundles(&self.bundles.edge_detect),
+            }
+        }
+        if let Some(ref mut stage_hook) = stage_hook {
+            stage_hook(encoder, SmaaStage::EdgeDetection);
+        }
+        if let (Some(pipeline), Some(bind_group), Some(tiles)) = (
+            &self.pipelines.tile_classification,
+            &self.bind_groups.tile_classification_bind_group,
+            &self.targets.tiles,
+        ) {
+            encoder.copy_buffer_to_buffer(
+                &tiles.initial_draw_args,
+                0,
+                &tiles.draw_args,
+                0,
+                tiles.draw_args.size(),
+            );
+
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.compute_pass.tile_classification"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.dispatch_workgroups(tiles.tile_counts.0, tiles.tile_counts.1, 1);
+        }
+        if let (Some(pipeline), Some(bind_group), Some(edge_presence)) = (
+            &self.pipelines.edge_presence,
+            &self.bind_groups.edge_presence_bind_group,
+            &self.targets.edge_presence,
+        ) {
+            encoder.copy_buffer_to_buffer(
+                &edge_presence.initial_pass_args,
+                0,
+                &edge_presence.pass_args,
+                0,
+                edge_presence.pass_args.size(),
+            );
+
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.compute_pass.edge_presence"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, bind_group, &[]);
+            cpass.dispatch_workgroups(edge_presence.tile_counts.0, edge_presence.tile_counts.1, 1);
+        }
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.intermediates.blend_target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Pixels masked out by the stencil test must end up with zero weights.
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.intermediates.stencil_target,
+                    depth_ops: None,
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                }),
+                label: Some("smaa.render_pass.blend_weight"),
+                occlusion_query_set: None,
+                timestamp_writes: timestamps.map(|query_set| wgpu::RenderPassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: None,
+                    end_of_pass_write_index: Some(timing::BLEND_WEIGHTS_END),
+                }),
+            });
+            if let Some((width, height)) = self.targets.scissor(0) {
+                rpass.set_scissor_rect(0, 0, width, height);
+            }
+            rpass.execute_bundles(Some(&self.bundles.blend_weight));
+        }
+        if let Some(stage_hook) = stage_hook {
+            stage_hook(encoder, SmaaStage::BlendWeights);
+        }
+    }
+
+    /// Record the pass that blends the image bound by `bind_group`, or the color target if it is
+    /// `None`, into the output, and writes the secondary view of `frame_views` next to it if
+    /// [`SmaaConfig::secondary_output`] is set. Multisampled outputs are resolved into its
+    /// resolve target, if any.
+    fn encode_neighborhood_blending(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_group: Option<&wgpu::BindGroup>,
+        output_view: &wgpu::TextureView,
+        frame_views: FrameViews,
+        origin: Option<(u32, u32)>,
+        timestamps: Option<&wgpu::QuerySet>,
+    ) {
+        {
+            let attachment = |view, resolve_target, load| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: match load {
+                            true => wgpu::LoadOp::Load,
+                            false => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        },
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            };
+            let output = attachment(
+                output_view,
+                frame_views.resolve,
+                origin.is_some() || self.blends_output,
+            );
+            let color_attachments = match self.formats.secondary {
+                Some(_) => vec![
+                    output,
+                    attachment(
+                        frame_views.secondary.expect(MISSING_SECONDARY_VIEW),
+                        None,
+                        origin.is_some(),
+                    ),
+                ],
+                None => vec![output],
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: None,
+                label: Some("smaa.render_pass.neighborhood_blending"),
+                occlusion_query_set: None,
+                timestamp_writes: timestamps.map(|query_set| wgpu::RenderPassTimestampWrites {
+                    query_set,
+                    beginning_of_pass_write_index: None,
+                    end_of_pass_write_index: Some(timing::NEIGHBORHOOD_BLENDING_END),
+                }),
+            });
+            let (x, y) = origin.unwrap_or((0, 0));
+            if origin.is_some() {
+                let (width, height) = self.targets.size;
+                rpass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+            }
+            if let Some((width, height)) = self.targets.scissor(0) {
+                rpass.set_scissor_rect(x, y, width, height);
+            }
+            match bind_group {
+                Some(bind_group) => Bundles::draw_neighborhood_blending(
+                    &mut rpass,
+                    &self.pipelines,
+                    &self.targets,
+                    bind_group,
+                ),
+                None => rpass.execute_bundles(Some(&self.bundles.neighborhood_blending)),
+            }
+        }
+    }
+}
+
+enum Backend {
+    Render(Box<SmaaTargetInner>),
+    Compute(Box<compute::ComputeTargetInner>),
+    Cmaa2(Box<cmaa2::Cmaa2TargetInner>),
 }
 
 /// Wraps a color buffer, which it can resolve into an antialiased image using the
 /// [Subpixel Morphological Antialiasing (SMAA)](http://www.iryoku.com/smaa) algorithm.
 pub struct SmaaTarget {
-    inner: Option<SmaaTargetInner>,
+    inner: Option<Backend>,
+    format: wgpu::TextureFormat,
+    size: (u32, u32),
+    render_scale: f32,
+    config: SmaaConfig,
+    // Whether the target is twice the size given by the application, for `SmaaMode::Ssaa2x`.
+    supersampled: bool,
+    // Field that the next frame antialiases with `SmaaConfig::interlaced`.
+    field: SmaaField,
+    stage_hook: Option<Box<StageHook>>,
+    // Number of frames left that reuse the current blend weights.
+    reused_frames: u32,
+    timer: Option<PassTimer>,
+    non_finite: Option<NonFiniteScan>,
+    adaptive_quality: Option<AdaptiveQuality>,
+    layer_views: Option<LayerViews>,
+    // Copy of the last texture passed to `process_external` that couldn't be sampled directly.
+    external_scratch: Option<(wgpu::Texture, wgpu::TextureView)>,
+    suspended: Option<Suspended>,
+}
+
+/// What [`SmaaTarget::resume`] needs to rebuild a suspended target.
+struct Suspended {
+    mode: SmaaMode,
+    resources: Option<SmaaResources>,
+}
+
+/// Single-layer views of the textures last passed to [`SmaaTarget::process_layers`], with the
+/// bind groups reading each input layer when the passes are rendered.
+struct LayerViews {
+    input_id: wgpu::Id<wgpu::Texture>,
+    output_id: wgpu::Id<wgpu::Texture>,
+    layers: Vec<LayerView>,
+    // A layer can't be sampled by the pass that writes it, so layers processed in place are
+    // written into this texture and copied back.
+    in_place: Option<(wgpu::Texture, wgpu::TextureView)>,
 }
+struct LayerView {
+    input: wgpu::TextureView,
+    output: wgpu::TextureView,
+    bind_groups: Option<(wgpu::BindGroup, wgpu::BindGroup)>,
+}
+impl LayerViews {
+    fn new(
+        device: &wgpu::Device,
+        backend: &Backend,
+        input: &wgpu::Texture,
+        output: &wgpu::Texture,
+    ) -> Self {
+        let layer_view = |texture: &wgpu::Texture, layer| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("smaa.texture_view.layer"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                mip_level_count: Some(1),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        };
+        let layers = (0..input.depth_or_array_layers())
+            .map(|layer| {
+                let input = layer_view(input, layer);
+                let bind_groups = match backend {
+                    Backend::Render(inner) => Some(inner.batch_bind_groups(device, &input)),
+                    // The compute passes create their bind groups for every image, like for
+                    // frames.
+                    Backend::Compute(_) | Backend::Cmaa2(_) => None,
+                };
+                LayerView {
+                    input,
+                    output: layer_view(output, layer),
+                    bind_groups,
+                }
+            })
+            .collect();
+        let in_place = (input.global_id() == output.global_id()).then(|| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("smaa.texture.layer_in_place"),
+                size: wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..output.size()
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: output.format(),
+                usage: output.usage() | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&Default::default());
+            (texture, view)
+        });
+        LayerViews {
+            input_id: input.global_id(),
+            output_id: output.global_id(),
+            layers,
+            in_place,
+        }
+    }
+}
+
+impl SmaaTarget {
+    /// Create a new `SmaaTarget`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+    ) -> Self {
+        Self::with_config(
+            device,
+            queue,
+            width,
+            height,
+            format,
+            mode,
+            SmaaConfig::default(),
+        )
+    }
+
+    /// Create a new `SmaaTarget` with the preset that [`SmaaPreset::for_adapter`] picks for the
+    /// adapter the device was requested from, falling back to [`SmaaConfig::rgba_edges`] if the
+    /// adapter can't render to the format of the edges.
+    pub fn new_auto(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+    ) -> Self {
+        Self::with_config(
+            device,
+            queue,
+            width,
+            height,
+            format,
+            mode,
+            SmaaConfig {
+                preset: SmaaPreset::for_adapter(&adapter.get_info()),
+                rgba_edges: !SmaaPrecision::default().edges_renderable(adapter),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a new `SmaaTarget` using non-default settings.
+    ///
+    /// Unless [`SmaaConfig::resources`] is set, the lookup textures are decompressed or
+    /// generated on another thread while the pipelines are created. Without the
+    /// `embedded-textures` feature, this hides most of the time that generating them takes,
+    /// when there is a spare CPU core.
+    ///
+    /// Output formats that can't be rendered to, like `Rgba8Snorm`, are supported by blending
+    /// frames into an `Rgba16Float` texture that a final compute pass converts to the output.
+    /// The outputs then need [`wgpu::TextureUsages::STORAGE_BINDING`], and the scene has to be
+    /// rendered in [`SmaaTarget::scene_format`].
+    pub fn with_config(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+        mut config: SmaaConfig,
+    ) -> Self {
+        let supersampled = mode == SmaaMode::Ssaa2x;
+        assert!(
+            !supersampled
+                || (config.backend == SmaaBackend::Render
+                    && config.output_size.is_none()
+                    && !config.dynamic_resolution),
+            "SmaaMode::Ssaa2x is only supported by SmaaBackend::Render, without \
+            SmaaConfig::output_size or SmaaConfig::dynamic_resolution"
+        );
+        assert!(
+            !config.interlaced
+                || (config.backend == SmaaBackend::Render
+                    && mode != SmaaMode::Cmaa2
+                    && config.output_size.is_none()
+                    && config.output_transform == SmaaOutputTransform::Identity),
+            "SmaaConfig::interlaced is only supported by SmaaBackend::Render, without \
+            SmaaConfig::output_size or SmaaConfig::output_transform"
+        );
+        if config.tile_classification && !supports_indirect_compute(device) {
+            log::warn!("SmaaConfig::tile_classification isn't supported by the device");
+            config.tile_classification = false;
+        }
+        if config.skip_without_edges && !supports_indirect_compute(device) {
+            log::warn!("SmaaConfig::skip_without_edges isn't supported by the device");
+            config.skip_without_edges = false;
+        }
+        let (width, height) = target_size(&mut config, supersampled, (width, height));
+        let mode = match mode {
+            SmaaMode::Ssaa2x => SmaaMode::Smaa1X,
+            mode => mode,
+        };
+        let inner = Self::create_backend(device, queue, width, height, format, mode, &config);
+        assert_output_supported(inner.as_ref(), config.output_size, config.output_transform);
+        let render = matches!(inner, None | Some(Backend::Render(_)));
+        assert!(
+            config.output_blend_state.is_none()
+                || (render && !Upscaling::needed(device, format, &config)),
+            "SmaaConfig::output_blend_state is only supported by SmaaBackend::Render with outputs \
+            that are rendered to directly"
+        );
+        match config.debug_output {
+            SmaaDebugOutput::None => {}
+            SmaaDebugOutput::Difference { amplification } => assert!(
+                render && amplification > 0.0 && amplification.is_finite(),
+                "SmaaDebugOutput::Difference is only supported by SmaaBackend::Render and \
+                requires a finite, positive amplification"
+            ),
+        }
+        match config.output_encoding {
+            SmaaOutputEncoding::None => {}
+            SmaaOutputEncoding::Hdr10 {
+                paper_white_nits,
+                peak_nits,
+            } => assert!(
+                paper_white_nits > 0.0
+                    && paper_white_nits.is_finite()
+                    && peak_nits > 0.0
+                    && peak_nits.is_finite(),
+                "SmaaOutputEncoding::Hdr10 requires finite, positive luminances"
+            ),
+            SmaaOutputEncoding::ScRgb { paper_white_nits } => assert!(
+                paper_white_nits > 0.0 && paper_white_nits.is_finite(),
+                "SmaaOutputEncoding::ScRgb requires a finite, positive paper white luminance"
+            ),
+        }
+        assert!(
+            config.secondary_output == SmaaSecondaryOutput::None
+                || (render && !Upscaling::needed(device, format, &config)),
+            "SmaaConfig::secondary_output is only supported by SmaaBackend::Render with outputs \
+            that are rendered to directly"
+        );
+        assert!(
+            config.output_sample_count <= 1
+                || (render
+                    && !Upscaling::needed(device, format, &config)
+                    && config.secondary_output == SmaaSecondaryOutput::None),
+            "SmaaConfig::output_sample_count is only supported by SmaaBackend::Render with \
+            outputs that are rendered to directly, without SmaaConfig::secondary_output"
+        );
+        assert!(
+            !config.luma_output || render,
+            "SmaaConfig::luma_output is only supported by SmaaBackend::Render"
+        );
+        assert!(
+            !config.luma_input || render,
+            "SmaaConfig::luma_input is only supported by SmaaBackend::Render"
+        );
+        assert!(
+            !config.luma_input || config.edge_detection == SmaaEdgeDetection::Luma,
+            "SmaaConfig::luma_input requires SmaaEdgeDetection::Luma"
+        );
+        match config.edge_detection {
+            SmaaEdgeDetection::Luma => {}
+            SmaaEdgeDetection::Alpha | SmaaEdgeDetection::LumaAndAlpha => assert!(
+                !matches!(inner, Some(Backend::Cmaa2(_))),
+                "SmaaMode::Cmaa2 only supports SmaaEdgeDetection::Luma"
+            ),
+            SmaaEdgeDetection::Depth(projection) => {
+                assert!(
+                    render,
+                    "SmaaEdgeDetection::Depth is only supported by SmaaBackend::Render"
+                );
+                match projection {
+                    SmaaDepthProjection::Perspective { near, far, .. } => assert!(
+                        0.0 < near && near < far,
+                        "SmaaDepthProjection::Perspective requires 0 < near < far"
+                    ),
+                    SmaaDepthProjection::Orthographic {
+                        near,
+                        far,
+                        threshold,
+                        ..
+                    } => assert!(
+                        near < far
+                            && near.is_finite()
+                            && far.is_finite()
+                            && threshold > 0.0
+                            && threshold.is_finite(),
+                        "SmaaDepthProjection::Orthographic requires a finite near < far and a \
+                        finite, positive threshold"
+                    ),
+                }
+            }
+        }
+        let timer = match inner {
+            Some(_) if config.profile || config.time_budget.is_some() => {
+                PassTimer::new(device, queue)
+            }
+            _ => None,
+        };
+        let adaptive_quality = config
+            .time_budget
+            .filter(|_| timer.is_some() && !config.deterministic)
+            .map(AdaptiveQuality::new);
+        let non_finite = config.detect_non_finite.then(|| NonFiniteScan::new(device));
+
+        SmaaTarget {
+            inner,
+            format,
+            size: (width, height),
+            render_scale: 1.0,
+            config,
+            supersampled,
+            field: SmaaField::Top,
+            stage_hook: None,
+            reused_frames: 0,
+            timer,
+            non_finite,
+            adaptive_quality,
+            layer_views: None,
+            external_scratch: None,
+            suspended: None,
+        }
+    }
+
+    /// Create the passes and textures that antialias frames of the given size and format.
+    fn create_backend(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+        config: &SmaaConfig,
+    ) -> Option<Backend> {
+        match (mode, config.backend) {
+            (SmaaMode::Disabled, _) => None,
+            (SmaaMode::Cmaa2, _) => Some(Backend::Cmaa2(Box::new(cmaa2::Cmaa2TargetInner::new(
+                device, width, height, format, config,
+            )))),
+            (_, SmaaBackend::Compute | SmaaBackend::ComputeFused) => {
+                Some(Backend::Compute(Box::new(
+                    compute::ComputeTargetInner::new(device, queue, width, height, format, config),
+                )))
+            }
+            _ => {
+                let blended_format = blended_format(device, format);
+                let color_format = config.color_format.unwrap_or(blended_format);
+                let layouts = BindGroupLayouts::new(
+                    device,
+                    color_format,
+                    config.tile_classification,
+                    config.skip_without_edges,
+                    config.background_depth.is_some()
+                        || matches!(config.edge_detection, SmaaEdgeDetection::Depth(_)),
+                    config.luma_input,
+                    false,
+                );
+                let (resources, pipelines) =
+                    SmaaResources::for_config(device, queue, config, || {
+                        Pipelines::new(device, blended_format, &layouts, config)
+                    });
+                let targets = Targets::new(
+                    device,
+                    width,
+                    height,
+                    color_format,
+                    config.tile_classification,
+                    config.skip_without_edges,
+                );
+                let formats = IntermediateFormats::new(config);
+                let intermediates = Intermediates::new(device, width, height, formats);
+                let bind_groups =
+                    BindGroups::new(device, &layouts, &resources, &targets, &intermediates);
+                let upscaling = Upscaling::needed(device, format, config).then(|| {
+                    Upscaling::new(
+                        device,
+                        width,
+                        height,
+                        blended_format,
+                        format,
+                        config.upscale_filter,
+                    )
+                });
+                let bundles = Bundles::new(
+                    device,
+                    blended_format,
+                    formats,
+                    &pipelines,
+                    &targets,
+                    &bind_groups,
+                );
+
+                Some(Backend::Render(Box::new(SmaaTargetInner {
+                    layouts,
+                    pipelines,
+                    resources,
+                    targets,
+                    intermediates,
+                    bind_groups,
+                    bundles,
+                    format: color_format,
+                    blended_format,
+                    formats,
+                    tile_classification: config.tile_classification,
+                    skip_without_edges: config.skip_without_edges,
+                    blends_output: config.output_blend_state.is_some(),
+                    upscaling,
+                })))
+            }
+        }
+    }
+
+    /// Lookup textures and samplers used by this target, for sharing with other targets through
+    /// [`SmaaConfig::resources`]. Returns `None` if antialiasing is disabled or uses
+    /// [`SmaaMode::Cmaa2`], which doesn't need them.
+    pub fn resources(&self) -> Option<SmaaResources> {
+        match self.inner {
+            Some(Backend::Render(ref inner)) => Some(SmaaResources(inner.resources.clone())),
+            Some(Backend::Compute(ref inner)) => Some(SmaaResources(inner.resources())),
+            Some(Backend::Cmaa2(_)) | None => None,
+        }
+    }
+
+    /// GPU time taken by the passes of the most recently measured frame, if
+    /// [`SmaaConfig::profile`] is set and the device supports timing them.
+    ///
+    /// Measurements are read back asynchronously, so this lags a few frames behind and is
+    /// `None` until the first one arrives.
+    pub fn pass_times(&self) -> Option<SmaaPassTimes> {
+        self.timer.as_ref().and_then(PassTimer::latest)
+    }
+
+    /// Average, shortest and longest GPU time of each pass over the last 60 measured frames, if
+    /// [`SmaaConfig::profile`] is set and any frames have been measured.
+    pub fn stats(&self) -> Option<SmaaStats> {
+        self.timer.as_ref().and_then(PassTimer::stats)
+    }
+
+    /// NaN and infinite values found in the color target of the most recently scanned frame, if
+    /// [`SmaaConfig::detect_non_finite`] is set.
+    ///
+    /// Scans are read back asynchronously, so this lags a few frames behind and is `None` until
+    /// the first one arrives.
+    pub fn non_finite_pixels(&self) -> Option<SmaaNonFinitePixels> {
+        self.non_finite.as_ref().and_then(NonFiniteScan::latest)
+    }
+
+    /// Start collecting [`SmaaTarget::stats`] from scratch, for instance after changing
+    /// settings that affect performance.
+    pub fn reset_stats(&mut self) {
+        if let Some(ref mut timer) = self.timer {
+            timer.clear();
+        }
+    }
+
+    /// Quality preset currently used by the shaders.
+    pub fn preset(&self) -> SmaaPreset {
+        self.config.preset
+    }
+
+    /// Switch to a different quality preset, recompiling the shaders.
+    pub fn set_preset(&mut self, device: &wgpu::Device, preset: SmaaPreset) {
+        if preset == self.config.preset {
+            return;
+        }
+        self.config.preset = preset;
+        match self.inner {
+            Some(Backend::Render(ref mut inner)) => {
+                inner.pipelines =
+                    Pipelines::new(device, inner.blended_format, &inner.layouts, &self.config);
+                inner.rerecord_bundles(device);
+            }
+            Some(Backend::Compute(ref mut inner)) => {
+                inner.rebuild_pipelines(device, self.format, &self.config)
+            }
+            Some(Backend::Cmaa2(ref mut inner)) => {
+                inner.rebuild_pipelines(device, self.format, &self.config)
+            }
+            None => {}
+        }
+    }
+
+    /// Fraction of the size of the target that frames are antialiased at.
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Antialias the following frames at a fraction of the size of the target, if
+    /// [`SmaaConfig::dynamic_resolution`] is set.
+    ///
+    /// The scene must then be rendered into the top left [`SmaaTarget::render_size`] pixels of
+    /// the frame's color target, for instance by setting the viewport of the render pass, and
+    /// the output is upscaled from those. A depth view passed to
+    /// [`SmaaTarget::start_frame_with_depth`] keeps the size of the target, and
+    /// [`SmaaTarget::process_batch`] and the like always process images at the full size.
+    ///
+    /// Panics if `scale` isn't in `(0, 1]`.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        assert!(
+            scale > 0.0 && scale <= 1.0,
+            "render scale must be in (0, 1]"
+        );
+        if scale != self.render_scale {
+            self.render_scale = scale;
+            self.reused_frames = 0;
+        }
+    }
+
+    /// Size of the part of the frame's color target that the scene must be rendered into. This
+    /// is the size of the target unless [`SmaaTarget::set_render_scale`] lowered it. It is
+    /// twice its width and height with [`SmaaMode::Ssaa2x`], and half its height rounded up
+    /// with [`SmaaConfig::interlaced`].
+    ///
+    /// When antialiasing is disabled, this is the size of the output instead.
+    pub fn render_size(&self) -> (u32, u32) {
+        match self.inner {
+            None => self.output_size(),
+            Some(Backend::Render(_)) if self.config.dynamic_resolution => {
+                let scale =
+                    |size: u32| ((size as f32 * self.render_scale).round() as u32).clamp(1, size);
+                (scale(self.size.0), scale(self.size.1))
+            }
+            _ => self.size,
+        }
+    }
+
+    /// Field of the output that the next frame antialiases with [`SmaaConfig::interlaced`],
+    /// which the scene must be rendered for. Fields alternate with every frame that is
+    /// resolved, starting with [`SmaaField::Top`].
+    pub fn field(&self) -> SmaaField {
+        self.field
+    }
+
+    /// Pick the field that the next frame antialiases with [`SmaaConfig::interlaced`], for
+    /// sources that give the field of each frame, like interlaced video.
+    pub fn set_field(&mut self, field: SmaaField) {
+        self.field = field;
+    }
+
+    /// Size of the output views that frames are resolved into. Unless
+    /// [`SmaaConfig::output_size`] is set, this is the size of the target, with its width and
+    /// height swapped if [`SmaaConfig::output_transform`] is a quarter turn.
+    pub fn output_size(&self) -> (u32, u32) {
+        let (width, height) = self.size;
+        self.config
+            .output_size
+            .unwrap_or(match self.config.output_transform {
+                SmaaOutputTransform::Rotate90 | SmaaOutputTransform::Rotate270 => (height, width),
+                _ => (width, height),
+            })
+    }
+
+    /// Check that frames written at `origin`, or the top left corner, fit within outputs of
+    /// `outputs_size`.
+    fn check_output_fits(
+        &self,
+        origin: Option<(u32, u32)>,
+        outputs_size: (u32, u32),
+    ) -> Result<(), SmaaError> {
+        let (width, height) = self.output_size();
+        let (x, y) = origin.unwrap_or((0, 0));
+        let (right, bottom) = (x.checked_add(width), y.checked_add(height));
+        let fits = right.is_some_and(|right| right <= outputs_size.0)
+            && bottom.is_some_and(|bottom| bottom <= outputs_size.1);
+        match (fits, origin) {
+            (true, _) => Ok(()),
+            (false, None) => Err(SmaaError::OutputSize {
+                expected: self.output_size(),
+                found: outputs_size,
+            }),
+            (false, Some(origin)) => Err(SmaaError::OutputOrigin {
+                origin,
+                size: self.output_size(),
+                found: outputs_size,
+            }),
+        }
+    }
+
+    /// Panic unless frames written at `origin` fit within outputs of `outputs_size`, if known,
+    /// instead of letting wgpu reject the viewport.
+    fn assert_output_fits(&self, origin: Option<(u32, u32)>, outputs_size: Option<(u32, u32)>) {
+        if let Some(Err(error)) = outputs_size.map(|size| self.check_output_fits(origin, size)) {
+            panic!("{error}");
+        }
+    }
+
+    /// Luma of the most recent frame, written by edge detection if [`SmaaConfig::luma_output`]
+    /// is set. Returns `None` otherwise, or if antialiasing is disabled.
+    ///
+    /// Only the top left [`SmaaTarget::render_size`] pixels belong to the frame, since the
+    /// texture can be larger than that with [`SmaaConfig::resize_slack`] or
+    /// [`SmaaConfig::dynamic_resolution`]. Frames that reuse the blend weights because of
+    /// [`SmaaConfig::frame_interval`] don't update it.
+    pub fn luma_view(&self) -> Option<&wgpu::TextureView> {
+        match self.inner {
+            Some(Backend::Render(ref inner)) => inner.intermediates.luma_target.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Edges of the most recent frame, written by edge detection into the red and green channels
+    /// for the left and top edges of each pixel. Returns `None` unless the target uses
+    /// [`SmaaBackend::Render`] with antialiasing enabled.
+    ///
+    /// Like [`SmaaTarget::luma_view`], only the top left [`SmaaTarget::render_size`] pixels
+    /// belong to the frame. The view stays the same until the target is resized, so bind groups
+    /// of it can be created ahead of a hook set with [`SmaaTarget::set_stage_hook`].
+    pub fn edges_view(&self) -> Option<&wgpu::TextureView> {
+        match self.inner {
+            Some(Backend::Render(ref inner)) => Some(&inner.intermediates.edges_target),
+            _ => None,
+        }
+    }
+
+    /// Blend weights of the most recent frame, written by the blend weight calculation. Returns
+    /// `None` unless the target uses [`SmaaBackend::Render`] with antialiasing enabled, like
+    /// [`SmaaTarget::edges_view`].
+    pub fn blend_weights_view(&self) -> Option<&wgpu::TextureView> {
+        match self.inner {
+            Some(Backend::Render(ref inner)) => Some(&inner.intermediates.blend_target),
+            _ => None,
+        }
+    }
+
+    /// Call `hook` after each pass of the frames that are resolved, to record work of the
+    /// application at that point of the frame, like outlines drawn from
+    /// [`SmaaTarget::edges_view`] or effects composited on top of the output.
+    ///
+    /// Frames that reuse the blend weights because of [`SmaaConfig::frame_interval`] only call
+    /// it for [`SmaaStage::NeighborhoodBlending`], after every output was written. With
+    /// [`SmaaConfig::profile`], the time of its work counts towards the following pass. Images
+    /// antialiased by the `process` methods don't call it. Panics unless the target uses
+    /// [`SmaaBackend::Render`] or antialiasing is disabled, in which case the hook is never
+    /// called.
+    pub fn set_stage_hook(
+        &mut self,
+        hook: impl FnMut(&mut wgpu::CommandEncoder, SmaaStage) + Send + Sync + 'static,
+    ) {
+        assert!(
+            matches!(self.inner, None | Some(Backend::Render(_))),
+            "stage hooks are only supported by SmaaBackend::Render"
+        );
+        self.stage_hook = Some(Box::new(hook));
+    }
+
+    /// Stop calling the hook set with [`SmaaTarget::set_stage_hook`].
+    pub fn remove_stage_hook(&mut self) {
+        self.stage_hook = None;
+    }
+
+    /// Format of the frame's color target, which the scene has to be rendered in. This is the
+    /// output format unless [`SmaaConfig::color_format`] is set, or the output can't be rendered
+    /// to and frames are written to it by a compute pass instead, in which case the scene is
+    /// rendered in `Rgba16Float`.
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        match self.inner {
+            Some(Backend::Render(ref inner)) => inner.format,
+            _ => self.config.color_format.unwrap_or(self.format),
+        }
+    }
+
+    /// Change [`SmaaConfig::output_size`], for instance after the window was resized while the
+    /// target keeps rendering at a fixed resolution.
+    ///
+    /// Panics with [`SmaaMode::Ssaa2x`] or [`SmaaConfig::interlaced`], which set the output size
+    /// themselves.
+    pub fn set_output_size(&mut self, device: &wgpu::Device, output_size: Option<(u32, u32)>) {
+        assert!(
+            !self.supersampled && !self.config.interlaced,
+            "SmaaMode::Ssaa2x and SmaaConfig::interlaced don't support SmaaConfig::output_size"
+        );
+        assert_output_supported(
+            self.inner.as_ref(),
+            output_size,
+            self.config.output_transform,
+        );
+        self.config.output_size = output_size;
+        self.update_upscaling(device);
+    }
+
+    /// Change [`SmaaConfig::output_transform`], for instance after the surface reported a new
+    /// transform because the device was rotated.
+    ///
+    /// Panics with [`SmaaConfig::interlaced`].
+    pub fn set_output_transform(
+        &mut self,
+        device: &wgpu::Device,
+        output_transform: SmaaOutputTransform,
+    ) {
+        assert_output_supported(
+            self.inner.as_ref(),
+            self.config.output_size,
+            output_transform,
+        );
+        assert!(
+            !self.config.interlaced,
+            "SmaaConfig::interlaced doesn't support SmaaConfig::output_transform"
+        );
+        let quarter_turn = |transform| {
+            matches!(
+                transform,
+                SmaaOutputTransform::Rotate90 | SmaaOutputTransform::Rotate270
+            )
+        };
+        // The output of `SmaaMode::Ssaa2x` keeps the size of the frames, turned with them.
+        if self.supersampled
+            && quarter_turn(output_transform) != quarter_turn(self.config.output_transform)
+        {
+            self.config.output_size = self
+                .config
+                .output_size
+                .map(|(width, height)| (height, width));
+        }
+        self.config.output_transform = output_transform;
+        self.update_upscaling(device);
+    }
+
+    /// Create or drop the texture for the final pass after the output settings changed.
+    fn update_upscaling(&mut self, device: &wgpu::Device) {
+        if let Some(Backend::Render(ref mut inner)) = self.inner {
+            let needed = Upscaling::needed(device, self.format, &self.config);
+            if needed != inner.upscaling.is_some() {
+                inner.upscaling = needed.then(|| {
+                    Upscaling::new(
+                        device,
+                        self.size.0,
+                        self.size.1,
+                        inner.blended_format,
+                        self.format,
+                        self.config.upscale_filter,
+                    )
+                });
+            }
+        }
+    }
+
+    /// Resize the render target.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let (width, height) = target_size(&mut self.config, self.supersampled, (width, height));
+        self.size = (width, height);
+        self.reused_frames = 0;
+        self.layer_views = None;
+        self.external_scratch = None;
+        match self.inner {
+            Some(Backend::Render(ref mut inner)) => {
+                inner.targets = Targets::new(
+                    device,
+                    width,
+                    height,
+                    inner.format,
+                    inner.tile_classification,
+                    inner.skip_without_edges,
+                );
+                let intermediates = &inner.intermediates;
+                if !fits_with_slack(
+                    (intermediates.width, intermediates.height),
+                    (width, height),
+                    self.config.resize_slack,
+                ) {
+                    inner.intermediates = Intermediates::new(device, width, height, inner.formats);
+                }
+                inner.bind_groups = BindGroups::new(
+                    device,
+                    &inner.layouts,
+                    &inner.resources,
+                    &inner.targets,
+                    &inner.intermediates,
+                );
+                if inner.upscaling.is_some() {
+                    inner.upscaling = Some(Upscaling::new(
+                        device,
+                        width,
+                        height,
+                        inner.blended_format,
+                        self.format,
+                        self.config.upscale_filter,
+                    ));
+                }
+                inner.rerecord_bundles(device);
+            }
+            Some(Backend::Compute(ref mut inner)) => {
+                inner.resize(device, width, height, self.config.resize_slack)
+            }
+            Some(Backend::Cmaa2(ref mut inner)) => inner.resize(device, width, height),
+            None => {}
+        }
+    }
+
+    /// Release the textures and passes that depend on the size and format of the output, for
+    /// instance when an Android surface is destroyed as the app goes into the background.
+    ///
+    /// The lookup textures are kept, so [`SmaaTarget::resume`] doesn't have to decompress or
+    /// generate them again. Until then, frames are resolved as if antialiasing was disabled.
+    /// Does nothing if the target is already suspended.
+    pub fn suspend(&mut self) {
+        if self.suspended.is_some() {
+            return;
+        }
+        let mode = match self.inner {
+            Some(Backend::Render(_) | Backend::Compute(_)) => SmaaMode::Smaa1X,
+            Some(Backend::Cmaa2(_)) => SmaaMode::Cmaa2,
+            None => SmaaMode::Disabled,
+        };
+        self.suspended = Some(Suspended {
+            mode,
+            resources: self.resources(),
+        });
+        self.inner = None;
+        self.layer_views = None;
+        self.external_scratch = None;
+    }
 
-impl SmaaTarget {
-    /// Create a new `SmaaTarget`.
-    pub fn new(
+    /// Rebuild a target released by [`SmaaTarget::suspend`] for a new surface, whose size and
+    /// format may differ from the previous one. The settings of the target are kept, and the
+    /// device must be the one it was created with.
+    ///
+    /// Calling this on a target that isn't suspended recreates it for the new size and format.
+    pub fn resume(
+        &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
-        mode: SmaaMode,
-    ) -> Self {
-        if let SmaaMode::Disabled = mode {
-            return SmaaTarget { inner: None };
-        }
-
-        let layouts = BindGroupLayouts::new(device);
-        let pipelines = Pipelines::new(device, format, &layouts);
-        let resources = Resources::new(device, queue);
-        let targets = Targets::new(device, width, height, format);
-        let bind_groups = BindGroups::new(device, &layouts, &resources, &targets);
-
-        SmaaTarget {
-            inner: Some(SmaaTargetInner {
-                layouts,
-                pipelines,
-                resources,
-                targets,
-                bind_groups,
-                format,
-            }),
-        }
+    ) {
+        self.suspend();
+        let suspended = self.suspended.take().unwrap();
+        let (width, height) = target_size(&mut self.config, self.supersampled, (width, height));
+        let config = SmaaConfig {
+            resources: suspended
+                .resources
+                .or_else(|| self.config.resources.clone()),
+            ..self.config.clone()
+        };
+        self.inner = Self::create_backend(
+            device,
+            queue,
+            width,
+            height,
+            format,
+            suspended.mode,
+            &config,
+        );
+        self.format = format;
+        self.size = (width, height);
+        self.reused_frames = 0;
     }
 
-    /// Resize the render target.
-    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
-        if let Some(ref mut inner) = self.inner {
-            inner.targets = Targets::new(device, width, height, inner.format);
-            inner.bind_groups =
-                BindGroups::new(device, &inner.layouts, &inner.resources, &inner.targets);
-        }
+    /// Whether the target was released by [`SmaaTarget::suspend`] and not resumed since.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
     }
 
     /// Start rendering a frame. Dropping or calling resolve() the returned frame object will resolve the scene into the provided output_view.
+    /// What dropping the frame does can be changed with [`SmaaConfig::drop_policy`].
     pub fn start_frame<'a>(
         &'a mut self,
         device: &'a wgpu::Device,
         queue: &'a wgpu::Queue,
         output_view: &'a wgpu::TextureView,
     ) -> SmaaFrame<'a> {
-        SmaaFrame {
-            target: self,
+        SmaaFrame::new(self, device, queue, OutputView::Borrowed(output_view), None)
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_frame`], also providing the scene's
+    /// depth buffer for [`SmaaConfig::background_depth`] or [`SmaaEdgeDetection::Depth`]. The
+    /// view must only contain the depth aspect and be the same size as the target.
+    pub fn start_frame_with_depth<'a>(
+        &'a mut self,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        output_view: &'a wgpu::TextureView,
+        depth_view: &'a wgpu::TextureView,
+    ) -> SmaaFrame<'a> {
+        SmaaFrame::new(
+            self,
             device,
             queue,
-            output_view,
+            OutputView::Borrowed(output_view),
+            Some(depth_view),
+        )
+    }
+
+    /// Check that frames can be resolved into `output` with the settings of the target: that it
+    /// is a 2D texture in the target's format with the usages that the final pass writes it
+    /// with and [`SmaaConfig::output_sample_count`] samples. Its size is only checked by
+    /// [`SmaaFrame::try_resolve`], once the origin that the frame is written at is known.
+    pub fn validate_output(&self, output: &wgpu::Texture) -> Result<(), SmaaError> {
+        if output.dimension() != wgpu::TextureDimension::D2 {
+            return Err(SmaaError::OutputDimension(output.dimension()));
+        }
+        let usage = match self.inner {
+            Some(Backend::Render(ref inner)) => match inner.upscaling {
+                Some(ref upscaling) if upscaling.pass.writes_storage() => {
+                    wgpu::TextureUsages::STORAGE_BINDING
+                }
+                _ => wgpu::TextureUsages::RENDER_ATTACHMENT,
+            },
+            Some(Backend::Compute(_) | Backend::Cmaa2(_)) => wgpu::TextureUsages::STORAGE_BINDING,
+            None => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
+        // The pipelines are created for exactly the target's format, which the default view of
+        // the output has to match.
+        if output.format() != self.format {
+            return Err(SmaaError::OutputFormat {
+                expected: self.format,
+                found: output.format(),
+            });
+        }
+        if !output.usage().contains(usage) {
+            return Err(SmaaError::OutputUsage {
+                missing: usage - output.usage(),
+            });
+        }
+        let sample_count = self.config.output_sample_count.max(1);
+        if output.sample_count() != sample_count {
+            return Err(SmaaError::OutputSampleCount {
+                expected: sample_count,
+                found: output.sample_count(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_frame`] or
+    /// [`SmaaTarget::start_frame_with_depth`], after checking `output` with
+    /// [`SmaaTarget::validate_output`] and that a depth view is given if the settings need one.
+    /// `output_view` must be a view of `output`. Resolve the frame with
+    /// [`SmaaFrame::try_resolve`] to check the views set on it and the size of `output` as well.
+    pub fn try_start_frame<'a>(
+        &'a mut self,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        output: &wgpu::Texture,
+        output_view: &'a wgpu::TextureView,
+        depth_view: Option<&'a wgpu::TextureView>,
+    ) -> Result<SmaaFrame<'a>, SmaaError> {
+        self.validate_output(output)?;
+        let needs_depth = match self.inner {
+            Some(Backend::Render(ref inner)) => inner.layouts.depth_input,
+            Some(Backend::Compute(_)) => self.config.background_depth.is_some(),
+            _ => false,
+        };
+        if needs_depth && depth_view.is_none() {
+            return Err(SmaaError::MissingDepthView);
+        }
+        let mut frame = SmaaFrame::new(
+            self,
+            device,
+            queue,
+            OutputView::Borrowed(output_view),
+            depth_view,
+        );
+        frame.outputs_size = Some((output.width(), output.height()));
+        Ok(frame)
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_frame`] that is resolved into one layer
+    /// of a 2D array texture, for layered compositors and XR runtimes.
+    ///
+    /// Panics if `output` isn't a 2D texture in the target's format with the size of
+    /// [`SmaaTarget::output_size`], or doesn't have the layer.
+    pub fn start_frame_into_layer<'a>(
+        &'a mut self,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        output: &wgpu::Texture,
+        layer: u32,
+    ) -> SmaaFrame<'a> {
+        assert_eq!(
+            output.dimension(),
+            wgpu::TextureDimension::D2,
+            "layered outputs must be 2D textures"
+        );
+        assert_eq!(
+            output.format(),
+            self.format,
+            "layered outputs must have the format of the target"
+        );
+        assert_eq!(
+            (output.width(), output.height()),
+            self.output_size(),
+            "layered outputs must have the output size of the target"
+        );
+        assert!(
+            layer < output.depth_or_array_layers(),
+            "layer {layer} is out of range for an output with {} layers",
+            output.depth_or_array_layers()
+        );
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("smaa.texture_view.output_layer"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        SmaaFrame::new(self, device, queue, OutputView::Owned(output_view), None)
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_frame`], without borrowing the device
+    /// and queue until the frame is resolved with [`SmaaDetachedFrame::resolve`].
+    ///
+    /// This leaves the application free to call methods needing mutable access to whatever
+    /// owns the device while it renders the scene.
+    pub fn start_detached_frame<'a>(
+        &'a mut self,
+        output_view: &'a wgpu::TextureView,
+    ) -> SmaaDetachedFrame<'a> {
+        SmaaDetachedFrame::new(self, output_view, None)
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_detached_frame`], also providing the
+    /// scene's depth buffer like [`SmaaTarget::start_frame_with_depth`].
+    pub fn start_detached_frame_with_depth<'a>(
+        &'a mut self,
+        output_view: &'a wgpu::TextureView,
+        depth_view: &'a wgpu::TextureView,
+    ) -> SmaaDetachedFrame<'a> {
+        SmaaDetachedFrame::new(self, output_view, Some(depth_view))
+    }
+
+    /// Antialias several images at once, recording every pass into a single command buffer.
+    /// This saves the overhead of a submission per image when processing many small images.
+    ///
+    /// Each `(input, output)` pair is processed like a frame rendered into
+    /// [`SmaaTarget::start_frame`] and resolved into `output`. The inputs must be the same size
+    /// and format as the frame's color target and be usable as `TEXTURE_BINDING`, while the
+    /// outputs need the same usages as the views passed to `start_frame`. The intermediate
+    /// textures and pipelines are shared between the images, so only the bind groups reading
+    /// the inputs are created for each of them. Batches aren't timed, and the next frame
+    /// calculates its blend weights from scratch even with [`SmaaConfig::frame_interval`].
+    ///
+    /// Panics if antialiasing is disabled or [`SmaaConfig::background_depth`] is set.
+    pub fn process_batch(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[(&wgpu::TextureView, &wgpu::TextureView)],
+    ) {
+        assert!(
+            self.config.background_depth.is_none(),
+            "process_batch doesn't support SmaaConfig::background_depth"
+        );
+        let backend = self
+            .inner
+            .as_ref()
+            .expect("process_batch requires antialiasing to be enabled");
+        self.reused_frames = 0;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.command_encoder.batch"),
+        });
+        Self::encode_batch(backend, device, &mut encoder, images);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// Antialias a texture created outside of wgpu into `output`, for instance a decoded video
+    /// frame that was imported from a dma-buf or a D3D shared handle with
+    /// [`wgpu::Device::create_texture_from_hal`].
+    ///
+    /// `input` is processed like an image passed to [`SmaaTarget::process_batch`], so it must
+    /// have the size of the target and be in [`SmaaTarget::scene_format`]. Only its first mip
+    /// level and layer are read. Imported textures often only have the usages that the other API
+    /// allows: with `TEXTURE_BINDING` the input is sampled directly, and otherwise it needs
+    /// `COPY_SRC` to be copied into a texture owned by the target first. Multi-planar formats
+    /// like `NV12` have to be converted to RGB beforehand.
+    ///
+    /// wgpu tracks the state of the imported texture like that of any other, so no layout
+    /// transitions are needed here, but the work of the API that produced the image has to be
+    /// complete before this submits: wait for its fence, or signal a semaphore that the queue
+    /// waits on, and hand the image over from the producer's queue family where that applies.
+    ///
+    /// Panics if antialiasing is disabled, [`SmaaConfig::background_depth`] is set or `input`
+    /// can't be processed.
+    pub fn process_external(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input: &wgpu::Texture,
+        output: &wgpu::TextureView,
+    ) {
+        let scene_format = self.scene_format();
+        assert!(
+            !input.format().is_multi_planar_format(),
+            "external textures in multi-planar formats have to be converted to RGB first"
+        );
+        assert_eq!(
+            input.format(),
+            scene_format,
+            "external textures must be in the target's scene format"
+        );
+        assert!(
+            input.dimension() == wgpu::TextureDimension::D2 && input.sample_count() == 1,
+            "external textures must be 2D and not multisampled"
+        );
+        assert_eq!(
+            (input.width(), input.height()),
+            self.size,
+            "external textures must have the size of the target"
+        );
+        assert!(
+            self.config.background_depth.is_none(),
+            "process_external doesn't support SmaaConfig::background_depth"
+        );
+        let backend = self
+            .inner
+            .as_ref()
+            .expect("process_external requires antialiasing to be enabled");
+        self.reused_frames = 0;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.command_encoder.external"),
+        });
+        let input_view;
+        let view = if input.usage().contains(wgpu::TextureUsages::TEXTURE_BINDING) {
+            input_view = input.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("smaa.texture_view.external"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                mip_level_count: Some(1),
+                array_layer_count: Some(1),
+                ..Default::default()
+            });
+            &input_view
+        } else {
+            assert!(
+                input.usage().contains(wgpu::TextureUsages::COPY_SRC),
+                "external textures need TEXTURE_BINDING or COPY_SRC usage"
+            );
+            let (scratch, scratch_view) = self.external_scratch.get_or_insert_with(|| {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("smaa.texture.external"),
+                    size: wgpu::Extent3d {
+                        width: self.size.0,
+                        height: self.size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: scene_format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                });
+                let view = texture.create_view(&Default::default());
+                (texture, view)
+            });
+            encoder.copy_texture_to_texture(
+                input.as_image_copy(),
+                scratch.as_image_copy(),
+                wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..scratch.size()
+                },
+            );
+            scratch_view
+        };
+        Self::encode_batch(backend, device, &mut encoder, &[(view, output)]);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// Antialias every layer of a 2D array texture into the same layer of `output`, for
+    /// instance the layers of a shadow atlas or the eyes of a stereo frame.
+    ///
+    /// The layers are processed in a single command buffer like the images passed to
+    /// [`SmaaTarget::process_batch`], with the same requirements on the usages of `input` and
+    /// `output`. Both textures need as many layers as each other and must have the size of the
+    /// target, with `input` in [`SmaaTarget::scene_format`] and `output` in the target's format.
+    /// The views of the layers and the bind groups reading them are kept until the next call
+    /// with different textures. If `input` and `output` are the same texture, each layer is
+    /// written into a texture owned by the target and copied back, which needs `COPY_DST` usage.
+    ///
+    /// This doesn't work on wgpu's GL backend, which ignores the layer range of texture views
+    /// when binding them.
+    ///
+    /// Panics if antialiasing is disabled or [`SmaaConfig::background_depth`] is set.
+    pub fn process_layers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input: &wgpu::Texture,
+        output: &wgpu::Texture,
+    ) {
+        assert_eq!(
+            input.depth_or_array_layers(),
+            output.depth_or_array_layers(),
+            "input and output must have the same number of layers"
+        );
+        assert_eq!(
+            (input.width(), input.height()),
+            self.size,
+            "input layers must have the size of the target"
+        );
+        assert_eq!(
+            (output.width(), output.height()),
+            self.size,
+            "output layers must have the size of the target"
+        );
+        assert_eq!(
+            input.format(),
+            self.scene_format(),
+            "input layers must be in the target's scene format"
+        );
+        assert_eq!(
+            output.format(),
+            self.format,
+            "output layers must be in the target's format"
+        );
+        assert!(
+            self.config.background_depth.is_none(),
+            "process_layers doesn't support SmaaConfig::background_depth"
+        );
+        let backend = self
+            .inner
+            .as_ref()
+            .expect("process_layers requires antialiasing to be enabled");
+        self.reused_frames = 0;
+
+        let views = match self.layer_views.take() {
+            Some(views)
+                if views.input_id == input.global_id() && views.output_id == output.global_id() =>
+            {
+                views
+            }
+            _ => LayerViews::new(device, backend, input, output),
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.command_encoder.layers"),
+        });
+        for (layer, view) in (0..).zip(&views.layers) {
+            let output_view = match views.in_place {
+                Some((_, ref in_place_view)) => in_place_view,
+                None => &view.output,
+            };
+            match (backend, &view.bind_groups) {
+                (Backend::Render(inner), Some(bind_groups)) => {
+                    inner.encode_batch_image(device, &mut encoder, bind_groups, output_view)
+                }
+                _ => {
+                    Self::encode_batch(backend, device, &mut encoder, &[(&view.input, output_view)])
+                }
+            }
+            if let Some((ref in_place, _)) = views.in_place {
+                encoder.copy_texture_to_texture(
+                    in_place.as_image_copy(),
+                    wgpu::ImageCopyTexture {
+                        origin: wgpu::Origin3d {
+                            z: layer,
+                            ..Default::default()
+                        },
+                        ..output.as_image_copy()
+                    },
+                    in_place.size(),
+                );
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+        self.layer_views = Some(views);
+    }
+
+    /// Antialias the six faces of a cubemap, writing them into `output` or back into `cubemap`
+    /// if it is `None`. This is [`SmaaTarget::process_layers`] with both textures checked to
+    /// have six layers, and has the same requirements; when processing in place, `cubemap`
+    /// needs the usages of both the input and the output. The faces are processed separately,
+    /// so edges are only detected within each face and not across the seams between them.
+    ///
+    /// Like `process_layers`, this doesn't work on wgpu's GL backend.
+    pub fn process_cubemap(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cubemap: &wgpu::Texture,
+        output: Option<&wgpu::Texture>,
+    ) {
+        let output = output.unwrap_or(cubemap);
+        assert!(
+            cubemap.depth_or_array_layers() == 6 && output.depth_or_array_layers() == 6,
+            "cubemaps must have six layers"
+        );
+        self.process_layers(device, queue, cubemap, output);
+    }
+
+    /// Undo [`SmaaTarget::set_render_scale`] and the output settings of a frame, so that images
+    /// processed between frames are processed at the full size.
+    fn reset_render_region(&mut self, queue: &wgpu::Queue) {
+        if let Some(Backend::Render(ref mut inner)) = self.inner {
+            let size = inner.targets.size;
+            inner.set_region(queue, size);
+            if let Some(ref mut upscaling) = inner.upscaling {
+                upscaling.pass.set_params(
+                    queue,
+                    size,
+                    size,
+                    SmaaOutputTransform::Identity,
+                    None,
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Record the passes antialiasing each `(input, output)` pair.
+    fn encode_batch(
+        backend: &Backend,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &[(&wgpu::TextureView, &wgpu::TextureView)],
+    ) {
+        match backend {
+            Backend::Render(inner) => {
+                for &(input, output) in images {
+                    let bind_groups = inner.batch_bind_groups(device, input);
+                    inner.encode_batch_image(device, encoder, &bind_groups, output);
+                }
+            }
+            Backend::Compute(inner) => inner.encode_batch(device, encoder, images),
+            Backend::Cmaa2(inner) => inner.encode_batch(device, encoder, images),
+        }
+    }
+
+    /// Run the passes antialiasing the frame rendered into the color target into `output_view`.
+    fn resolve_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        output_view: &wgpu::TextureView,
+        extra_outputs: &[&wgpu::TextureView],
+        output_origin: Option<(u32, u32)>,
+        frame_views: FrameViews,
+    ) {
+        let preset = self.config.preset;
+        let times = self.timer.as_mut().and_then(PassTimer::update);
+        let next_preset = match (times, &mut self.adaptive_quality) {
+            (Some(times), Some(adaptive_quality)) => adaptive_quality.update(times.total(), preset),
+            _ => None,
+        };
+        if let Some(next_preset) = next_preset {
+            self.set_preset(device, next_preset);
+        }
+        if let Some(ref mut non_finite) = self.non_finite {
+            non_finite.update();
+        }
+
+        let render_size = self.render_size();
+        let output_size = self.output_size();
+        let output_transform = self.config.output_transform;
+        let field = self.config.interlaced.then_some(self.field);
+        if let Some(ref mut backend) = self.inner {
+            let update_weights = self.reused_frames == 0;
+            self.reused_frames = match update_weights {
+                true => self.config.frame_interval.saturating_sub(1),
+                false => self.reused_frames - 1,
+            };
+            // Only frames that run every pass are timed.
+            let timestamps = self
+                .timer
+                .as_ref()
+                .filter(|_| update_weights)
+                .and_then(PassTimer::query_set);
+
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("smaa.command_encoder"),
+            });
+            let scanned = self.non_finite.as_mut().is_some_and(|non_finite| {
+                let color_view = match backend {
+                    Backend::Render(inner) => &inner.targets.color_target,
+                    Backend::Compute(inner) => inner.color_target(),
+                    Backend::Cmaa2(inner) => inner.color_target(),
+                };
+                non_finite.encode(device, queue, &mut encoder, color_view, render_size)
+            });
+            // Only the passes writing the first output are timed.
+            let outputs = std::iter::once(output_view).chain(extra_outputs.iter().copied());
+            match backend {
+                Backend::Render(inner) => {
+                    inner.set_region(queue, render_size);
+                    if update_weights {
+                        // The bundle only exists when the bind group doesn't depend on the
+                        // frame's depth or luma view.
+                        let frame_bind_group = inner.bundles.edge_detect.is_none().then(|| {
+                            let layouts = &inner.layouts;
+                            let color_view = match layouts.luma_input {
+                                true => frame_views.luma.expect(MISSING_LUMA_VIEW),
+                                false => &inner.targets.color_target,
+                            };
+                            let depth_view = layouts
+                                .depth_input
+                                .then(|| frame_views.depth.expect(MISSING_DEPTH_VIEW));
+                            BindGroups::edge_detect(
+                                device,
+                                layouts,
+                                &inner.resources,
+                                &inner.targets.rt_uniforms,
+                                color_view,
+                                depth_view,
+                            )
+                        });
+                        inner.encode_blend_weights(
+                            &mut encoder,
+                            frame_bind_group.as_ref(),
+                            timestamps,
+                            self.stage_hook.as_deref_mut(),
+                        );
+                    }
+                    if let Some(ref mut upscaling) = inner.upscaling {
+                        upscaling.pass.set_params(
+                            queue,
+                            render_size,
+                            output_size,
+                            output_transform,
+                            output_origin,
+                            field,
+                        );
+                    }
+                    let size = inner.targets.size;
+                    let transformed = render_size != size
+                        || output_size != size
+                        || output_transform != SmaaOutputTransform::Identity
+                        || field.is_some();
+                    match inner.upscaling {
+                        Some(ref upscaling) if transformed || upscaling.pass.writes_storage() => {
+                            inner.encode_neighborhood_blending(
+                                &mut encoder,
+                                None,
+                                &upscaling.output,
+                                FrameViews::default(),
+                                None,
+                                timestamps,
+                            );
+                            for output in outputs {
+                                upscaling.pass.encode(device, &mut encoder, output);
+                            }
+                        }
+                        _ => {
+                            for (i, output) in outputs.enumerate() {
+                                inner.encode_neighborhood_blending(
+                                    &mut encoder,
+                                    None,
+                                    output,
+                                    FrameViews {
+                                        resolve: frame_views.resolve.filter(|_| i == 0),
+                                        ..frame_views
+                                    },
+                                    output_origin,
+                                    timestamps.filter(|_| i == 0),
+                                );
+                            }
+                        }
+                    }
+                    if let Some(ref mut stage_hook) = self.stage_hook {
+                        stage_hook(&mut encoder, SmaaStage::NeighborhoodBlending);
+                    }
+                }
+                // Further outputs reuse the blend weights calculated for the first one.
+                Backend::Compute(inner) => {
+                    for (i, output) in outputs.enumerate() {
+                        inner.encode(
+                            device,
+                            &mut encoder,
+                            output,
+                            frame_views.depth,
+                            update_weights && i == 0,
+                            timestamps.filter(|_| i == 0),
+                        );
+                    }
+                }
+                // CMAA2 has nothing to reuse between frames or outputs.
+                Backend::Cmaa2(inner) => {
+                    for (i, output) in outputs.enumerate() {
+                        inner.encode(device, &mut encoder, output, timestamps.filter(|_| i == 0));
+                    }
+                }
+            }
+            let timed = timestamps.is_some();
+            if let (true, Some(timer)) = (timed, &self.timer) {
+                timer.resolve(&mut encoder);
+            }
+            queue.submit(Some(encoder.finish()));
+            if let Backend::Compute(inner) = backend {
+                inner.finish_frame();
+            }
+            if let (true, Some(timer)) = (timed, &mut self.timer) {
+                timer.map();
+            }
+            if let (true, Some(non_finite)) = (scanned, &mut self.non_finite) {
+                non_finite.map();
+            }
+        }
+        // `PostProcess::encode` has no queue to write the uniforms with, so the passes are left
+        // set up for full size images after every frame. The writes are ordered after the
+        // frame's submission.
+        self.reset_render_region(queue);
+        if field.is_some() {
+            self.field = match self.field {
+                SmaaField::Top => SmaaField::Bottom,
+                SmaaField::Bottom => SmaaField::Top,
+            };
+        }
+    }
+
+    fn assert_output_origin_supported(&self) {
+        assert!(
+            matches!(self.inner, Some(Backend::Render(_))),
+            "output origins are only supported by SmaaBackend::Render"
+        );
+    }
+
+    fn assert_extra_outputs_supported(&self) {
+        assert!(
+            self.inner.is_some(),
+            "additional outputs require antialiasing to be enabled"
+        );
+    }
+
+    /// Texture that frames written to `output_view` should be rendered into.
+    fn frame_color_target<'a>(
+        &'a self,
+        output_view: &'a wgpu::TextureView,
+    ) -> &'a wgpu::TextureView {
+        match self.inner {
+            None => output_view,
+            Some(Backend::Render(ref inner)) => &inner.targets.color_target,
+            Some(Backend::Compute(ref inner)) => inner.color_target(),
+            Some(Backend::Cmaa2(ref inner)) => inner.color_target(),
+        }
+    }
+}
+
+/// View that a frame is resolved into, which the frame creates itself for
+/// [`SmaaTarget::start_frame_into_layer`].
+enum OutputView<'a> {
+    Borrowed(&'a wgpu::TextureView),
+    Owned(wgpu::TextureView),
+}
+impl<'a> std::ops::Deref for OutputView<'a> {
+    type Target = wgpu::TextureView;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            OutputView::Borrowed(view) => view,
+            OutputView::Owned(view) => view,
         }
     }
 }
@@ -713,89 +4539,311 @@ pub struct SmaaFrame<'a> {
     target: &'a mut SmaaTarget,
     device: &'a wgpu::Device,
     queue: &'a wgpu::Queue,
-    output_view: &'a wgpu::TextureView,
+    output_view: OutputView<'a>,
+    depth_view: Option<&'a wgpu::TextureView>,
+    luma_view: Option<&'a wgpu::TextureView>,
+    secondary_view: Option<&'a wgpu::TextureView>,
+    resolve_view: Option<&'a wgpu::TextureView>,
+    extra_outputs: Vec<&'a wgpu::TextureView>,
+    output_origin: Option<(u32, u32)>,
+    // Size of the outputs, if the frame was started from the output texture or given an origin.
+    outputs_size: Option<(u32, u32)>,
+    resolved: bool,
 }
 impl<'a> SmaaFrame<'a> {
+    fn new(
+        target: &'a mut SmaaTarget,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        output_view: OutputView<'a>,
+        depth_view: Option<&'a wgpu::TextureView>,
+    ) -> Self {
+        Self {
+            target,
+            device,
+            queue,
+            output_view,
+            depth_view,
+            luma_view: None,
+            secondary_view: None,
+            resolve_view: None,
+            extra_outputs: Vec::new(),
+            output_origin: None,
+            outputs_size: None,
+            resolved: false,
+        }
+    }
+
+    /// Also resolve the frame into `output_view`, for instance a texture that the frame is
+    /// recorded from as well as being presented. The blend weights are shared between the
+    /// outputs, so that only the final pass runs again for each of them. [`SmaaBackend::Compute`]
+    /// and [`SmaaMode::Cmaa2`] run more of their passes again.
+    ///
+    /// The view needs the same size, format and usages as the one passed to
+    /// [`SmaaTarget::start_frame`]. Panics if antialiasing is disabled.
+    pub fn add_output(&mut self, output_view: &'a wgpu::TextureView) {
+        self.target.assert_extra_outputs_supported();
+        self.extra_outputs.push(output_view);
+    }
+
+    /// Write the frame into the rectangle of the outputs starting at `(x, y)` instead of their
+    /// top left corner, leaving the rest of them untouched. This lets frames be composited into
+    /// a larger texture, like a sheet of thumbnails, without copying them.
+    ///
+    /// The rectangle has the size of [`SmaaTarget::output_size`] and must fit within the outputs,
+    /// which are `outputs_size` large. Resolving the frame panics if it doesn't, and
+    /// [`SmaaFrame::try_resolve`] returns [`SmaaError::OutputOrigin`] instead. Panics unless
+    /// antialiasing is enabled with [`SmaaBackend::Render`].
+    pub fn set_output_origin(&mut self, x: u32, y: u32, outputs_size: (u32, u32)) {
+        self.target.assert_output_origin_supported();
+        self.output_origin = Some((x, y));
+        self.outputs_size = Some(outputs_size);
+    }
+
+    /// Detect the edges of the frame in `luma_view` instead of its colors, which is required
+    /// with [`SmaaConfig::luma_input`] and ignored otherwise.
+    ///
+    /// The view must be the size of the target, have a filterable float format such as
+    /// `R8Unorm` or `R16Float`, and hold the luma of the scene in its red channel once the frame
+    /// is resolved.
+    pub fn set_luma_input(&mut self, luma_view: &'a wgpu::TextureView) {
+        self.luma_view = Some(luma_view);
+    }
+
+    /// Write the data chosen by [`SmaaConfig::secondary_output`] into `secondary_view` as the
+    /// frame is resolved, which is required with that setting and ignored otherwise.
+    ///
+    /// The view must be `R8Unorm` with [`wgpu::TextureUsages::RENDER_ATTACHMENT`] and the size
+    /// of the outputs. It is written in the same place as them, so an origin set with
+    /// [`SmaaFrame::set_output_origin`] applies to it as well.
+    pub fn set_secondary_output(&mut self, secondary_view: &'a wgpu::TextureView) {
+        self.secondary_view = Some(secondary_view);
+    }
+
+    /// Resolve the multisampled output into `resolve_view` as the frame is written to it, with
+    /// [`SmaaConfig::output_sample_count`]. Ignored for outputs that aren't multisampled.
+    ///
+    /// The view must have the format and size of the output and a single sample. Only the output
+    /// passed to [`SmaaTarget::start_frame`] is resolved, not those of
+    /// [`SmaaFrame::add_output`].
+    pub fn set_resolve_target(&mut self, resolve_view: &'a wgpu::TextureView) {
+        self.resolve_view = Some(resolve_view);
+    }
+
     /// Resolve the multisampled image into the output texture.
-    pub fn resolve(self) {
-        std::mem::drop(self);
+    pub fn resolve(mut self) {
+        self.encode_resolve();
+    }
+
+    /// Resolve the frame like [`SmaaFrame::resolve`] if the views it needs were set and, for
+    /// frames started with [`SmaaTarget::try_start_frame`], it fits within the output, or return
+    /// why it can't be resolved without recording anything. The frame is then dropped without
+    /// being resolved, whatever [`SmaaConfig::drop_policy`] is.
+    pub fn try_resolve(mut self) -> Result<(), SmaaError> {
+        let (needs_luma, needs_secondary) = match self.target.inner {
+            Some(Backend::Render(ref inner)) => {
+                (inner.layouts.luma_input, inner.formats.secondary.is_some())
+            }
+            _ => (false, false),
+        };
+        if needs_luma && self.luma_view.is_none() {
+            self.resolved = true;
+            return Err(SmaaError::MissingLumaView);
+        }
+        if needs_secondary && self.secondary_view.is_none() {
+            self.resolved = true;
+            return Err(SmaaError::MissingSecondaryView);
+        }
+        if let Some(size) = self.outputs_size {
+            if let Err(error) = self.target.check_output_fits(self.output_origin, size) {
+                self.resolved = true;
+                return Err(error);
+            }
+        }
+        self.encode_resolve();
+        Ok(())
+    }
+
+    fn encode_resolve(&mut self) {
+        self.resolved = true;
+        self.target
+            .assert_output_fits(self.output_origin, self.outputs_size);
+        self.target.resolve_frame(
+            self.device,
+            self.queue,
+            &self.output_view,
+            &self.extra_outputs,
+            self.output_origin,
+            FrameViews {
+                depth: self.depth_view,
+                luma: self.luma_view,
+                secondary: self.secondary_view,
+                resolve: self.resolve_view,
+            },
+        );
+    }
+
+    /// Resolve the frame like [`SmaaFrame::resolve`] and start reading the result back,
+    /// without blocking.
+    ///
+    /// `output` must be the texture that the output view passed to
+    /// [`SmaaTarget::start_frame`] belongs to, and needs the `COPY_SRC` usage. See
+    /// [`SmaaReadback`] for when the returned future completes.
+    pub fn read_output_async(self, output: &wgpu::Texture) -> SmaaReadback {
+        let (device, queue) = (self.device, self.queue);
+        self.resolve();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.command_encoder.readback"),
+        });
+        let readback = readback::Readback::new(device, &mut encoder, output);
+        queue.submit(Some(encoder.finish()));
+        readback.map_async()
+    }
+
+    /// Resolve the frame like [`SmaaFrame::resolve`] and wait for the result to be read back
+    /// as an image, which can then be saved as a PNG with [`image::RgbaImage::save`].
+    ///
+    /// `output` must be the texture that the output view passed to
+    /// [`SmaaTarget::start_frame`] belongs to. It needs the `COPY_SRC` usage and an 8-bit RGBA
+    /// or BGRA format.
+    #[cfg(feature = "image")]
+    pub fn resolve_to_image(self, output: &wgpu::Texture) -> image::RgbaImage {
+        let (device, queue) = (self.device, self.queue);
+        self.resolve();
+        readback::read_image(device, queue, output)
     }
 }
 impl<'a> std::ops::Deref for SmaaFrame<'a> {
     type Target = wgpu::TextureView;
     fn deref(&self) -> &Self::Target {
-        match self.target.inner {
-            None => self.output_view,
-            Some(ref inner) => &inner.targets.color_target,
-        }
+        self.target.frame_color_target(&self.output_view)
     }
 }
 impl<'a> Drop for SmaaFrame<'a> {
     fn drop(&mut self) {
-        if let Some(ref mut inner) = self.target.inner {
-            let mut encoder = self
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("smaa.command_encoder"),
-                });
-            {
-                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &inner.targets.edges_target,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    label: Some("smaa.render_pass.edge_detect"),
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                });
-                rpass.set_pipeline(&inner.pipelines.edge_detect);
-                rpass.set_bind_group(0, &inner.bind_groups.edge_detect_bind_group, &[]);
-                rpass.draw(0..3, 0..1);
-            }
-            {
-                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &inner.targets.blend_target,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    label: Some("smaa.render_pass.blend_weight"),
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                });
-                rpass.set_pipeline(&inner.pipelines.blend_weight);
-                rpass.set_bind_group(0, &inner.bind_groups.blend_weight_bind_group, &[]);
-                rpass.draw(0..3, 0..1);
+        // wgpu doesn't end passes that are dropped while a panic unwinds, so nothing can be
+        // recorded then.
+        if self.resolved || std::thread::panicking() {
+            return;
+        }
+        match self.target.config.drop_policy {
+            SmaaDropPolicy::Resolve => self.encode_resolve(),
+            SmaaDropPolicy::DebugAssert => {
+                debug_assert!(false, "SmaaFrame dropped without calling resolve");
+                self.encode_resolve();
             }
-            {
-                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: self.output_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    label: Some("smaa.render_pass.neighborhood_blending"),
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                });
-                rpass.set_pipeline(&inner.pipelines.neighborhood_blending);
-                rpass.set_bind_group(0, &inner.bind_groups.neighborhood_blending_bind_group, &[]);
-                rpass.draw(0..3, 0..1);
+            SmaaDropPolicy::Skip => {
+                log::warn!("SmaaFrame dropped without calling resolve, skipping antialiasing")
             }
-            self.queue.submit(Some(encoder.finish()));
         }
     }
 }
+
+/// Frame that the scene should be rendered into, which only needs the device and queue once it
+/// is resolved; can be created by [`SmaaTarget::start_detached_frame`].
+///
+/// Without a device to resolve with, dropping the frame can't resolve it. It is then skipped,
+/// as with [`SmaaDropPolicy::Skip`], except that [`SmaaDropPolicy::DebugAssert`] still panics
+/// in debug builds.
+pub struct SmaaDetachedFrame<'a> {
+    target: &'a mut SmaaTarget,
+    output_view: &'a wgpu::TextureView,
+    depth_view: Option<&'a wgpu::TextureView>,
+    luma_view: Option<&'a wgpu::TextureView>,
+    secondary_view: Option<&'a wgpu::TextureView>,
+    resolve_view: Option<&'a wgpu::TextureView>,
+    extra_outputs: Vec<&'a wgpu::TextureView>,
+    output_origin: Option<(u32, u32)>,
+    outputs_size: Option<(u32, u32)>,
+    resolved: bool,
+}
+impl<'a> SmaaDetachedFrame<'a> {
+    fn new(
+        target: &'a mut SmaaTarget,
+        output_view: &'a wgpu::TextureView,
+        depth_view: Option<&'a wgpu::TextureView>,
+    ) -> Self {
+        Self {
+            target,
+            output_view,
+            depth_view,
+            luma_view: None,
+            secondary_view: None,
+            resolve_view: None,
+            extra_outputs: Vec::new(),
+            output_origin: None,
+            outputs_size: None,
+            resolved: false,
+        }
+    }
+
+    /// Also resolve the frame into `output_view`, like [`SmaaFrame::add_output`].
+    pub fn add_output(&mut self, output_view: &'a wgpu::TextureView) {
+        self.target.assert_extra_outputs_supported();
+        self.extra_outputs.push(output_view);
+    }
+
+    /// Write the frame into a rectangle of the outputs, like [`SmaaFrame::set_output_origin`].
+    pub fn set_output_origin(&mut self, x: u32, y: u32, outputs_size: (u32, u32)) {
+        self.target.assert_output_origin_supported();
+        self.output_origin = Some((x, y));
+        self.outputs_size = Some(outputs_size);
+    }
+
+    /// Detect edges in `luma_view`, like [`SmaaFrame::set_luma_input`].
+    pub fn set_luma_input(&mut self, luma_view: &'a wgpu::TextureView) {
+        self.luma_view = Some(luma_view);
+    }
+
+    /// Write the secondary output into `secondary_view`, like
+    /// [`SmaaFrame::set_secondary_output`].
+    pub fn set_secondary_output(&mut self, secondary_view: &'a wgpu::TextureView) {
+        self.secondary_view = Some(secondary_view);
+    }
+
+    /// Resolve the multisampled output into `resolve_view`, like
+    /// [`SmaaFrame::set_resolve_target`].
+    pub fn set_resolve_target(&mut self, resolve_view: &'a wgpu::TextureView) {
+        self.resolve_view = Some(resolve_view);
+    }
+
+    /// Resolve the frame into the output view.
+    pub fn resolve(mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.resolved = true;
+        self.target
+            .assert_output_fits(self.output_origin, self.outputs_size);
+        self.target.resolve_frame(
+            device,
+            queue,
+            self.output_view,
+            &self.extra_outputs,
+            self.output_origin,
+            FrameViews {
+                depth: self.depth_view,
+                luma: self.luma_view,
+                secondary: self.secondary_view,
+                resolve: self.resolve_view,
+            },
+        );
+    }
+}
+impl<'a> std::ops::Deref for SmaaDetachedFrame<'a> {
+    type Target = wgpu::TextureView;
+    fn deref(&self) -> &Self::Target {
+        self.target.frame_color_target(self.output_view)
+    }
+}
+impl<'a> Drop for SmaaDetachedFrame<'a> {
+    fn drop(&mut self) {
+        if self.resolved {
+            return;
+        }
+        if self.target.config.drop_policy == SmaaDropPolicy::DebugAssert {
+            debug_assert!(
+                std::thread::panicking(),
+                "SmaaDetachedFrame dropped without calling resolve"
+            );
+        }
+        log::warn!("SmaaDetachedFrame dropped without calling resolve, skipping antialiasing");
+    }
+}
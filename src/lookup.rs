@@ -0,0 +1,67 @@
+//! Contents of the area and search lookup textures used by the blend weight pass.
+//!
+//! The textures are only embedded with the `embedded-textures` feature. With the
+//! `compressed-textures` feature, which is enabled by default and implies it, they are embedded
+//! compressed with zlib and decompressed when a target is created.
+
+#[cfg(feature = "embedded-textures")]
+use std::borrow::Cow;
+
+#[path = "../third_party/smaa/Textures/AreaTex.rs"]
+#[cfg_attr(
+    any(feature = "compressed-textures", not(feature = "embedded-textures")),
+    allow(dead_code)
+)]
+pub(crate) mod area_tex;
+pub use area_tex::{AREATEX_HEIGHT, AREATEX_WIDTH};
+
+#[path = "../third_party/smaa/Textures/SearchTex.rs"]
+#[cfg_attr(
+    any(feature = "compressed-textures", not(feature = "embedded-textures")),
+    allow(dead_code)
+)]
+pub(crate) mod search_tex;
+pub use search_tex::{SEARCHTEX_HEIGHT, SEARCHTEX_WIDTH};
+
+#[cfg(feature = "compressed-textures")]
+fn decompress(data: &[u8], size: usize) -> Cow<'static, [u8]> {
+    let bytes = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, size)
+        .expect("embedded lookup texture is corrupted");
+    assert_eq!(bytes.len(), size);
+    Cow::Owned(bytes)
+}
+
+/// Bytes of the Rg8Unorm area texture.
+#[cfg(feature = "embedded-textures")]
+pub fn area_texture_bytes() -> Cow<'static, [u8]> {
+    #[cfg(feature = "compressed-textures")]
+    return decompress(
+        include_bytes!("../third_party/smaa/Textures/AreaTex.zlib"),
+        area_tex::AREATEX_SIZE,
+    );
+    #[cfg(not(feature = "compressed-textures"))]
+    return Cow::Borrowed(&area_tex::AREATEX_BYTES);
+}
+
+/// Bytes of the R8Unorm search texture.
+#[cfg(feature = "embedded-textures")]
+pub fn search_texture_bytes() -> Cow<'static, [u8]> {
+    #[cfg(feature = "compressed-textures")]
+    return decompress(
+        include_bytes!("../third_party/smaa/Textures/SearchTex.zlib"),
+        search_tex::SEARCHTEX_SIZE,
+    );
+    #[cfg(not(feature = "compressed-textures"))]
+    return Cow::Borrowed(&search_tex::SEARCHTEX_BYTES);
+}
+
+#[cfg(all(test, feature = "compressed-textures"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_textures_match_uncompressed() {
+        assert!(area_texture_bytes()[..] == area_tex::AREATEX_BYTES[..]);
+        assert!(search_texture_bytes()[..] == search_tex::SEARCHTEX_BYTES[..]);
+    }
+}
@@ -0,0 +1,327 @@
+//! Image quality metrics computed on the GPU, for comparing antialiased output to a reference.
+
+use std::time::Duration;
+
+use crate::{SmaaConfig, SmaaMode, SmaaPreset, SmaaStats, SmaaTarget};
+
+/// Width and height of the blocks that SSIM is computed over.
+const BLOCK_SIZE: u32 = 8;
+/// Number of frames rendered with each preset by [`SmaaMetrics::report`]. The last one is
+/// compared to the reference, and all but the first are timed.
+const REPORT_FRAMES: u32 = 16;
+
+/// How closely an image matches a reference, as measured by [`SmaaMetrics::compare`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SmaaImageMetrics {
+    /// Peak signal-to-noise ratio over the RGB channels in decibels. Infinite for identical
+    /// images.
+    pub psnr: f64,
+    /// Structural similarity of the luma, averaged over 8x8 pixel blocks. 1.0 for identical
+    /// images.
+    pub ssim: f64,
+}
+
+/// Quality and cost of antialiasing a scene with one preset, as measured by
+/// [`SmaaMetrics::report`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SmaaReportEntry {
+    /// Preset that the scene was antialiased with, or `None` for [`SmaaMode::Disabled`].
+    pub preset: Option<SmaaPreset>,
+    /// How closely the output matches the reference.
+    pub metrics: SmaaImageMetrics,
+    /// GPU time taken by the passes, if the device supports timing them. Always `None` without
+    /// antialiasing.
+    pub times: Option<SmaaStats>,
+}
+
+/// Results of antialiasing the same scene with every preset, for choosing a preset per type of
+/// content from measurements.
+///
+/// The `Display` implementation formats the entries as a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmaaQualityReport {
+    /// Entries without antialiasing, then with each preset from lowest to highest.
+    pub entries: Vec<SmaaReportEntry>,
+}
+impl SmaaQualityReport {
+    /// The entry with the highest SSIM whose passes took at most `budget` on average, and the
+    /// faster one of entries with the same SSIM. Entries that weren't timed are only considered
+    /// without antialiasing.
+    pub fn best_within(&self, budget: Duration) -> Option<&SmaaReportEntry> {
+        let time = |entry: &SmaaReportEntry| entry.times.map(|times| times.total.average);
+        self.entries
+            .iter()
+            .filter(|entry| match time(entry) {
+                Some(time) => time <= budget,
+                None => entry.preset.is_none(),
+            })
+            .max_by(|a, b| {
+                let ssim = a.metrics.ssim.total_cmp(&b.metrics.ssim);
+                ssim.then_with(|| time(b).cmp(&time(a)))
+            })
+    }
+}
+impl std::fmt::Display for SmaaQualityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "preset    PSNR (dB)   SSIM     time (ms)")?;
+        for entry in &self.entries {
+            let preset = match entry.preset {
+                Some(preset) => format!("{preset:?}"),
+                None => "Disabled".to_owned(),
+            };
+            let time = match entry.times {
+                Some(times) => format!("{:.3}", times.total.average.as_secs_f64() * 1000.0),
+                None => "-".to_owned(),
+            };
+            writeln!(
+                f,
+                "{preset:<9} {:>9.2}   {:.5}  {time:>9}",
+                entry.metrics.psnr, entry.metrics.ssim,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two textures on the GPU, for instance the output of SMAA against a supersampled
+/// reference of the same scene.
+///
+/// Only a few values per 8x8 pixel block are read back rather than the images themselves.
+/// Requires an adapter with [`wgpu::DownlevelFlags::COMPUTE_SHADERS`].
+pub struct SmaaMetrics {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl SmaaMetrics {
+    /// Create the compute pipeline used for the comparisons.
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smaa.bind_group_layout.metrics"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.shader.metrics.comp"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("metrics.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("smaa.pipeline_layout.metrics"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("smaa.pipeline.metrics"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        SmaaMetrics {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Compare `image` to `reference` and wait for the result.
+    ///
+    /// Both textures must have the same size, a float format and the `TEXTURE_BINDING` usage.
+    /// They are compared as the shaders read them, so sRGB textures are compared in linear
+    /// space.
+    pub fn compare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &wgpu::Texture,
+        reference: &wgpu::Texture,
+    ) -> SmaaImageMetrics {
+        assert_eq!(
+            image.size(),
+            reference.size(),
+            "image and reference must have the same size"
+        );
+        let block_count = (
+            image.width().div_ceil(BLOCK_SIZE),
+            image.height().div_ceil(BLOCK_SIZE),
+        );
+        let size = block_count.0 as u64 * block_count.1 as u64 * 16;
+        let blocks_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("smaa.buffer.metrics"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("smaa.buffer.metrics_readback"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let image_view = image.create_view(&Default::default());
+        let reference_view = reference.create_view(&Default::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.metrics"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&image_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&reference_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: blocks_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.command_encoder.metrics"),
+        });
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.compute_pass.metrics"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(block_count.0, block_count.1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&blocks_buffer, 0, &readback_buffer, 0, size);
+        queue.submit(Some(encoder.finish()));
+        readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+
+        let (mut squared_error, mut ssim, mut pixels) = (0.0, 0.0, 0.0);
+        for block in readback_buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(16)
+        {
+            let value = |i: usize| f32::from_ne_bytes(block[i * 4..][..4].try_into().unwrap());
+            squared_error += value(0) as f64;
+            ssim += value(1) as f64;
+            pixels += value(2) as f64;
+        }
+        let mean_squared_error = squared_error / (3.0 * pixels);
+        SmaaImageMetrics {
+            psnr: -10.0 * mean_squared_error.log10(),
+            ssim: ssim / pixels,
+        }
+    }
+
+    /// Antialias the scene drawn by `draw` without antialiasing and with every preset, and
+    /// compare each result to `reference`.
+    ///
+    /// `reference` is what the scene should ideally look like at the output size, for instance
+    /// the scene rendered at four times the resolution and downsampled. `draw` renders the scene
+    /// into the view it is given, which has the size of the reference and the scene format
+    /// implied by `format` and `config`. It is called for several frames per preset so that
+    /// the passes can be timed: [`SmaaConfig::profile`] is enabled, which requires the device
+    /// to have [`wgpu::Features::TIMESTAMP_QUERY`] enabled, and [`SmaaConfig::time_budget`] is
+    /// ignored so that the presets stay fixed. The other settings in `config` apply to every
+    /// entry, so the report can compare presets for a particular backend or scene format.
+    ///
+    /// The outputs are created in `format` with the usages that [`SmaaMetrics::compare`] and the
+    /// configured backend need, so it must be a float format that they support.
+    pub fn report(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        reference: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+        config: SmaaConfig,
+        mut draw: impl FnMut(&wgpu::TextureView),
+    ) -> SmaaQualityReport {
+        let (width, height) = (reference.width(), reference.height());
+        let mut usage =
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+        if format
+            .guaranteed_format_features(device.features())
+            .allowed_usages
+            .contains(wgpu::TextureUsages::STORAGE_BINDING)
+        {
+            usage |= wgpu::TextureUsages::STORAGE_BINDING;
+        }
+        let output = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("smaa.texture.report_output"),
+            size: reference.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+        let output_view = output.create_view(&Default::default());
+
+        let presets = [
+            SmaaPreset::Low,
+            SmaaPreset::Medium,
+            SmaaPreset::High,
+            SmaaPreset::Ultra,
+        ];
+        let runs = std::iter::once(None).chain(presets.map(Some));
+        let entries = runs
+            .map(|preset| {
+                let mode = match preset {
+                    Some(_) => SmaaMode::Smaa1X,
+                    None => SmaaMode::Disabled,
+                };
+                let config = SmaaConfig {
+                    preset: preset.unwrap_or_default(),
+                    time_budget: None,
+                    profile: true,
+                    ..config.clone()
+                };
+                let mut target =
+                    SmaaTarget::with_config(device, queue, width, height, format, mode, config);
+                for i in 0..REPORT_FRAMES {
+                    let frame = target.start_frame(device, queue, &output_view);
+                    draw(&frame);
+                    frame.resolve();
+                    device.poll(wgpu::Maintain::Wait);
+                    // The first frame includes creating the pipelines on some drivers.
+                    if i == 0 {
+                        target.reset_stats();
+                    }
+                }
+                device.poll(wgpu::Maintain::Wait);
+                SmaaReportEntry {
+                    preset,
+                    metrics: self.compare(device, queue, &output, reference),
+                    times: target.stats(),
+                }
+            })
+            .collect();
+        SmaaQualityReport { entries }
+    }
+}
@@ -0,0 +1,69 @@
+// Sums the squared error and the luma statistics needed for SSIM over each 8x8 block, writing
+// (squared error, SSIM * pixels, pixels, 0) for every block.
+
+@group(0) @binding(0) var image_tex: texture_2d<f32>;
+@group(0) @binding(1) var reference_tex: texture_2d<f32>;
+@group(0) @binding(2) var<storage, read_write> blocks: array<vec4<f32>>;
+
+// Stabilizing constants of SSIM for values between 0 and 1.
+const C1: f32 = 0.0001;
+const C2: f32 = 0.0009;
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+// (pixels, squared error, image luma, reference luma)
+var<workgroup> first_sums: array<vec4<f32>, 64>;
+// Sums of the squared deviations of the lumas from their means and of their product. These are
+// computed once the means are known, since subtracting the squared mean from the mean square
+// loses too much precision in f32.
+var<workgroup> second_sums: array<vec3<f32>, 64>;
+
+@compute @workgroup_size(8, 8)
+fn main(
+    @builtin(global_invocation_id) pixel: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+    @builtin(workgroup_id) block: vec3<u32>,
+    @builtin(num_workgroups) block_count: vec3<u32>,
+) {
+    let inside = all(pixel.xy < textureDimensions(image_tex));
+    var luma = vec2<f32>(0.0);
+    var first = vec4<f32>(0.0);
+    if inside {
+        let image = textureLoad(image_tex, pixel.xy, 0).rgb;
+        let reference = textureLoad(reference_tex, pixel.xy, 0).rgb;
+        let error = image - reference;
+        luma = vec2<f32>(dot(image, LUMA), dot(reference, LUMA));
+        first = vec4<f32>(1.0, dot(error, error), luma);
+    }
+    first_sums[index] = first;
+    for (var stride = 32u; stride > 0u; stride >>= 1u) {
+        workgroupBarrier();
+        if index < stride {
+            first_sums[index] += first_sums[index + stride];
+        }
+    }
+    workgroupBarrier();
+
+    let pixels = first_sums[0].x;
+    let mean = first_sums[0].zw / pixels;
+    var second = vec3<f32>(0.0);
+    if inside {
+        let deviation = luma - mean;
+        second = vec3<f32>(deviation * deviation, deviation.x * deviation.y);
+    }
+    second_sums[index] = second;
+    for (var stride = 32u; stride > 0u; stride >>= 1u) {
+        workgroupBarrier();
+        if index < stride {
+            second_sums[index] += second_sums[index + stride];
+        }
+    }
+
+    if index == 0u {
+        let variance = second_sums[0].xy / pixels;
+        let covariance = second_sums[0].z / pixels;
+        let ssim = (2.0 * mean.x * mean.y + C1) * (2.0 * covariance + C2)
+            / ((dot(mean, mean) + C1) * (variance.x + variance.y + C2));
+        blocks[block.y * block_count.x + block.x] =
+            vec4<f32>(first_sums[0].y, ssim * pixels, pixels, 0.0);
+    }
+}
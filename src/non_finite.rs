@@ -0,0 +1,228 @@
+//! Detection of NaN and infinite values in the color target, reported through
+//! [`SmaaTarget::non_finite_pixels`] when [`SmaaConfig::detect_non_finite`] is set.
+//!
+//! [`SmaaTarget::non_finite_pixels`]: crate::SmaaTarget::non_finite_pixels
+//! [`SmaaConfig::detect_non_finite`]: crate::SmaaConfig::detect_non_finite
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+use crate::SmaaNonFinitePixels;
+
+/// Width and height of the workgroups of the scan.
+const WORKGROUP_SIZE: u32 = 8;
+/// Number of pixels with NaN values, with infinite values, and index of the first of either.
+const BUFFER_SIZE: u64 = 3 * 4;
+
+pub(crate) struct NonFiniteScan {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    region_buffer: wgpu::Buffer,
+    initial_counts: wgpu::Buffer,
+    counts: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Width of the scanned region, for turning the index of the first pixel into coordinates.
+    width: u32,
+    in_flight: bool,
+    mapped: Arc<AtomicBool>,
+    latest: Option<SmaaNonFinitePixels>,
+}
+
+impl NonFiniteScan {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smaa.bind_group_layout.non_finite"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.shader.non_finite.comp"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("non_finite.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("smaa.pipeline_layout.non_finite"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("smaa.pipeline.non_finite"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let initial: [u32; 3] = [0, 0, u32::MAX];
+        Self {
+            bind_group_layout,
+            pipeline,
+            region_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.non_finite_region"),
+                size: 8,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            initial_counts: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("smaa.buffer.non_finite_initial"),
+                contents: &initial.map(u32::to_ne_bytes).concat(),
+                usage: wgpu::BufferUsages::COPY_SRC,
+            }),
+            counts: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.non_finite"),
+                size: BUFFER_SIZE,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.non_finite_readback"),
+                size: BUFFER_SIZE,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            width: 0,
+            in_flight: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+            latest: None,
+        }
+    }
+
+    /// Scan the part of `color_view` that the frame was rendered into, unless the previous scan
+    /// is still being read back. Returns whether anything was encoded.
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        (width, height): (u32, u32),
+    ) -> bool {
+        if self.in_flight {
+            return false;
+        }
+        self.width = width;
+        queue.write_buffer(
+            &self.region_buffer,
+            0,
+            &[width.to_ne_bytes(), height.to_ne_bytes()].concat(),
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.non_finite"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.region_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.counts.as_entire_binding(),
+                },
+            ],
+        });
+
+        encoder.copy_buffer_to_buffer(&self.initial_counts, 0, &self.counts, 0, BUFFER_SIZE);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("smaa.compute_pass.non_finite"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                width.div_ceil(WORKGROUP_SIZE),
+                height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&self.counts, 0, &self.readback_buffer, 0, BUFFER_SIZE);
+        true
+    }
+
+    /// Start reading back the counts once the commands that scan the frame were submitted.
+    pub fn map(&mut self) {
+        let mapped = self.mapped.clone();
+        self.in_flight = true;
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+    }
+
+    /// Take in the latest counts if they have just been read back, and warn if the frame held
+    /// any NaN or infinite values.
+    pub fn update(&mut self) {
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+        let counts: Vec<u32> = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            data.chunks_exact(4)
+                .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        self.in_flight = false;
+
+        let first = counts[2];
+        let pixels = SmaaNonFinitePixels {
+            nan: counts[0],
+            infinite: counts[1],
+            first: (first != u32::MAX).then(|| (first % self.width, first / self.width)),
+        };
+        if let Some((x, y)) = pixels.first {
+            log::warn!(
+                "Color target holds {} NaN and {} infinite pixels, the first at ({x}, {y})",
+                pixels.nan,
+                pixels.infinite,
+            );
+        }
+        self.latest = Some(pixels);
+    }
+
+    /// The most recent counts that were read back.
+    pub fn latest(&self) -> Option<SmaaNonFinitePixels> {
+        self.latest
+    }
+}
@@ -0,0 +1,42 @@
+// Counts the pixels of the color target holding NaN or infinite values.
+
+struct Region {
+    size: vec2<u32>,
+}
+
+struct Counts {
+    nan: atomic<u32>,
+    infinite: atomic<u32>,
+    // Index in row-major order of the first pixel holding either.
+    first: atomic<u32>,
+}
+
+@group(0) @binding(0) var color_tex: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> region: Region;
+@group(0) @binding(2) var<storage, read_write> counts: Counts;
+
+const EXPONENT_MASK: u32 = 0x7f800000u;
+const MANTISSA_MASK: u32 = 0x007fffffu;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= region.size) {
+        return;
+    }
+    // Inspect the bits, since comparisons may be optimized on the assumption that floats are
+    // finite.
+    let bits = bitcast<vec4<u32>>(textureLoad(color_tex, id.xy, 0));
+    let special = (bits & vec4(EXPONENT_MASK)) == vec4(EXPONENT_MASK);
+    let fraction = (bits & vec4(MANTISSA_MASK)) != vec4(0u);
+    let nan = any(special & fraction);
+    let infinite = any(special & !fraction);
+    if nan {
+        atomicAdd(&counts.nan, 1u);
+    }
+    if infinite {
+        atomicAdd(&counts.infinite, 1u);
+    }
+    if nan || infinite {
+        atomicMin(&counts.first, id.y * region.size.x + id.x);
+    }
+}
@@ -0,0 +1,118 @@
+//! Antialiasing images in memory on a device of its own, for tools that don't otherwise use
+//! wgpu.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+
+use crate::{SmaaConfig, VideoSmaa};
+
+/// Run a future to completion on the current thread. Requesting adapters and devices doesn't
+/// need an executor, but the futures still have to be polled.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(std::thread::Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+/// Antialiases images of RGBA pixels in memory, on a headless device that it creates itself.
+///
+/// This is meant for batch tools and tests that only need to antialias some images, without
+/// setting up wgpu themselves:
+///
+/// ```no_run
+/// let mut smaa = smaa::OffscreenSmaa::new().expect("no adapter available");
+/// # let (width, height, pixels) = (1, 1, vec![0; 4]);
+/// let antialiased = smaa.antialias(width, height, &pixels);
+/// ```
+///
+/// Like [`crate::antialias_rgba8`], images are sRGB encoded with 8 bits per channel and tightly
+/// packed rows. The passes are kept for the size of the last image, so that images of the same
+/// size don't compile them again. Not available on the web, where waiting for the device blocks.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct OffscreenSmaa {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: SmaaConfig,
+    video: Option<((u32, u32), VideoSmaa)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl OffscreenSmaa {
+    /// Request a device from the default adapter. Returns `None` if no adapter is available.
+    pub fn new() -> Option<Self> {
+        Self::with_config(SmaaConfig {
+            blend_space: crate::SmaaBlendSpace::Linear,
+            ..Default::default()
+        })
+    }
+
+    /// Request a device like [`OffscreenSmaa::new`], antialiasing with non-default settings.
+    /// Images are then uploaded in [`SmaaConfig::color_format`], if set, and always read back
+    /// as `Rgba8Unorm`.
+    pub fn with_config(config: SmaaConfig) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = block_on(instance.request_adapter(&Default::default()))?;
+        let (device, queue) = block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("smaa.offscreen.device"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            },
+            None,
+        ))
+        .ok()?;
+        Some(Self {
+            device,
+            queue,
+            config,
+            video: None,
+        })
+    }
+
+    /// The device that images are antialiased on.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// The queue that the passes are submitted to.
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Antialias an image of the given size and wait for the result.
+    ///
+    /// Panics if `pixels` doesn't hold exactly one image of that size.
+    pub fn antialias(&mut self, width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+        let video = match self.video {
+            Some((size, ref mut video)) if size == (width, height) => video,
+            _ => {
+                let video = VideoSmaa::new(
+                    &self.device,
+                    &self.queue,
+                    width,
+                    height,
+                    wgpu::TextureFormat::Rgba8Unorm,
+                    self.config.clone(),
+                    1,
+                );
+                &mut self.video.insert(((width, height), video)).1
+            }
+        };
+        video.push_frame(&self.device, &self.queue, pixels);
+        video.pop_frame(&self.device, true).unwrap()
+    }
+}
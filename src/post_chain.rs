@@ -0,0 +1,596 @@
+//! A fixed sequence of post-processing stages around SMAA, run from a single frame.
+
+use wgpu::util::DeviceExt;
+
+use crate::exposure::AutoExposure;
+use crate::{PostProcess, SmaaBackend, SmaaConfig, SmaaMode, SmaaTarget};
+
+/// Curve that maps the HDR colors of the scene to the displayable range.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PostTonemap {
+    /// Maps each channel `x` to `x / (1 + x)`.
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+/// Settings of the auto-exposure of a [`PostChain`], which builds a histogram of the luminance of
+/// every frame and adapts the exposure towards the average of its middle part.
+///
+/// Both happen on the GPU, so nothing is read back. Requires an adapter with
+/// [`wgpu::DownlevelFlags::COMPUTE_SHADERS`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PostAutoExposure {
+    /// Base-2 logarithm of the lowest luminance the histogram covers. Darker pixels count as
+    /// this luminance, and black pixels are left out. The default is `-10.0`.
+    pub min_log_luminance: f32,
+    /// Base-2 logarithm of the highest luminance the histogram covers. Brighter pixels count as
+    /// this luminance. The default is `6.0`.
+    pub max_log_luminance: f32,
+    /// Fraction of the darkest pixels left out of the average. The default is `0.1`.
+    pub low_fraction: f32,
+    /// Fraction of the pixels, counted from the darkest, above which the brightest pixels are
+    /// left out of the average. The default is `0.9`.
+    pub high_fraction: f32,
+    /// Luminance that the average is mapped to before tonemapping. The default is `0.18`, the
+    /// middle grey of a scene.
+    pub key: f32,
+    /// Fraction of the difference between the current and the metered exposure that is made up
+    /// every frame, between 0 and 1. The default is `0.05`; 1 adapts immediately. The first
+    /// frame is always exposed for its own average.
+    pub adaptation: f32,
+}
+
+impl Default for PostAutoExposure {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -10.0,
+            max_log_luminance: 6.0,
+            low_fraction: 0.1,
+            high_fraction: 0.9,
+            key: 0.18,
+            adaptation: 0.05,
+        }
+    }
+}
+
+/// Filters used to bring the scene up to the output size and sharpen it.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PostUpscale {
+    /// Bilinear upscaling, and contrast adaptive sharpening.
+    #[default]
+    Bilinear,
+    /// AMD FidelityFX Super Resolution 1.0: edge adaptive spatial upsampling (EASU), which keeps
+    /// edges sharp while upscaling, and robust contrast adaptive sharpening (RCAS).
+    ///
+    /// EASU expects antialiased input without noise, so it should be paired with SMAA. It is
+    /// meant for scales between 0.5 and 1, like 0.77 for FSR's "Ultra Quality" mode.
+    Fsr1,
+}
+
+/// Stages run by a [`PostChain`]. Enabled stages run in the order of the fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostChainConfig {
+    /// Tonemap the scene before antialiasing it, which is where SMAA expects its input to be.
+    /// The scene is then rendered into an `Rgba16Float` texture instead of the output format.
+    pub tonemap: Option<PostTonemap>,
+    /// Factor that colors are scaled by before tonemapping. The default is `1.0`.
+    ///
+    /// With [`PostChainConfig::auto_exposure`], this scales the metered exposure instead, like
+    /// exposure compensation on a camera.
+    pub exposure: f32,
+    /// Expose the scene for its average luminance before tonemapping it, adapting over several
+    /// frames. Requires [`PostChainConfig::tonemap`].
+    pub auto_exposure: Option<PostAutoExposure>,
+    /// Settings of the SMAA stage, or `None` to skip antialiasing. [`SmaaConfig::color_format`]
+    /// and [`SmaaConfig::background_depth`] must not be set.
+    pub smaa: Option<SmaaConfig>,
+    /// Size of the scene relative to the output. Values below 1 render the scene at a lower
+    /// resolution and add an upscaling stage after SMAA. The default is `1.0`.
+    pub render_scale: f32,
+    /// Filters of the upscaling and sharpening stages.
+    pub upscale: PostUpscale,
+    /// Strength between 0 and 1 of a sharpening stage run last, at the size of the output.
+    ///
+    /// With [`PostUpscale::Fsr1`], a strength of 1 is RCAS' maximum sharpness and every step of
+    /// 0.5 below it halves the sharpening, like a step of one in FSR's sharpness setting.
+    pub sharpen: Option<f32>,
+}
+
+impl Default for PostChainConfig {
+    fn default() -> Self {
+        Self {
+            tonemap: None,
+            exposure: 1.0,
+            auto_exposure: None,
+            smaa: Some(SmaaConfig::default()),
+            render_scale: 1.0,
+            upscale: PostUpscale::Bilinear,
+            sharpen: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum StageKind {
+    Tonemap,
+    Smaa,
+    Upscale,
+    Sharpen,
+}
+
+struct Stage {
+    kind: StageKind,
+    // Built-in stages only, SMAA goes through its own target.
+    pipeline: Option<wgpu::RenderPipeline>,
+    params: [f32; 4],
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+/// Runs the scene through tonemapping, SMAA, upscaling and sharpening, each of which can be
+/// turned off, with a single frame and a single `resize`.
+///
+/// The scene is rendered into the view of a [`PostChainFrame`] at [`PostChain::render_size`]
+/// in [`PostChain::scene_format`]. Stages in between ping-pong between two intermediate textures
+/// per size, and the last one writes straight into the output view, which must have the size
+/// and format that the chain was created with.
+///
+/// ```no_run
+/// # fn frame(device: &wgpu::Device, queue: &wgpu::Queue, output: &wgpu::TextureView) {
+/// use smaa::{PostChain, PostChainConfig, PostTonemap, PostUpscale};
+///
+/// let config = PostChainConfig {
+///     tonemap: Some(PostTonemap::Aces),
+///     render_scale: 0.75,
+///     upscale: PostUpscale::Fsr1,
+///     sharpen: Some(0.5),
+///     ..Default::default()
+/// };
+/// let mut chain = PostChain::new(
+///     device,
+///     queue,
+///     1920,
+///     1080,
+///     wgpu::TextureFormat::Bgra8UnormSrgb,
+///     config,
+/// );
+/// let frame = chain.start_frame(device, queue, output);
+/// // Render the scene into `*frame`, in `Rgba16Float` at 1440x810.
+/// frame.resolve();
+/// # }
+/// ```
+pub struct PostChain {
+    format: wgpu::TextureFormat,
+    config: PostChainConfig,
+    render_size: (u32, u32),
+    smaa: Option<SmaaTarget>,
+    auto_exposure: Option<AutoExposure>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    stages: Vec<Stage>,
+    // Texture the scene is rendered into, unless there are no stages.
+    scene: Option<wgpu::TextureView>,
+    intermediates: Vec<wgpu::TextureView>,
+    // Index into `intermediates` of the output of every stage but the last.
+    stage_outputs: Vec<usize>,
+}
+
+impl PostChain {
+    /// Create a chain writing output of the given size and format.
+    ///
+    /// Panics if the SMAA stage sets [`SmaaConfig::color_format`] or
+    /// [`SmaaConfig::background_depth`], `render_scale` isn't in `(0, 1]`, or auto-exposure is
+    /// enabled without tonemapping.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        config: PostChainConfig,
+    ) -> Self {
+        assert!(
+            config.render_scale > 0.0 && config.render_scale <= 1.0,
+            "PostChainConfig::render_scale must be in (0, 1]"
+        );
+        if let Some(ref smaa) = config.smaa {
+            assert!(
+                smaa.color_format.is_none() && smaa.background_depth.is_none(),
+                "PostChain doesn't support SmaaConfig::color_format or background_depth"
+            );
+        }
+        assert!(
+            config.auto_exposure.is_none() || config.tonemap.is_some(),
+            "PostChainConfig::auto_exposure requires tonemap to be set"
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smaa.post_chain.bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.shader.post_chain"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("post_chain.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("smaa.pipeline_layout.post_chain"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(format.into())],
+                    compilation_options: Default::default(),
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        let mut stages = Vec::new();
+        let built_in = |kind, entry_point, params| Stage {
+            kind,
+            pipeline: Some(create_pipeline(entry_point)),
+            params,
+            bind_group: None,
+        };
+        if let Some(tonemap) = config.tonemap {
+            let entry_point = match tonemap {
+                PostTonemap::Reinhard => "fs_tonemap_reinhard",
+                PostTonemap::Aces => "fs_tonemap_aces",
+            };
+            stages.push(built_in(
+                StageKind::Tonemap,
+                entry_point,
+                [config.exposure, 0.0, 0.0, 0.0],
+            ));
+        }
+        if config.smaa.is_some() {
+            stages.push(Stage {
+                kind: StageKind::Smaa,
+                pipeline: None,
+                params: [0.0; 4],
+                bind_group: None,
+            });
+        }
+        if config.render_scale < 1.0 {
+            // The parameters hold the output size, which is filled in by `resize`.
+            let entry_point = match config.upscale {
+                PostUpscale::Bilinear => "fs_upscale",
+                PostUpscale::Fsr1 => "fs_easu",
+            };
+            stages.push(built_in(StageKind::Upscale, entry_point, [0.0; 4]));
+        }
+        if let Some(strength) = config.sharpen {
+            let strength = strength.clamp(0.0, 1.0);
+            let (entry_point, sharpness) = match config.upscale {
+                PostUpscale::Bilinear => ("fs_sharpen", strength),
+                // RCAS takes the sharpness in stops below its maximum.
+                PostUpscale::Fsr1 => ("fs_rcas", (-2.0 * (1.0 - strength)).exp2()),
+            };
+            stages.push(built_in(
+                StageKind::Sharpen,
+                entry_point,
+                [sharpness, 0.0, 0.0, 0.0],
+            ));
+        }
+
+        let render_size = Self::scaled_size(width, height, config.render_scale);
+        let smaa = config.smaa.clone().map(|smaa| {
+            SmaaTarget::with_config(
+                device,
+                queue,
+                render_size.0,
+                render_size.1,
+                format,
+                SmaaMode::Smaa1X,
+                smaa,
+            )
+        });
+        let auto_exposure = config
+            .auto_exposure
+            .map(|settings| AutoExposure::new(device, &settings, config.exposure));
+        let mut chain = Self {
+            format,
+            config,
+            render_size,
+            smaa,
+            auto_exposure,
+            bind_group_layout,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("smaa.sampler.post_chain"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            stages,
+            scene: None,
+            intermediates: Vec::new(),
+            stage_outputs: Vec::new(),
+        };
+        chain.create_textures(device, width, height);
+        chain
+    }
+
+    fn scaled_size(width: u32, height: u32, scale: f32) -> (u32, u32) {
+        let scale = |size: u32| ((size as f32 * scale).ceil() as u32).max(1);
+        (scale(width), scale(height))
+    }
+
+    /// Size that the scene is rendered at, which is the output size scaled by
+    /// [`PostChainConfig::render_scale`].
+    pub fn render_size(&self) -> (u32, u32) {
+        self.render_size
+    }
+
+    /// Format of the view that the scene is rendered into.
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        match self.config.tonemap {
+            Some(_) => wgpu::TextureFormat::Rgba16Float,
+            None => self.format,
+        }
+    }
+
+    /// Recreate the intermediate textures for output of the given size.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.render_size = Self::scaled_size(width, height, self.config.render_scale);
+        if let Some(ref mut smaa) = self.smaa {
+            PostProcess::resize(smaa, device, self.render_size.0, self.render_size.1);
+        }
+        self.create_textures(device, width, height);
+    }
+
+    fn create_textures(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let create_view = |label, (width, height), format, usage| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+
+        self.scene = (!self.stages.is_empty()).then(|| {
+            create_view(
+                "smaa.post_chain.scene",
+                self.render_size,
+                self.scene_format(),
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+
+        // SMAA may write its output with compute shaders.
+        let usage = match self.config.smaa {
+            Some(SmaaConfig {
+                backend: SmaaBackend::Render,
+                ..
+            })
+            | None => wgpu::TextureUsages::RENDER_ATTACHMENT,
+            Some(_) => {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::STORAGE_BINDING
+            }
+        };
+        // Stages alternate between two textures of each size, starting with the render size.
+        let mut pools: [Vec<usize>; 2] = Default::default();
+        let mut counts = [0; 2];
+        self.intermediates.clear();
+        self.stage_outputs.clear();
+        for stage in &self.stages[..self.stages.len().saturating_sub(1)] {
+            let full_size = matches!(stage.kind, StageKind::Upscale | StageKind::Sharpen);
+            let (pool, size) = match full_size {
+                false => (0, self.render_size),
+                true => (1, (width, height)),
+            };
+            let slot = counts[pool] % 2;
+            counts[pool] += 1;
+            if slot == pools[pool].len() {
+                pools[pool].push(self.intermediates.len());
+                self.intermediates.push(create_view(
+                    "smaa.post_chain.intermediate",
+                    size,
+                    self.format,
+                    usage,
+                ));
+            }
+            self.stage_outputs.push(pools[pool][slot]);
+        }
+
+        for (i, stage) in self.stages.iter_mut().enumerate() {
+            if stage.kind == StageKind::Smaa {
+                continue;
+            }
+            if stage.kind == StageKind::Upscale {
+                stage.params = [width as f32, height as f32, 0.0, 0.0];
+            }
+            let input = match i {
+                0 => self.scene.as_ref().unwrap(),
+                _ => &self.intermediates[self.stage_outputs[i - 1]],
+            };
+            let contents: Vec<u8> = stage.params.iter().flat_map(|f| f.to_ne_bytes()).collect();
+            // Auto-exposure writes the exposure of the tonemap stage.
+            let auto_exposure = match stage.kind {
+                StageKind::Tonemap => self.auto_exposure.as_mut(),
+                _ => None,
+            };
+            let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("smaa.post_chain.params"),
+                contents: &contents,
+                usage: match auto_exposure {
+                    Some(_) => wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::STORAGE,
+                    None => wgpu::BufferUsages::UNIFORM,
+                },
+            });
+            if let Some(auto_exposure) = auto_exposure {
+                auto_exposure.bind(device, input, self.render_size, &params);
+            }
+            stage.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("smaa.post_chain.bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: params.as_entire_binding(),
+                    },
+                ],
+            }));
+        }
+    }
+
+    /// Start a frame that is written to `output_view` when it is resolved or dropped.
+    pub fn start_frame<'a>(
+        &'a mut self,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        output_view: &'a wgpu::TextureView,
+    ) -> PostChainFrame<'a> {
+        PostChainFrame {
+            chain: self,
+            device,
+            queue,
+            output_view,
+        }
+    }
+
+    /// Record every stage into `encoder`, with the last one writing to `output_view`.
+    fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+    ) {
+        if let Some(ref auto_exposure) = self.auto_exposure {
+            auto_exposure.encode(encoder);
+        }
+        for (i, stage) in self.stages.iter().enumerate() {
+            let output = match self.stage_outputs.get(i) {
+                Some(&index) => &self.intermediates[index],
+                None => output_view,
+            };
+            if stage.kind == StageKind::Smaa {
+                let input = match i {
+                    0 => self.scene.as_ref().unwrap(),
+                    _ => &self.intermediates[self.stage_outputs[i - 1]],
+                };
+                let smaa = self.smaa.as_mut().unwrap();
+                smaa.encode(device, encoder, input, output);
+                continue;
+            }
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("smaa.render_pass.post_chain"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(stage.pipeline.as_ref().unwrap());
+            pass.set_bind_group(0, stage.bind_group.as_ref().unwrap(), &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+/// Frame that the scene should be rendered into; can be created by a [`PostChain`].
+pub struct PostChainFrame<'a> {
+    chain: &'a mut PostChain,
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    output_view: &'a wgpu::TextureView,
+}
+
+impl<'a> PostChainFrame<'a> {
+    /// Run every stage of the chain and write the result into the output view.
+    pub fn resolve(self) {
+        std::mem::drop(self);
+    }
+}
+
+impl<'a> std::ops::Deref for PostChainFrame<'a> {
+    type Target = wgpu::TextureView;
+    fn deref(&self) -> &Self::Target {
+        self.chain.scene.as_ref().unwrap_or(self.output_view)
+    }
+}
+
+impl<'a> Drop for PostChainFrame<'a> {
+    fn drop(&mut self) {
+        if self.chain.stages.is_empty() {
+            return;
+        }
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("smaa.command_encoder.post_chain"),
+            });
+        self.chain
+            .encode(self.device, &mut encoder, self.output_view);
+        self.queue.submit(Some(encoder.finish()));
+    }
+}
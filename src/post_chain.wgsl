@@ -0,0 +1,207 @@
+// Built-in stages of a PostChain. Each one draws a fullscreen triangle that reads the output of
+// the previous stage.
+
+@group(0) @binding(0) var input_tex: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: vec4<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Tonemapping, with the exposure in params.x.
+
+@fragment
+fn fs_tonemap_reinhard(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(input_tex, vec2<i32>(position.xy), 0);
+    let rgb = max(color.rgb * params.x, vec3<f32>(0.0));
+    return vec4<f32>(rgb / (1.0 + rgb), color.a);
+}
+
+@fragment
+fn fs_tonemap_aces(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(input_tex, vec2<i32>(position.xy), 0);
+    let rgb = max(color.rgb * params.x, vec3<f32>(0.0));
+    // Krzysztof Narkowicz's fit of the ACES filmic curve.
+    let mapped = (rgb * (2.51 * rgb + 0.03)) / (rgb * (2.43 * rgb + 0.59) + 0.14);
+    return vec4<f32>(clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
+
+// Bilinear upscaling to the size of the render target.
+
+@fragment
+fn fs_upscale(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / params.xy;
+    return textureSampleLevel(input_tex, input_sampler, uv, 0.0);
+}
+
+// AMD FidelityFX Super Resolution 1.0 edge adaptive spatial upsampling (EASU), with the size of
+// the render target in params.xy. This follows FsrEasuF from ffx_fsr1.h, loading the 12 taps
+// around the output pixel instead of gathering them.
+
+fn easu_load(pixel: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(input_tex)) - 1;
+    return textureLoad(input_tex, clamp(pixel, vec2<i32>(0), size), 0);
+}
+
+// Luma times two, which is all that the direction and length estimates need.
+fn easu_luma(c: vec4<f32>) -> f32 {
+    return c.b * 0.5 + (c.r * 0.5 + c.g);
+}
+
+// Accumulate the direction and length of the edge around one of the four texels nearest to the
+// output pixel, weighted by its bilinear weight `w`. The lumas are of the texel `c` and its
+// neighbors above (`a`), left (`b`), right (`d`) and below (`e`).
+fn easu_set(w: f32, a: f32, b: f32, c: f32, d: f32, e: f32) -> vec3<f32> {
+    let dc = d - c;
+    let cb = c - b;
+    let dir_x = d - b;
+    var len_x = clamp(abs(dir_x) / max(max(abs(dc), abs(cb)), 1e-8), 0.0, 1.0);
+    len_x *= len_x;
+    let ec = e - c;
+    let ca = c - a;
+    let dir_y = e - a;
+    var len_y = clamp(abs(dir_y) / max(max(abs(ec), abs(ca)), 1e-8), 0.0, 1.0);
+    len_y *= len_y;
+    return vec3<f32>(dir_x, dir_y, len_x + len_y) * w;
+}
+
+// Weight of a tap at `offset` from the output pixel, from the approximated Lanczos lobe stretched
+// along the edge direction.
+fn easu_weight(offset: vec2<f32>, dir: vec2<f32>, len2: vec2<f32>, lobe: f32, clip: f32) -> f32 {
+    var v = vec2<f32>(dot(offset, dir), dot(offset, vec2<f32>(-dir.y, dir.x))) * len2;
+    let d2 = min(dot(v, v), clip);
+    var wb = 2.0 / 5.0 * d2 - 1.0;
+    var wa = lobe * d2 - 1.0;
+    wb *= wb;
+    wa *= wa;
+    wb = 25.0 / 16.0 * wb - (25.0 / 16.0 - 1.0);
+    return wb * wa;
+}
+
+@fragment
+fn fs_easu(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let scale = vec2<f32>(textureDimensions(input_tex)) / params.xy;
+    var pp = floor(position.xy) * scale + (0.5 * scale - 0.5);
+    let fp = floor(pp);
+    pp -= fp;
+    let origin = vec2<i32>(fp);
+
+    //    b c
+    //  e f g h
+    //  i j k l
+    //    n o
+    var offsets = array<vec2<i32>, 12>(
+        vec2<i32>(0, -1), vec2<i32>(1, -1),
+        vec2<i32>(-1, 0), vec2<i32>(0, 0), vec2<i32>(1, 0), vec2<i32>(2, 0),
+        vec2<i32>(-1, 1), vec2<i32>(0, 1), vec2<i32>(1, 1), vec2<i32>(2, 1),
+        vec2<i32>(0, 2), vec2<i32>(1, 2),
+    );
+    var taps: array<vec4<f32>, 12>;
+    var lumas: array<f32, 12>;
+    for (var i = 0; i < 12; i++) {
+        taps[i] = easu_load(origin + offsets[i]);
+        lumas[i] = easu_luma(taps[i]);
+    }
+
+    let dir_len = easu_set((1.0 - pp.x) * (1.0 - pp.y), lumas[0], lumas[2], lumas[3], lumas[4], lumas[7])
+        + easu_set(pp.x * (1.0 - pp.y), lumas[1], lumas[3], lumas[4], lumas[5], lumas[8])
+        + easu_set((1.0 - pp.x) * pp.y, lumas[3], lumas[6], lumas[7], lumas[8], lumas[10])
+        + easu_set(pp.x * pp.y, lumas[4], lumas[7], lumas[8], lumas[9], lumas[11]);
+
+    // Normalize the direction, defaulting to horizontal where there is no edge.
+    var dir = dir_len.xy;
+    let dir_r = dot(dir, dir);
+    if dir_r < 1.0 / 32768.0 {
+        dir = vec2<f32>(1.0, dir.y);
+    } else {
+        dir *= inverseSqrt(dir_r);
+    }
+    var len = dir_len.z * 0.5;
+    len *= len;
+    // Stretch the kernel along the edge, and shape the lobe from nearly bilinear where there is
+    // no edge to sharper along strong ones.
+    let stretch = dot(dir, dir) / max(abs(dir.x), abs(dir.y));
+    let len2 = vec2<f32>(1.0 + (stretch - 1.0) * len, 1.0 - 0.5 * len);
+    let lobe = 0.5 + ((1.0 / 4.0 - 0.04) - 0.5) * len;
+    let clip = 1.0 / lobe;
+
+    var color = vec4<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0; i < 12; i++) {
+        let w = easu_weight(vec2<f32>(offsets[i]) - pp, dir, len2, lobe, clip);
+        color += taps[i] * w;
+        weight += w;
+    }
+    // Clamp to the nearest four texels to remove the ringing of the negative lobes.
+    let lowest = min(min(taps[3], taps[4]), min(taps[7], taps[8]));
+    let highest = max(max(taps[3], taps[4]), max(taps[7], taps[8]));
+    return clamp(color / weight, lowest, highest);
+}
+
+// FSR 1.0 robust contrast adaptive sharpening (RCAS), with the sharpness as a linear factor in
+// params.x. This follows FsrRcasF from ffx_fsr1.h, limiting the sharpening lobe so that no
+// channel of the neighborhood is pushed past the range of the pixels around it.
+
+const RCAS_LIMIT: f32 = 0.25 - 1.0 / 16.0;
+
+@fragment
+fn fs_rcas(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let size = vec2<i32>(textureDimensions(input_tex)) - 1;
+    //    b
+    //  d e f
+    //    h
+    let e = textureLoad(input_tex, pixel, 0);
+    let b = textureLoad(input_tex, clamp(pixel + vec2<i32>(0, -1), vec2<i32>(0), size), 0).rgb;
+    let d = textureLoad(input_tex, clamp(pixel + vec2<i32>(-1, 0), vec2<i32>(0), size), 0).rgb;
+    let f = textureLoad(input_tex, clamp(pixel + vec2<i32>(1, 0), vec2<i32>(0), size), 0).rgb;
+    let h = textureLoad(input_tex, clamp(pixel + vec2<i32>(0, 1), vec2<i32>(0), size), 0).rgb;
+
+    let lowest = min(min(b, d), min(f, h));
+    let highest = max(max(b, d), max(f, h));
+    let hit_min = min(lowest, e.rgb) / max(4.0 * highest, vec3<f32>(1e-5));
+    let hit_max = (1.0 - max(highest, e.rgb)) / min(4.0 * lowest - 4.0, vec3<f32>(-1e-5));
+    let lobe_rgb = max(-hit_min, hit_max);
+    let lobe = max(-RCAS_LIMIT, min(max(lobe_rgb.r, max(lobe_rgb.g, lobe_rgb.b)), 0.0)) * params.x;
+
+    // Sharpen less where the center stands out from its neighbors on its own, which is noise.
+    let luma = vec4<f32>(
+        easu_luma(vec4<f32>(b, 0.0)),
+        easu_luma(vec4<f32>(d, 0.0)),
+        easu_luma(vec4<f32>(f, 0.0)),
+        easu_luma(vec4<f32>(h, 0.0)),
+    );
+    let e_luma = easu_luma(e);
+    let luma_range = max(max(max(luma.x, luma.y), max(luma.z, luma.w)), e_luma)
+        - min(min(min(luma.x, luma.y), min(luma.z, luma.w)), e_luma);
+    let noise = clamp(abs(dot(luma, vec4<f32>(0.25)) - e_luma) / max(luma_range, 1e-5), 0.0, 1.0);
+    let denoised_lobe = lobe * (1.0 - 0.5 * noise);
+
+    let sharpened = (denoised_lobe * (b + d + f + h) + e.rgb) / (4.0 * denoised_lobe + 1.0);
+    return vec4<f32>(sharpened, e.a);
+}
+
+// Contrast adaptive sharpening, with the strength between 0 and 1 in params.x. Pixels are
+// sharpened less where the contrast of their neighborhood is already high, which avoids ringing
+// around the edges that SMAA just smoothed.
+
+@fragment
+fn fs_sharpen(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let size = vec2<i32>(textureDimensions(input_tex)) - 1;
+    let center = textureLoad(input_tex, pixel, 0);
+    let north = textureLoad(input_tex, clamp(pixel + vec2<i32>(0, -1), vec2<i32>(0), size), 0).rgb;
+    let south = textureLoad(input_tex, clamp(pixel + vec2<i32>(0, 1), vec2<i32>(0), size), 0).rgb;
+    let west = textureLoad(input_tex, clamp(pixel + vec2<i32>(-1, 0), vec2<i32>(0), size), 0).rgb;
+    let east = textureLoad(input_tex, clamp(pixel + vec2<i32>(1, 0), vec2<i32>(0), size), 0).rgb;
+
+    let lowest = min(center.rgb, min(min(north, south), min(west, east)));
+    let highest = max(center.rgb, max(max(north, south), max(west, east)));
+    let amount = sqrt(clamp(min(lowest, 1.0 - highest) / max(highest, vec3<f32>(1e-5)), vec3<f32>(0.0), vec3<f32>(1.0)));
+    let weight = -amount / mix(8.0, 5.0, params.x);
+    let sharpened = (center.rgb + (north + south + west + east) * weight) / (1.0 + 4.0 * weight);
+    return vec4<f32>(clamp(sharpened, vec3<f32>(0.0), vec3<f32>(1.0)), center.a);
+}
@@ -0,0 +1,68 @@
+//! A minimal interface for post-processing effects, so that SMAA can be sequenced with effects
+//! from other crates without going through its own frame lifecycle.
+
+use crate::SmaaTarget;
+
+/// A post-processing effect that reads one texture and writes the result into another.
+///
+/// Effects only record commands into an encoder that the caller owns and submits, so a chain of
+/// them can share a single command buffer, each one reading the output of the previous one:
+///
+/// ```no_run
+/// # fn chain(device: &wgpu::Device, queue: &wgpu::Queue, effects: &mut [Box<dyn smaa::PostProcess>],
+/// #     views: &[wgpu::TextureView]) {
+/// let mut encoder = device.create_command_encoder(&Default::default());
+/// for (effect, views) in effects.iter_mut().zip(views.windows(2)) {
+///     effect.encode(device, &mut encoder, &views[0], &views[1]);
+/// }
+/// queue.submit(Some(encoder.finish()));
+/// # }
+/// ```
+///
+/// The trait is deliberately small so that other crates can implement it for their own effects.
+/// Which sizes, formats and usages the views need is up to each implementation.
+pub trait PostProcess {
+    /// Record the commands that apply the effect to `input` and write the result to `output`.
+    fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+    );
+
+    /// Recreate the resources that depend on the size of the images, before images of the new
+    /// size are encoded.
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32);
+}
+
+/// Antialiases `input` into `output` like [`SmaaTarget::process_batch`] does for a single image,
+/// with the same requirements on the views, but recording the passes into the caller's encoder.
+///
+/// Panics if antialiasing is disabled or [`SmaaConfig::background_depth`] is set.
+///
+/// [`SmaaConfig::background_depth`]: crate::SmaaConfig::background_depth
+impl PostProcess for SmaaTarget {
+    fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+    ) {
+        assert!(
+            self.config.background_depth.is_none(),
+            "PostProcess::encode doesn't support SmaaConfig::background_depth"
+        );
+        let backend = self
+            .inner
+            .as_ref()
+            .expect("PostProcess::encode requires antialiasing to be enabled");
+        self.reused_frames = 0;
+        Self::encode_batch(backend, device, encoder, &[(input, output)]);
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        SmaaTarget::resize(self, device, width, height);
+    }
+}
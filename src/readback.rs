@@ -0,0 +1,165 @@
+//! Reading the contents of output textures back into memory.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Buffer that a texture is being copied into, with each row padded to
+/// [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`].
+pub(crate) struct Readback {
+    buffer: wgpu::Buffer,
+    bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl Readback {
+    /// Record a copy of the first mip level of a 2D texture into a new buffer. The texture must
+    /// have been created with `COPY_SRC` usage.
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Self {
+        let bytes_per_row = texture.width()
+            * texture
+                .format()
+                .block_copy_size(None)
+                .expect("can't read back compressed textures");
+        let padded_bytes_per_row =
+            bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("smaa.buffer.readback"),
+            size: padded_bytes_per_row as u64 * texture.height() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..texture.size()
+            },
+        );
+        Readback {
+            buffer,
+            bytes_per_row,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Wait for the copy, which must have been submitted, and return the texels with tightly
+    /// packed rows.
+    #[cfg(any(feature = "image", feature = "test-utils"))]
+    pub fn wait(self, device: &wgpu::Device) -> Vec<u8> {
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        self.into_bytes()
+    }
+
+    /// Start mapping the buffer once the copy was submitted, returning a future that resolves
+    /// to the texels when it's done.
+    pub fn map_async(self) -> SmaaReadback {
+        let state = Arc::new(Mutex::new(MapState::default()));
+        let callback_state = state.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let mut state = callback_state.lock().unwrap();
+                state.result = Some(result);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+        SmaaReadback {
+            readback: Some(self),
+            state,
+        }
+    }
+
+    /// Texels of the mapped buffer with tightly packed rows.
+    fn into_bytes(self) -> Vec<u8> {
+        let data = self.buffer.slice(..).get_mapped_range();
+        data.chunks_exact(self.padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..self.bytes_per_row as usize])
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Future resolving to the texels of an output texture once they have been read back, returned
+/// by [`SmaaFrame::read_output_async`](crate::SmaaFrame::read_output_async).
+///
+/// The texels have the output's format, with tightly packed rows. Like any buffer mapping, this
+/// only completes when the device is polled: on native platforms either
+/// [`wgpu::Device::poll`] has to be called, for instance once per frame, or the future has to
+/// be awaited on another thread while the device is polled. On the web it completes on its own.
+pub struct SmaaReadback {
+    readback: Option<Readback>,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl Future for SmaaReadback {
+    type Output = Result<Vec<u8>, wgpu::BufferAsyncError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = {
+            let mut state = self.state.lock().unwrap();
+            match state.result.take() {
+                Some(result) => result,
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        let readback = self
+            .readback
+            .take()
+            .expect("SmaaReadback polled after completion");
+        Poll::Ready(result.map(|()| readback.into_bytes()))
+    }
+}
+
+/// Read back an output texture with 8-bit RGBA or BGRA texels as an image.
+#[cfg(feature = "image")]
+pub(crate) fn read_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> image::RgbaImage {
+    use wgpu::TextureFormat::*;
+    let bgra = match texture.format() {
+        Rgba8Unorm | Rgba8UnormSrgb => false,
+        Bgra8Unorm | Bgra8UnormSrgb => true,
+        format => panic!("can't convert {format:?} texels to an image"),
+    };
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("smaa.command_encoder.readback"),
+    });
+    let readback = Readback::new(device, &mut encoder, texture);
+    queue.submit(Some(encoder.finish()));
+    let mut texels = readback.wait(device);
+    if bgra {
+        for texel in texels.chunks_exact_mut(4) {
+            texel.swap(0, 2);
+        }
+    }
+    image::RgbaImage::from_raw(texture.width(), texture.height(), texels).unwrap()
+}
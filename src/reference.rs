@@ -0,0 +1,649 @@
+//! Straightforward CPU port of SMAA 1x, used by the `test-utils` feature to check the output of
+//! the GPU passes.
+//!
+//! Each pass follows the functions of `SMAA.hlsl` line by line, including the way they compute
+//! texture coordinates and rely on bilinear filtering, so that the results only differ from the
+//! GPU in rounding. Nothing is optimized: every pixel samples its textures one texel at a time.
+
+use crate::lookup::{AREATEX_HEIGHT, AREATEX_WIDTH, SEARCHTEX_HEIGHT, SEARCHTEX_WIDTH};
+use crate::{SmaaPreset, SmaaResources};
+
+const AREATEX_MAX_DISTANCE: f32 = 16.0;
+const AREATEX_MAX_DISTANCE_DIAG: f32 = 20.0;
+const AREATEX_SUBTEX_SIZE: f32 = 1.0 / 7.0;
+const SEARCHTEX_SIZE: [f32; 2] = [66.0, 33.0];
+const CORNER_ROUNDING_NORM: f32 = 0.25;
+const LOCAL_CONTRAST_ADAPTATION_FACTOR: f32 = 2.0;
+
+/// Parameters of the `SMAA_PRESET_*` defines.
+struct Preset {
+    threshold: f32,
+    max_search_steps: f32,
+    max_search_steps_diag: Option<f32>,
+    corner_rounding: bool,
+}
+impl Preset {
+    fn new(preset: SmaaPreset) -> Self {
+        let (threshold, max_search_steps, max_search_steps_diag) = match preset {
+            SmaaPreset::Low => (0.15, 4.0, None),
+            SmaaPreset::Medium => (0.1, 8.0, None),
+            SmaaPreset::High => (0.1, 16.0, Some(8.0)),
+            SmaaPreset::Ultra => (0.05, 32.0, Some(16.0)),
+        };
+        Preset {
+            threshold,
+            max_search_steps,
+            max_search_steps_diag,
+            corner_rounding: max_search_steps_diag.is_some(),
+        }
+    }
+}
+
+/// Texture sampled with clamp-to-edge addressing, like through the passes' sampler.
+struct Texture<const N: usize> {
+    width: usize,
+    height: usize,
+    texels: Vec<[f32; N]>,
+}
+impl<const N: usize> Texture<N> {
+    fn new(width: usize, height: usize) -> Self {
+        Texture {
+            width,
+            height,
+            texels: vec![[0.0; N]; width * height],
+        }
+    }
+
+    /// Texture holding normalized 8-bit channels.
+    fn from_unorm8(width: usize, height: usize, bytes: &[u8]) -> Self {
+        Texture {
+            width,
+            height,
+            texels: bytes
+                .chunks_exact(N)
+                .map(|texel| std::array::from_fn(|i| texel[i] as f32 / 255.0))
+                .collect(),
+        }
+    }
+
+    fn load(&self, x: isize, y: isize) -> [f32; N] {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.texels[y * self.width + x]
+    }
+
+    fn store(&mut self, x: usize, y: usize, texel: [f32; N]) {
+        self.texels[y * self.width + x] = texel;
+    }
+
+    /// `SMAASampleLevelZero`: bilinear filtering at normalized coordinates.
+    fn sample(&self, coord: [f32; 2]) -> [f32; N] {
+        self.sample_offset(coord, [0, 0])
+    }
+
+    /// `SMAASampleLevelZeroOffset`: bilinear filtering with an offset in texels.
+    fn sample_offset(&self, coord: [f32; 2], offset: [isize; 2]) -> [f32; N] {
+        let x = coord[0] * self.width as f32 - 0.5;
+        let y = coord[1] * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let x = x.floor() as isize + offset[0];
+        let y = y.floor() as isize + offset[1];
+        let (a, b) = (self.load(x, y), self.load(x + 1, y));
+        let (c, d) = (self.load(x, y + 1), self.load(x + 1, y + 1));
+        std::array::from_fn(|i| {
+            let top = a[i] + (b[i] - a[i]) * fx;
+            let bottom = c[i] + (d[i] - c[i]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+fn mad(a: f32, b: f32, c: f32) -> f32 {
+    a.mul_add(b, c)
+}
+
+fn step(edge: f32, x: f32) -> f32 {
+    if x >= edge {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Round to the nearest value that an 8-bit normalized texture can store.
+fn quantize(value: f32) -> f32 {
+    (value.clamp(0.0, 1.0) * 255.0).round() / 255.0
+}
+
+struct Smaa<'a> {
+    preset: Preset,
+    // `SMAA_RT_METRICS`.
+    rt: [f32; 4],
+    area: &'a Texture<2>,
+    search: &'a Texture<1>,
+    edges: Texture<2>,
+}
+
+impl Smaa<'_> {
+    fn luma(color: [f32; 4]) -> f32 {
+        0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+    }
+
+    /// `SMAALumaEdgeDetectionPS`.
+    fn edge_detection(&self, color: &Texture<4>, x: isize, y: isize) -> [f32; 2] {
+        let luma = |dx, dy| Self::luma(color.load(x + dx, y + dy));
+        let threshold = self.preset.threshold;
+
+        let l = luma(0, 0);
+        let l_left = luma(-1, 0);
+        let l_top = luma(0, -1);
+        let delta = [(l - l_left).abs(), (l - l_top).abs()];
+        let edges = [step(threshold, delta[0]), step(threshold, delta[1])];
+        if edges == [0.0, 0.0] {
+            return edges;
+        }
+
+        let l_right = luma(1, 0);
+        let l_bottom = luma(0, 1);
+        let mut max_delta = [
+            delta[0].max((l - l_right).abs()),
+            delta[1].max((l - l_bottom).abs()),
+        ];
+        let l_left_left = luma(-2, 0);
+        let l_top_top = luma(0, -2);
+        max_delta[0] = max_delta[0].max((l_left - l_left_left).abs());
+        max_delta[1] = max_delta[1].max((l_top - l_top_top).abs());
+        let final_delta = max_delta[0].max(max_delta[1]);
+
+        [
+            edges[0] * step(final_delta, LOCAL_CONTRAST_ADAPTATION_FACTOR * delta[0]),
+            edges[1] * step(final_delta, LOCAL_CONTRAST_ADAPTATION_FACTOR * delta[1]),
+        ]
+    }
+
+    /// `SMAADecodeDiagBilinearAccess` for a single pair of edges.
+    fn decode_diag_bilinear_access(e: [f32; 2]) -> [f32; 2] {
+        let r = e[0] * (5.0 * e[0] - 5.0 * 0.75).abs();
+        [r.round(), e[1].round()]
+    }
+
+    /// `SMAASearchDiag1`, returning the distance and whether the search ended on an edge.
+    fn search_diag1(&self, texcoord: [f32; 2], dir: [f32; 2], e: &mut [f32; 2]) -> [f32; 2] {
+        let max_steps = self.preset.max_search_steps_diag.unwrap();
+        let mut coord = [texcoord[0], texcoord[1], -1.0, 1.0];
+        while coord[2] < max_steps - 1.0 && coord[3] > 0.9 {
+            coord[0] = mad(self.rt[0], dir[0], coord[0]);
+            coord[1] = mad(self.rt[1], dir[1], coord[1]);
+            coord[2] += 1.0;
+            *e = self.edges.sample([coord[0], coord[1]]);
+            coord[3] = 0.5 * e[0] + 0.5 * e[1];
+        }
+        [coord[2], coord[3]]
+    }
+
+    /// `SMAASearchDiag2`.
+    fn search_diag2(&self, texcoord: [f32; 2], dir: [f32; 2], e: &mut [f32; 2]) -> [f32; 2] {
+        let max_steps = self.preset.max_search_steps_diag.unwrap();
+        let mut coord = [texcoord[0], texcoord[1], -1.0, 1.0];
+        coord[0] += 0.25 * self.rt[0];
+        while coord[2] < max_steps - 1.0 && coord[3] > 0.9 {
+            coord[0] = mad(self.rt[0], dir[0], coord[0]);
+            coord[1] = mad(self.rt[1], dir[1], coord[1]);
+            coord[2] += 1.0;
+            *e = Self::decode_diag_bilinear_access(self.edges.sample([coord[0], coord[1]]));
+            coord[3] = 0.5 * e[0] + 0.5 * e[1];
+        }
+        [coord[2], coord[3]]
+    }
+
+    /// `SMAAAreaDiag`.
+    fn area_diag(&self, dist: [f32; 2], e: [f32; 2]) -> [f32; 2] {
+        let pixel_size = [1.0 / AREATEX_WIDTH as f32, 1.0 / AREATEX_HEIGHT as f32];
+        let mut texcoord: [f32; 2] =
+            std::array::from_fn(|i| mad(AREATEX_MAX_DISTANCE_DIAG, e[i], dist[i]));
+        texcoord = std::array::from_fn(|i| mad(pixel_size[i], texcoord[i], 0.5 * pixel_size[i]));
+        texcoord[0] += 0.5;
+        // The subtexture offset is always zero for SMAA 1x.
+        texcoord[1] += AREATEX_SUBTEX_SIZE * 0.0;
+        self.area.sample(texcoord)
+    }
+
+    /// `SMAACalculateDiagWeights`.
+    fn calculate_diag_weights(&self, texcoord: [f32; 2], e: [f32; 2]) -> [f32; 2] {
+        let rt = self.rt;
+        let mut weights = [0.0; 2];
+
+        // Search for the line ends.
+        let mut end = [0.0; 2];
+        let mut d = [0.0; 4];
+        if e[0] > 0.0 {
+            let found = self.search_diag1(texcoord, [-1.0, 1.0], &mut end);
+            d[0] = found[0];
+            d[2] = found[1];
+            d[0] += (end[1] > 0.9) as u32 as f32;
+        } else {
+            d[0] = 0.0;
+            d[2] = 0.0;
+        }
+        let found = self.search_diag1(texcoord, [1.0, -1.0], &mut end);
+        d[1] = found[0];
+        d[3] = found[1];
+
+        if d[0] + d[1] > 2.0 {
+            // Fetch the crossing edges.
+            let coords = [
+                mad(-d[0] + 0.25, rt[0], texcoord[0]),
+                mad(d[0], rt[1], texcoord[1]),
+                mad(d[1], rt[0], texcoord[0]),
+                mad(-d[1] - 0.25, rt[1], texcoord[1]),
+            ];
+            // Both crossing edges of each side are fetched at once, then unpacked with
+            // `c.yxwz = SMAADecodeDiagBilinearAccess(c.xyzw)`.
+            let xy = Self::decode_diag_bilinear_access(
+                self.edges.sample_offset([coords[0], coords[1]], [-1, 0]),
+            );
+            let zw = Self::decode_diag_bilinear_access(
+                self.edges.sample_offset([coords[2], coords[3]], [1, 0]),
+            );
+            let c = [xy[1], xy[0], zw[1], zw[0]];
+
+            // Merge crossing edges at each side into a single value.
+            let mut cc = [mad(2.0, c[0], c[1]), mad(2.0, c[2], c[3])];
+            if d[2] >= 0.9 {
+                cc[0] = 0.0;
+            }
+            if d[3] >= 0.9 {
+                cc[1] = 0.0;
+            }
+            weights = self.area_diag([d[0], d[1]], cc);
+        }
+
+        // Search for the line ends in the other diagonal direction.
+        let found = self.search_diag2(texcoord, [-1.0, -1.0], &mut end);
+        d[0] = found[0];
+        d[2] = found[1];
+        if self.edges.sample_offset(texcoord, [1, 0])[0] > 0.0 {
+            let found = self.search_diag2(texcoord, [1.0, 1.0], &mut end);
+            d[1] = found[0];
+            d[3] = found[1];
+            d[1] += (end[1] > 0.9) as u32 as f32;
+        } else {
+            d[1] = 0.0;
+            d[3] = 0.0;
+        }
+
+        if d[0] + d[1] > 2.0 {
+            let coords = [
+                mad(-d[0], rt[0], texcoord[0]),
+                mad(-d[0], rt[1], texcoord[1]),
+                mad(d[1], rt[0], texcoord[0]),
+                mad(d[1], rt[1], texcoord[1]),
+            ];
+            let zw = self.edges.sample_offset([coords[2], coords[3]], [1, 0]);
+            let c = [
+                self.edges.sample_offset([coords[0], coords[1]], [-1, 0])[1],
+                self.edges.sample_offset([coords[0], coords[1]], [0, -1])[0],
+                zw[1],
+                zw[0],
+            ];
+            let mut cc = [mad(2.0, c[0], c[1]), mad(2.0, c[2], c[3])];
+            if d[2] >= 0.9 {
+                cc[0] = 0.0;
+            }
+            if d[3] >= 0.9 {
+                cc[1] = 0.0;
+            }
+            let area = self.area_diag([d[0], d[1]], cc);
+            weights[0] += area[1];
+            weights[1] += area[0];
+        }
+
+        weights
+    }
+
+    /// `SMAASearchLength`.
+    fn search_length(&self, e: [f32; 2], offset: f32) -> f32 {
+        // The search texture only stores the useful part of the lookup table, so the scale and
+        // bias map into the region of the full table that it was cropped from.
+        let scale = [SEARCHTEX_SIZE[0] * 0.5 - 1.0, -SEARCHTEX_SIZE[1] + 1.0];
+        let bias = [SEARCHTEX_SIZE[0] * offset + 0.5, SEARCHTEX_SIZE[1] - 0.5];
+        let packed_size = [SEARCHTEX_WIDTH as f32, SEARCHTEX_HEIGHT as f32];
+        let coord: [f32; 2] =
+            std::array::from_fn(|i| mad(scale[i] / packed_size[i], e[i], bias[i] / packed_size[i]));
+        self.search.sample(coord)[0]
+    }
+
+    /// `SMAASearchXLeft`.
+    fn search_x_left(&self, mut texcoord: [f32; 2], end: f32) -> f32 {
+        let mut e = [0.0, 1.0];
+        while texcoord[0] > end && e[1] > 0.8281 && e[0] == 0.0 {
+            e = self.edges.sample(texcoord);
+            texcoord[0] = mad(-2.0, self.rt[0], texcoord[0]);
+        }
+        let offset = mad(-(255.0 / 127.0), self.search_length(e, 0.0), 3.25);
+        mad(self.rt[0], offset, texcoord[0])
+    }
+
+    /// `SMAASearchXRight`.
+    fn search_x_right(&self, mut texcoord: [f32; 2], end: f32) -> f32 {
+        let mut e = [0.0, 1.0];
+        while texcoord[0] < end && e[1] > 0.8281 && e[0] == 0.0 {
+            e = self.edges.sample(texcoord);
+            texcoord[0] = mad(2.0, self.rt[0], texcoord[0]);
+        }
+        let offset = mad(-(255.0 / 127.0), self.search_length(e, 0.5), 3.25);
+        mad(-self.rt[0], offset, texcoord[0])
+    }
+
+    /// `SMAASearchYUp`.
+    fn search_y_up(&self, mut texcoord: [f32; 2], end: f32) -> f32 {
+        let mut e = [1.0, 0.0];
+        while texcoord[1] > end && e[0] > 0.8281 && e[1] == 0.0 {
+            e = self.edges.sample(texcoord);
+            texcoord[1] = mad(-2.0, self.rt[1], texcoord[1]);
+        }
+        let offset = mad(
+            -(255.0 / 127.0),
+            self.search_length([e[1], e[0]], 0.0),
+            3.25,
+        );
+        mad(self.rt[1], offset, texcoord[1])
+    }
+
+    /// `SMAASearchYDown`.
+    fn search_y_down(&self, mut texcoord: [f32; 2], end: f32) -> f32 {
+        let mut e = [1.0, 0.0];
+        while texcoord[1] < end && e[0] > 0.8281 && e[1] == 0.0 {
+            e = self.edges.sample(texcoord);
+            texcoord[1] = mad(2.0, self.rt[1], texcoord[1]);
+        }
+        let offset = mad(
+            -(255.0 / 127.0),
+            self.search_length([e[1], e[0]], 0.5),
+            3.25,
+        );
+        mad(-self.rt[1], offset, texcoord[1])
+    }
+
+    /// `SMAAArea`.
+    fn area(&self, dist: [f32; 2], e1: f32, e2: f32) -> [f32; 2] {
+        let pixel_size = [1.0 / AREATEX_WIDTH as f32, 1.0 / AREATEX_HEIGHT as f32];
+        let e = [(4.0 * e1).round(), (4.0 * e2).round()];
+        let mut texcoord: [f32; 2] =
+            std::array::from_fn(|i| mad(AREATEX_MAX_DISTANCE, e[i], dist[i]));
+        texcoord = std::array::from_fn(|i| mad(pixel_size[i], texcoord[i], 0.5 * pixel_size[i]));
+        // The subtexture offset is always zero for SMAA 1x.
+        texcoord[1] = mad(AREATEX_SUBTEX_SIZE, 0.0, texcoord[1]);
+        self.area.sample(texcoord)
+    }
+
+    /// `SMAADetectHorizontalCornerPattern` and `SMAADetectVerticalCornerPattern`, with the
+    /// offsets of the edges to check on either side of the line.
+    fn detect_corner_pattern(
+        &self,
+        weights: &mut [f32; 2],
+        texcoord: [f32; 4],
+        d: [f32; 2],
+        channel: usize,
+        offsets: [[isize; 2]; 4],
+    ) {
+        if !self.preset.corner_rounding {
+            return;
+        }
+        let left_right = [step(d[0], d[1]), step(d[1], d[0])];
+        let sum = left_right[0] + left_right[1];
+        let rounding = left_right.map(|x| (1.0 - CORNER_ROUNDING_NORM) * x / sum);
+
+        let start = [texcoord[0], texcoord[1]];
+        let end = [texcoord[2], texcoord[3]];
+        let mut factor = [1.0f32; 2];
+        factor[0] -= rounding[0] * self.edges.sample_offset(start, offsets[0])[channel];
+        factor[0] -= rounding[1] * self.edges.sample_offset(end, offsets[1])[channel];
+        factor[1] -= rounding[0] * self.edges.sample_offset(start, offsets[2])[channel];
+        factor[1] -= rounding[1] * self.edges.sample_offset(end, offsets[3])[channel];
+        weights[0] *= factor[0].clamp(0.0, 1.0);
+        weights[1] *= factor[1].clamp(0.0, 1.0);
+    }
+
+    /// `SMAABlendingWeightCalculationPS`, with the offsets of `SMAABlendingWeightCalculationVS`.
+    fn blending_weights(&self, x: usize, y: usize) -> [f32; 4] {
+        let rt = self.rt;
+        let max_steps = self.preset.max_search_steps;
+        let texcoord = [(x as f32 + 0.5) * rt[0], (y as f32 + 0.5) * rt[1]];
+        let pixcoord = [texcoord[0] * rt[2], texcoord[1] * rt[3]];
+        let offset = [
+            [
+                mad(rt[0], -0.25, texcoord[0]),
+                mad(rt[1], -0.125, texcoord[1]),
+                mad(rt[0], 1.25, texcoord[0]),
+                mad(rt[1], -0.125, texcoord[1]),
+            ],
+            [
+                mad(rt[0], -0.125, texcoord[0]),
+                mad(rt[1], -0.25, texcoord[1]),
+                mad(rt[0], -0.125, texcoord[0]),
+                mad(rt[1], 1.25, texcoord[1]),
+            ],
+        ];
+        let offset2 = [
+            mad(rt[0], -2.0 * max_steps, offset[0][0]),
+            mad(rt[0], 2.0 * max_steps, offset[0][2]),
+            mad(rt[1], -2.0 * max_steps, offset[1][1]),
+            mad(rt[1], 2.0 * max_steps, offset[1][3]),
+        ];
+
+        let mut weights = [0.0; 4];
+        let mut e = self.edges.load(x as isize, y as isize);
+
+        if e[1] > 0.0 {
+            // Edge at north.
+            let mut horizontal = true;
+            if self.preset.max_search_steps_diag.is_some() {
+                let diag = self.calculate_diag_weights(texcoord, e);
+                weights[0] = diag[0];
+                weights[1] = diag[1];
+                // Skip the horizontal and vertical processing if diagonal weights were found.
+                if diag[0] != -diag[1] {
+                    horizontal = false;
+                    e[0] = 0.0;
+                }
+            }
+
+            if horizontal {
+                let mut coords = [0.0; 4];
+                coords[0] = self.search_x_left([offset[0][0], offset[0][1]], offset2[0]);
+                coords[1] = offset[1][1];
+                let e1 = self.edges.sample([coords[0], coords[1]])[0];
+                coords[2] = self.search_x_right([offset[0][2], offset[0][3]], offset2[1]);
+                let d = [coords[0], coords[2]].map(|d| mad(rt[2], d, -pixcoord[0]).round().abs());
+                let sqrt_d = d.map(f32::sqrt);
+                let e2 = self.edges.sample_offset([coords[2], coords[1]], [1, 0])[0];
+                let mut area = self.area(sqrt_d, e1, e2);
+
+                coords[1] = texcoord[1];
+                self.detect_corner_pattern(
+                    &mut area,
+                    [coords[0], coords[1], coords[2], coords[1]],
+                    d,
+                    0,
+                    [[0, 1], [1, 1], [0, -2], [1, -2]],
+                );
+                weights[0] = area[0];
+                weights[1] = area[1];
+            }
+        }
+
+        if e[0] > 0.0 {
+            // Edge at west.
+            let mut coords = [0.0; 4];
+            coords[1] = self.search_y_up([offset[1][0], offset[1][1]], offset2[2]);
+            coords[0] = offset[0][0];
+            let e1 = self.edges.sample([coords[0], coords[1]])[1];
+            coords[2] = self.search_y_down([offset[1][2], offset[1][3]], offset2[3]);
+            let d = [coords[1], coords[2]].map(|d| mad(rt[3], d, -pixcoord[1]).round().abs());
+            let sqrt_d = d.map(f32::sqrt);
+            let e2 = self.edges.sample_offset([coords[0], coords[2]], [0, 1])[1];
+            let mut area = self.area(sqrt_d, e1, e2);
+
+            coords[0] = texcoord[0];
+            self.detect_corner_pattern(
+                &mut area,
+                [coords[0], coords[1], coords[0], coords[2]],
+                d,
+                1,
+                [[1, 0], [1, 1], [-2, 0], [-2, 1]],
+            );
+            weights[2] = area[0];
+            weights[3] = area[1];
+        }
+
+        weights
+    }
+
+    /// `SMAANeighborhoodBlendingPS`.
+    fn neighborhood_blending(
+        &self,
+        color: &Texture<4>,
+        blend: &Texture<4>,
+        x: usize,
+        y: usize,
+    ) -> [f32; 4] {
+        let rt = self.rt;
+        let (x, y) = (x as isize, y as isize);
+        let texcoord = [(x as f32 + 0.5) * rt[0], (y as f32 + 0.5) * rt[1]];
+        let current = blend.load(x, y);
+        let a = [
+            blend.load(x + 1, y)[3],
+            blend.load(x, y + 1)[1],
+            current[2],
+            current[0],
+        ];
+        if a.iter().sum::<f32>() < 1e-5 {
+            return color.load(x, y);
+        }
+
+        let horizontal = a[0].max(a[2]) > a[1].max(a[3]);
+        let (offset, weights) = if horizontal {
+            ([a[0], 0.0, a[2], 0.0], [a[0], a[2]])
+        } else {
+            ([0.0, a[1], 0.0, a[3]], [a[1], a[3]])
+        };
+        let sum = weights[0] + weights[1];
+        let weights = weights.map(|w| w / sum);
+        let coords = [
+            mad(offset[0], rt[0], texcoord[0]),
+            mad(offset[1], rt[1], texcoord[1]),
+            mad(offset[2], -rt[0], texcoord[0]),
+            mad(offset[3], -rt[1], texcoord[1]),
+        ];
+        let first = color.sample([coords[0], coords[1]]);
+        let second = color.sample([coords[2], coords[3]]);
+        std::array::from_fn(|i| weights[0] * first[i] + weights[1] * second[i])
+    }
+}
+
+/// Antialias RGBA pixels with 8 bits per channel on the CPU, as a [`SmaaTarget`] in
+/// [`SmaaMode::Smaa1X`] with the default configuration apart from `preset` would for a
+/// `Rgba8Unorm` target.
+///
+/// [`SmaaTarget`]: crate::SmaaTarget
+/// [`SmaaMode::Smaa1X`]: crate::SmaaMode::Smaa1X
+pub fn reference_antialias(width: u32, height: u32, pixels: &[u8], preset: SmaaPreset) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    assert_eq!(
+        pixels.len(),
+        width * height * 4,
+        "pixels must hold width * height RGBA texels"
+    );
+    let (area_bytes, search_bytes) = SmaaResources::default_bytes();
+    let area = Texture::from_unorm8(AREATEX_WIDTH as usize, AREATEX_HEIGHT as usize, &area_bytes);
+    let search = Texture::from_unorm8(
+        SEARCHTEX_WIDTH as usize,
+        SEARCHTEX_HEIGHT as usize,
+        &search_bytes,
+    );
+    let color = Texture::from_unorm8(width, height, pixels);
+
+    let mut smaa = Smaa {
+        preset: Preset::new(preset),
+        rt: [
+            1.0 / width as f32,
+            1.0 / height as f32,
+            width as f32,
+            height as f32,
+        ],
+        area: &area,
+        search: &search,
+        edges: Texture::new(width, height),
+    };
+    for y in 0..height {
+        for x in 0..width {
+            let edges = smaa.edge_detection(&color, x as isize, y as isize);
+            smaa.edges.store(x, y, edges);
+        }
+    }
+
+    let mut blend = Texture::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            blend.store(x, y, smaa.blending_weights(x, y).map(quantize));
+        }
+    }
+
+    let mut output = Vec::with_capacity(pixels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let texel = smaa.neighborhood_blending(&color, &blend, x, y);
+            output.extend(texel.map(|c| (quantize(c) * 255.0).round() as u8));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 16;
+
+    /// Black image that is white right of column `edge(y)` in every row `y`.
+    fn vertical_edge(edge: impl Fn(usize) -> usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(SIZE * SIZE * 4);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let value = if x >= edge(y) { 255 } else { 0 };
+                pixels.extend([value, value, value, 255]);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn straight_edge_is_left_untouched() {
+        // A line without ends has no coverage to estimate, so its blend weights are zero.
+        let input = vertical_edge(|_| 8);
+        let output = reference_antialias(SIZE as u32, SIZE as u32, &input, SmaaPreset::High);
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn stepped_edge_is_blended_into_a_ramp() {
+        // The edge moves one pixel to the right halfway down, a Z pattern whose ideal line runs
+        // diagonally from the top to the bottom of the image through the middle of the step.
+        let input = vertical_edge(|y| if y < SIZE / 2 { 8 } else { 9 });
+        let output = reference_antialias(SIZE as u32, SIZE as u32, &input, SmaaPreset::Low);
+        let pixel = |x: usize, y: usize| &output[(y * SIZE + x) * 4..][..4];
+        for y in 0..SIZE {
+            for x in (0..SIZE).filter(|&x| x != 8) {
+                assert_eq!(pixel(x, y), &input[(y * SIZE + x) * 4..][..4], "({x}, {y})");
+            }
+        }
+        let column: Vec<u8> = (0..SIZE).map(|y| pixel(8, y)[0]).collect();
+        assert!(column.windows(2).all(|w| w[0] > w[1]), "{column:?}");
+        assert!(
+            column[SIZE / 2 - 1] > 128 && column[SIZE / 2] < 128,
+            "{column:?}"
+        );
+        assert!(column[0] < 255 && column[SIZE - 1] > 0, "{column:?}");
+    }
+}
@@ -0,0 +1,331 @@
+//! Antialiasing with textures that the caller owns, for engines whose frame graph allocates
+//! transient textures for every frame.
+
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    rt_metrics, BindGroupLayouts, BindGroups, Bundles, IntermediateFormats, Pipelines, Resources,
+    SmaaBackend, SmaaConfig, SmaaEdgeDetection, SmaaResources, Upscaling, MISSING_DEPTH_VIEW,
+    MISSING_LUMA_VIEW, MISSING_SECONDARY_VIEW, STENCIL_FORMAT,
+};
+
+/// Views that [`SmaaRenderer::encode`] reads and writes for one frame. All of them must have
+/// the same size, a single mip level and a single layer. With [`SmaaRenderer::encode_crop`],
+/// only `edges`, `blend` and `stencil` have the size of the region, and the other views that of
+/// the whole texture.
+///
+/// The intermediate views don't have to keep their contents between frames, or even between
+/// calls: every pass clears what it writes, so they can come from a transient allocator and be
+/// reused for other passes once `encode` has been recorded.
+pub struct SmaaRendererViews<'a> {
+    /// The scene, in [`SmaaRenderer::scene_format`] with
+    /// [`wgpu::TextureUsages::TEXTURE_BINDING`].
+    pub color: &'a wgpu::TextureView,
+    /// Edges found by the first pass, in [`SmaaRenderer::edges_format`] with
+    /// [`wgpu::TextureUsages::RENDER_ATTACHMENT`] and [`wgpu::TextureUsages::TEXTURE_BINDING`].
+    pub edges: &'a wgpu::TextureView,
+    /// Blend weights calculated by the second pass, in [`SmaaRenderer::blend_format`] with the
+    /// same usages as `edges`.
+    pub blend: &'a wgpu::TextureView,
+    /// Stencil buffer marking the pixels with edges, in [`SmaaRenderer::stencil_format`] with
+    /// [`wgpu::TextureUsages::RENDER_ATTACHMENT`].
+    pub stencil: &'a wgpu::TextureView,
+    /// The scene's depth buffer, which [`SmaaConfig::background_depth`] and
+    /// [`SmaaEdgeDetection::Depth`] require, like [`crate::SmaaTarget::start_frame_with_depth`].
+    pub depth: Option<&'a wgpu::TextureView>,
+    /// Luma view that [`SmaaConfig::luma_input`] requires, like
+    /// [`crate::SmaaFrame::set_luma_input`].
+    pub luma: Option<&'a wgpu::TextureView>,
+    /// The antialiased image, in the format the renderer was created for with
+    /// [`wgpu::TextureUsages::RENDER_ATTACHMENT`]. It is blended onto rather than cleared if
+    /// [`SmaaConfig::output_blend_state`] is set.
+    pub output: &'a wgpu::TextureView,
+    /// `R8Unorm` view that [`SmaaConfig::secondary_output`] requires, like
+    /// [`crate::SmaaFrame::set_secondary_output`].
+    pub secondary: Option<&'a wgpu::TextureView>,
+}
+
+/// Region of larger views that [`SmaaRenderer::encode_crop`] antialiases, for instance one of
+/// several viewports rendered into an atlas.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SmaaCrop {
+    /// Width and height of the whole color, depth, luma, output and secondary views.
+    pub texture_size: (u32, u32),
+    /// Top left corner of the region.
+    pub origin: (u32, u32),
+    /// Width and height of the region, which the edges, blend and stencil views have.
+    pub size: (u32, u32),
+}
+
+/// The pipelines and lookup textures of SMAA without any textures of a particular size, which
+/// are borrowed from the caller for every frame instead.
+///
+/// Unlike a [`crate::SmaaTarget`], a renderer doesn't own long-lived color and intermediate
+/// targets, so it can be used by engines whose frame graph allocates transient resources. It
+/// never has to be resized, and one renderer can antialias images of different sizes.
+///
+/// Only [`SmaaBackend::Render`] is supported. Creating the renderer panics if the output format
+/// can't be rendered to, or if [`SmaaConfig::tile_classification`],
+/// [`SmaaConfig::skip_without_edges`], [`SmaaConfig::luma_output`],
+/// [`SmaaConfig::output_sample_count`], [`SmaaConfig::dynamic_resolution`],
+/// [`SmaaConfig::output_size`] or [`SmaaConfig::output_transform`] is set. Settings about the
+/// frame lifecycle, like [`SmaaConfig::frame_interval`], [`SmaaConfig::time_budget`] and
+/// [`SmaaConfig::profile`], have no effect.
+pub struct SmaaRenderer {
+    layouts: BindGroupLayouts,
+    pipelines: Pipelines,
+    resources: Arc<Resources>,
+    color_format: wgpu::TextureFormat,
+    formats: IntermediateFormats,
+    blends_output: bool,
+}
+
+impl SmaaRenderer {
+    /// Create the pipelines writing outputs of `format`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        config: SmaaConfig,
+    ) -> Self {
+        assert!(
+            config.backend == SmaaBackend::Render,
+            "SmaaRenderer only supports SmaaBackend::Render"
+        );
+        assert!(
+            !config.tile_classification
+                && !config.skip_without_edges
+                && !config.luma_output
+                && config.output_sample_count <= 1,
+            "SmaaRenderer doesn't support SmaaConfig::tile_classification, skip_without_edges, \
+            luma_output or output_sample_count"
+        );
+        assert!(
+            !Upscaling::needed(device, format, &config),
+            "SmaaRenderer needs an output format that can be rendered to, and doesn't support \
+            SmaaConfig::dynamic_resolution, output_size or output_transform"
+        );
+        let color_format = config.color_format.unwrap_or(format);
+        let layouts = BindGroupLayouts::new(
+            device,
+            color_format,
+            false,
+            false,
+            config.background_depth.is_some()
+                || matches!(config.edge_detection, SmaaEdgeDetection::Depth(_)),
+            config.luma_input,
+            true,
+        );
+        let (resources, pipelines) = SmaaResources::for_config(device, queue, &config, || {
+            Pipelines::new(device, format, &layouts, &config)
+        });
+        Self {
+            layouts,
+            pipelines,
+            resources,
+            color_format,
+            formats: IntermediateFormats::new(&config),
+            blends_output: config.output_blend_state.is_some(),
+        }
+    }
+
+    /// Format of [`SmaaRendererViews::color`], which is the output format unless
+    /// [`SmaaConfig::color_format`] is set.
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        self.color_format
+    }
+
+    /// Format of [`SmaaRendererViews::edges`], which depends on [`SmaaConfig::precision`] and
+    /// [`SmaaConfig::rgba_edges`].
+    pub fn edges_format(&self) -> wgpu::TextureFormat {
+        self.formats.edges
+    }
+
+    /// Format of [`SmaaRendererViews::blend`], which depends on [`SmaaConfig::precision`].
+    pub fn blend_format(&self) -> wgpu::TextureFormat {
+        self.formats.blend
+    }
+
+    /// Format of [`SmaaRendererViews::stencil`].
+    pub fn stencil_format(&self) -> wgpu::TextureFormat {
+        STENCIL_FORMAT
+    }
+
+    /// Lookup textures and samplers used by this renderer, for sharing with targets and other
+    /// renderers through [`SmaaConfig::resources`].
+    pub fn resources(&self) -> SmaaResources {
+        SmaaResources(self.resources.clone())
+    }
+
+    /// Record the passes antialiasing `views.color` of the given size into `views.output`.
+    ///
+    /// The bind groups, and a small uniform buffer holding the size, are created for each call.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        width: u32,
+        height: u32,
+        views: &SmaaRendererViews,
+    ) {
+        let crop = SmaaCrop {
+            texture_size: (width, height),
+            origin: (0, 0),
+            size: (width, height),
+        };
+        self.encode_crop(device, encoder, crop, views);
+    }
+
+    /// Record the passes antialiasing only a region of `views.color` into the same region of
+    /// `views.output`, which keeps the rest of its contents.
+    ///
+    /// Pixels just outside of the region are never read, so neighboring regions don't bleed
+    /// into each other, and the region is antialiased exactly like a texture of its size would
+    /// be. Panics if the region doesn't fit into the views.
+    pub fn encode_crop(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        crop: SmaaCrop,
+        views: &SmaaRendererViews,
+    ) {
+        let (x, y) = crop.origin;
+        let (width, height) = crop.size;
+        let (texture_width, texture_height) = crop.texture_size;
+        assert!(
+            x.checked_add(width)
+                .is_some_and(|right| right <= texture_width)
+                && y.checked_add(height)
+                    .is_some_and(|bottom| bottom <= texture_height),
+            "crop region must fit into the texture"
+        );
+        let cropped = crop.size != crop.texture_size;
+
+        let layouts = &self.layouts;
+        let resources = &self.resources;
+        let crop_metrics = [
+            x as f32,
+            y as f32,
+            1.0 / texture_width as f32,
+            1.0 / texture_height as f32,
+        ];
+        let mut uniform_data = rt_metrics(crop.size, crop.size);
+        for f in crop_metrics {
+            uniform_data.extend_from_slice(&f.to_ne_bytes());
+        }
+        let rt_uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("smaa.uniforms"),
+            usage: wgpu::BufferUsages::UNIFORM,
+            contents: &uniform_data,
+        });
+        let edge_view = match layouts.luma_input {
+            true => views.luma.expect(MISSING_LUMA_VIEW),
+            false => views.color,
+        };
+        let depth_view = layouts
+            .depth_input
+            .then(|| views.depth.expect(MISSING_DEPTH_VIEW));
+        let edge_detect_bind_group = BindGroups::edge_detect(
+            device,
+            layouts,
+            resources,
+            &rt_uniforms,
+            edge_view,
+            depth_view,
+        );
+        let blend_weight_bind_group =
+            BindGroups::blend_weight(device, layouts, resources, &rt_uniforms, views.edges);
+        let neighborhood_blending_bind_group = BindGroups::neighborhood_blending(
+            device,
+            layouts,
+            resources,
+            &rt_uniforms,
+            views.blend,
+            views.color,
+        );
+
+        let attachment = |view, load| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+        let stencil = |load, store| {
+            Some(wgpu::RenderPassDepthStencilAttachment {
+                view: views.stencil,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations { load, store }),
+            })
+        };
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[attachment(
+                    views.edges,
+                    wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                )],
+                depth_stencil_attachment: stencil(wgpu::LoadOp::Clear(0), wgpu::StoreOp::Store),
+                label: Some("smaa.render_pass.edge_detect"),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            Bundles::draw_edge_detect(&mut rpass, &self.pipelines, &edge_detect_bind_group);
+        }
+        {
+            // Pixels masked out by the stencil test must end up with zero weights.
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[attachment(
+                    views.blend,
+                    wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                )],
+                depth_stencil_attachment: stencil(wgpu::LoadOp::Load, wgpu::StoreOp::Discard),
+                label: Some("smaa.render_pass.blend_weight"),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            rpass.set_pipeline(&self.pipelines.blend_weight);
+            rpass.set_bind_group(0, &blend_weight_bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+        {
+            // The rest of the output belongs to other regions when cropping.
+            let load = match self.blends_output || cropped {
+                true => wgpu::LoadOp::Load,
+                false => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            };
+            let secondary_load = match cropped {
+                true => wgpu::LoadOp::Load,
+                false => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            };
+            let color_attachments = match self.formats.secondary {
+                Some(_) => vec![
+                    attachment(views.output, load),
+                    attachment(
+                        views.secondary.expect(MISSING_SECONDARY_VIEW),
+                        secondary_load,
+                    ),
+                ],
+                None => vec![attachment(views.output, load)],
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: None,
+                label: Some("smaa.render_pass.neighborhood_blending"),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            if cropped {
+                rpass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+                rpass.set_scissor_rect(x, y, width, height);
+            }
+            rpass.set_pipeline(&self.pipelines.neighborhood_blending);
+            rpass.set_bind_group(0, &neighborhood_blending_bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
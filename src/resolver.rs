@@ -0,0 +1,54 @@
+//! Object-safe interface to antialiasing targets, for engines that switch between several
+//! antialiasing methods at runtime.
+
+use crate::SmaaTarget;
+
+/// A target that the scene is rendered into and then resolved to an output, like a
+/// [`SmaaTarget`].
+///
+/// The trait is object safe, so that engines supporting several antialiasing methods can store
+/// a `Box<dyn AaResolver>` and swap it out at runtime. Implementations for MSAA or TAA live in
+/// the engine; [`SmaaTarget`] implements it out of the box.
+///
+/// A frame consists of rendering the scene into [`AaResolver::frame_view`] and then calling
+/// [`AaResolver::resolve`] with the same output view.
+pub trait AaResolver {
+    /// Change the size of the frames, like [`SmaaTarget::resize`].
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32);
+
+    /// View that the scene of a frame written to `output_view` should be rendered into. This
+    /// may be `output_view` itself if the resolver has nothing to do.
+    fn frame_view<'a>(&'a self, output_view: &'a wgpu::TextureView) -> &'a wgpu::TextureView;
+
+    /// Resolve the scene rendered into [`AaResolver::frame_view`] into `output_view`.
+    fn resolve(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        output_view: &wgpu::TextureView,
+    );
+}
+
+/// Frames are resolved like [`SmaaTarget::start_frame`] would. Panics when resolving if
+/// [`SmaaConfig::background_depth`](crate::SmaaConfig::background_depth),
+/// [`SmaaConfig::luma_input`](crate::SmaaConfig::luma_input) or
+/// [`SmaaEdgeDetection::Depth`](crate::SmaaEdgeDetection::Depth) is set, since there is no way
+/// to pass the depth or luma view.
+impl AaResolver for SmaaTarget {
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        SmaaTarget::resize(self, device, width, height);
+    }
+
+    fn frame_view<'a>(&'a self, output_view: &'a wgpu::TextureView) -> &'a wgpu::TextureView {
+        self.frame_color_target(output_view)
+    }
+
+    fn resolve(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        output_view: &wgpu::TextureView,
+    ) {
+        self.resolve_frame(device, queue, output_view, &[], None, Default::default());
+    }
+}
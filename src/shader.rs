@@ -1,4 +1,5 @@
-#[allow(dead_code)]
+use wgpu::naga;
+
 pub enum ShaderQuality {
     Low,
     Medium,
@@ -16,50 +17,114 @@ impl ShaderQuality {
     }
 }
 
+/// Conversion applied to color texels before they are used by one of the passes.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ColorTransform {
+    None,
+    SrgbToLinear,
+    LinearToSrgb,
+}
+impl ColorTransform {
+    fn as_define(&self) -> u32 {
+        match *self {
+            ColorTransform::None => 0,
+            ColorTransform::SrgbToLinear => 1,
+            ColorTransform::LinearToSrgb => 2,
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum ShaderStage {
     EdgeDetectionVS,
     LumaEdgeDetectionPS,
 
     BlendingWeightVS,
+    BlendingWeightTiledVS,
     BlendingWeightPS,
 
     NeighborhoodBlendingVS,
     NeighborhoodBlendingPS,
+    NeighborhoodBlendingCopyPS,
 
     #[allow(unused)]
     NeighborhoodBlendingAcesTonemapPS,
+
+    EdgeDetectionCS,
+    BlendingWeightCS,
+    NeighborhoodBlendingCS,
+    FusedNeighborhoodBlendingCS,
 }
 impl ShaderStage {
-    fn is_vertex_shader(&self) -> bool {
+    pub fn naga_stage(&self) -> wgpu::naga::ShaderStage {
         match *self {
             ShaderStage::EdgeDetectionVS
             | ShaderStage::BlendingWeightVS
-            | ShaderStage::NeighborhoodBlendingVS => true,
+            | ShaderStage::BlendingWeightTiledVS
+            | ShaderStage::NeighborhoodBlendingVS => wgpu::naga::ShaderStage::Vertex,
 
             ShaderStage::LumaEdgeDetectionPS
             | ShaderStage::BlendingWeightPS
             | ShaderStage::NeighborhoodBlendingPS
-            | ShaderStage::NeighborhoodBlendingAcesTonemapPS => false,
+            | ShaderStage::NeighborhoodBlendingCopyPS
+            | ShaderStage::NeighborhoodBlendingAcesTonemapPS => wgpu::naga::ShaderStage::Fragment,
+
+            ShaderStage::EdgeDetectionCS
+            | ShaderStage::BlendingWeightCS
+            | ShaderStage::NeighborhoodBlendingCS
+            | ShaderStage::FusedNeighborhoodBlendingCS => wgpu::naga::ShaderStage::Compute,
+        }
+    }
+    /// Declarations that have to come before SMAA.hlsl is included.
+    fn prelude(&self) -> &'static str {
+        match *self {
+            // Blend weights for the workgroup's pixels plus one pixel to the right and bottom are
+            // kept in workgroup memory, which the neighborhood blending reads instead of blendTex.
+            ShaderStage::FusedNeighborhoodBlendingCS => {
+                "#define SMAA_FUSED_SIZE (SMAA_WORKGROUP_SIZE + 1)
+                 shared vec4 blendWeights[SMAA_FUSED_SIZE * SMAA_FUSED_SIZE];
+                 vec4 SMAAFusedBlendWeights(vec2 coord) {
+                     ivec2 local = ivec2(coord * SMAA_RT_METRICS.zw) -
+                         ivec2(gl_WorkGroupID.xy) * SMAA_WORKGROUP_SIZE;
+                     return blendWeights[local.y * SMAA_FUSED_SIZE + local.x];
+                 }
+                 #define SMAA_SAMPLE_BLEND_WEIGHTS(tex, coord) SMAAFusedBlendWeights(coord)"
+            }
+            // The blend target can be larger than the color target, see `Intermediates`. Clamping
+            // to the pixels inside the color target samples it like a texture of the same size.
+            ShaderStage::NeighborhoodBlendingPS
+            | ShaderStage::NeighborhoodBlendingAcesTonemapPS
+            | ShaderStage::NeighborhoodBlendingCS => {
+                "vec2 SMAABlendWeightsPixel(vec2 coord) {
+                     return clamp(coord / SMAA_RT_METRICS.xy, vec2(0.5), SMAA_RT_METRICS.zw - 0.5);
+                 }
+                 #define SMAA_SAMPLE_BLEND_WEIGHTS(tex, coord) \\
+                     SMAASample(tex, SMAABlendWeightsPixel(coord) / vec2(textureSize(tex, 0)))"
+            }
+            // With a luma output every pixel has to be written, so edge detection returns zero
+            // edges instead of discarding pixels without any. Edge detection only point samples
+            // the color target, so mapping those samples is enough to read a cropped input.
+            ShaderStage::LumaEdgeDetectionPS => {
+                "#if SMAA_LUMA_OUTPUT
+                 #define discard return float2(0.0, 0.0)
+                 #endif
+                 #if SMAA_CROP
+                 #define SMAASamplePoint(tex, coord) \\
+                     textureLod(sampler2D(tex, linearSampler), SMAACropCoord(coord), 0)
+                 #endif"
+            }
+            _ => "",
         }
     }
     fn as_str(&self) -> &'static str {
         match *self {
+            // The edges target can be larger than the color target, so the texture coordinates are
+            // calculated by the pixel shader instead.
             ShaderStage::EdgeDetectionVS => {
-                "layout(location = 0) out float4 offset0;
-                 layout(location = 1) out float4 offset1;
-                 layout(location = 2) out float4 offset2;
-                 layout(location = 3) out float2 texcoord;
-                 void main() {
+                "void main() {
                      if(gl_VertexIndex == 0) gl_Position = vec4(-1, -1, 1, 1);
                      if(gl_VertexIndex == 1) gl_Position = vec4(-1,  3, 1, 1);
         	         if(gl_VertexIndex == 2) gl_Position = vec4( 3, -1, 1, 1);
-                     texcoord = gl_Position.xy * vec2(0.5, -0.5) + vec2(0.5);
-                     float4 offset[3];
-                     SMAAEdgeDetectionVS(texcoord, offset);
-                     offset0=offset[0];
-                     offset1=offset[1];
-                     offset2=offset[2];
                  }"
             }
             ShaderStage::BlendingWeightVS => {
@@ -80,6 +145,31 @@ impl ShaderStage {
                      offset2=offset[2];
                  }"
             }
+            // Draws a quad covering one of the tiles that `tile_classification_shader` found edges in.
+            ShaderStage::BlendingWeightTiledVS => {
+                "layout(location = 0) in uint tile;
+                 layout(location = 0) out float2 pixcoord;
+                 layout(location = 1) out float4 offset0;
+                 layout(location = 2) out float4 offset1;
+                 layout(location = 3) out float4 offset2;
+                 layout(location = 4) out float2 texcoord;
+                 void main() {
+                     int vertex = int(gl_VertexIndex);
+                     int index = vertex % 3;
+                     vec2 corner = vec2(index & 1, index >> 1);
+                     if(vertex >= 3) corner = vec2(1) - corner;
+                     float tileSize = float(SMAA_TILE_SIZE);
+                     vec2 tileOrigin = vec2(tile & 0xffffu, tile >> 16) * tileSize;
+                     vec2 position = min(tileOrigin + corner * tileSize, SMAA_RT_METRICS.zw);
+                     texcoord = position * SMAA_RT_METRICS.xy;
+                     gl_Position = vec4(texcoord * vec2(2, -2) + vec2(-1, 1), 1, 1);
+                     float4 offset[3];
+                     SMAABlendingWeightCalculationVS(texcoord, pixcoord, offset);
+                     offset0=offset[0];
+                     offset1=offset[1];
+                     offset2=offset[2];
+                 }"
+            }
             ShaderStage::NeighborhoodBlendingVS => {
                 "layout(location = 0) out float4 offset;
                  layout(location = 1) out float2 texcoord;
@@ -92,20 +182,86 @@ impl ShaderStage {
                  }"
             }
             ShaderStage::LumaEdgeDetectionPS => {
-                "layout(location = 0) in float4 offset0;
-                 layout(location = 1) in float4 offset1;
-                 layout(location = 2) in float4 offset2;
-                 layout(location = 3) in float2 texcoord;
-                 layout(set = 0, binding = 2) uniform texture2D colorTex;
+                "layout(set = 0, binding = 2) uniform texture2D colorTex;
+                 #if SMAA_DEPTH_EARLY_OUT || SMAA_DEPTH_EDGES
+                 layout(set = 0, binding = 3) uniform texture2D depthTex;
+                 #endif
+                 // Targets that can't render to two channel formats store edges in four channels.
+                 #if SMAA_RGBA_EDGES
+                 layout(location = 0) out float4 OutColor;
+                 #else
                  layout(location = 0) out float2 OutColor;
-                 void main() {
+                 #endif
+                 #if SMAA_LUMA_OUTPUT
+                 layout(location = 1) out float OutLuma;
+                 #endif
+                 #if SMAA_DEPTH_EDGES
+                 // The distance for orthographic projections and its inverse for perspective ones,
+                 // which both map linearly to the depth buffer, with neighbors beyond the rendered
+                 // part clamped like colors.
+                 float SMAALinearizeDepth(ivec2 pixel) {
+                    ivec2 maxPixel = ivec2(SMAA_RT_METRICS.zw) - 1;
+                    ivec2 source = clamp(pixel, ivec2(0), maxPixel) + SMAA_CROP_ORIGIN;
+                    float depth = texelFetch(depthTex, source, 0).r;
+                    return SMAA_DEPTH_LINEARIZE.x + SMAA_DEPTH_LINEARIZE.y * depth;
+                 }
+                 // Edges where the distance differs from that of the left or top neighbor by more
+                 // than the threshold, relative to the nearer of the two with perspective
+                 // projections. In inverse distance this is the difference relative to the smaller
+                 // value.
+                 float2 SMAALinearDepthEdgeDetection(float2 texcoord) {
+                    ivec2 pixel = ivec2(texcoord / SMAA_RT_METRICS.xy);
+                    float depth = SMAALinearizeDepth(pixel);
+                    float2 neighbors = float2(SMAALinearizeDepth(pixel - ivec2(1, 0)),
+                        SMAALinearizeDepth(pixel - ivec2(0, 1)));
+                    #if SMAA_DEPTH_ORTHOGRAPHIC
+                    float2 edges = step(SMAA_DEPTH_ORTHOGRAPHIC_THRESHOLD, abs(depth - neighbors));
+                    #else
+                    float2 delta = abs(depth - neighbors) / max(min(float2(depth), neighbors), 1e-30);
+                    float2 edges = step(SMAA_DEPTH_THRESHOLD, delta);
+                    #endif
+                    if (dot(edges, float2(1.0, 1.0)) == 0.0)
+                        discard;
+                    return edges;
+                 }
+                 #endif
+                 float2 SMAADetectEdges(float2 texcoord) {
+                    #if SMAA_DEPTH_EARLY_OUT
+                    if(SMAAIsBackground(depthTex, texcoord)) discard;
+                    #endif
+                    #if SMAA_DEPTH_EDGES
+                    return SMAALinearDepthEdgeDetection(texcoord);
+                    #else
                     float4 offset[3];
-                    offset[0] = offset0;
-                    offset[1] = offset1;
-                    offset[2] = offset2;
-                    OutColor = SMAALumaEdgeDetectionPS(texcoord, offset, colorTex);
+                    offset[0] = mad(SMAA_RT_METRICS.xyxy, float4(-1.0, 0.0, 0.0, -1.0), texcoord.xyxy);
+                    offset[1] = mad(SMAA_RT_METRICS.xyxy, float4( 1.0, 0.0, 0.0,  1.0), texcoord.xyxy);
+                    offset[2] = mad(SMAA_RT_METRICS.xyxy, float4(-2.0, 0.0, 0.0, -2.0), texcoord.xyxy);
+                    // So do the neighbors beyond the part of the color target that was rendered.
+                    offset[1] = min(offset[1], ((SMAA_RT_METRICS.zw - 0.5) * SMAA_RT_METRICS.xy).xyxy);
+                    return SMAA_EDGE_DETECTION_PS(texcoord, offset, colorTex);
+                    #endif
+                 }
+                 void main() {
+                    // Pixels beyond the color target repeat the edges along its border.
+                    float2 texcoord = min(gl_FragCoord.xy, SMAA_RT_METRICS.zw - 0.5) *
+                        SMAA_RT_METRICS.xy;
+                    #if SMAA_LUMA_OUTPUT
+                    // The same luma that SMAALumaEdgeDetectionPS calculates for the pixel.
+                    OutLuma = dot(SMAA_EDGE_SPACE_COLOR(SMAASamplePoint(colorTex, texcoord)).rgb,
+                        float3(0.2126, 0.7152, 0.0722));
+                    #endif
+                    #if SMAA_RGBA_EDGES
+                    OutColor = float4(SMAADetectEdges(texcoord), 0.0, 0.0);
+                    #else
+                    OutColor = SMAADetectEdges(texcoord);
+                    #endif
                  }"
             }
+            // The searches in this pass deliberately don't use textureGather: each bilinear fetch
+            // of the edges texture already returns both channels of four texels, packed into
+            // values that the search texture decodes, while a gather only returns one channel.
+            // The crossing edge and corner fetches never read the same channel from one 2x2
+            // footprint either, so gathering would only add fetches.
             ShaderStage::BlendingWeightPS => {
                 "layout(location = 0) in float2 pixcoord;
                  layout(location = 1) in float4 offset0;
@@ -132,8 +288,48 @@ impl ShaderStage {
                  layout(set = 0, binding = 2) uniform texture2D colorTex;
                  layout(set = 0, binding = 3) uniform texture2D blendTex;
                  layout(location = 0) out float4 OutColor;
+                 #if SMAA_SECONDARY_OUTPUT
+                 layout(location = 1) out float OutSecondary;
+                 #endif
+                 void main() {
+                     OutColor = SMAAFromBlendSpace(
+                         SMAANeighborhoodBlendingPS(texcoord, offset, colorTex, blendTex));
+                     #if SMAA_SECONDARY_OUTPUT == 1
+                     // The weights that SMAANeighborhoodBlendingPS fetches for the pixel.
+                     float4 a = float4(SMAASample(blendTex, offset.xy).a,
+                         SMAASample(blendTex, offset.zw).g, SMAASample(blendTex, texcoord).zx);
+                     OutSecondary = max(max(a.x, a.y), max(a.z, a.w));
+                     #elif SMAA_SECONDARY_OUTPUT == 2
+                     OutSecondary = dot(OutColor.rgb, float3(0.2126, 0.7152, 0.0722));
+                     #endif
+                     #if SMAA_DEBUG_DIFFERENCE
+                     // Compare against what NeighborhoodBlendingCopyPS writes for pixels without edges.
+                     float4 original = SMAAFromBlendSpace(SMAA_SAMPLE_COLOR(colorTex, texcoord));
+                     OutColor = float4(
+                         abs(OutColor.rgb - original.rgb) * SMAA_DEBUG_AMPLIFICATION, 1.0);
+                     #endif
+                 }"
+            }
+            // Used instead of the neighborhood blending when no edges were found, and produces the
+            // same result as it does for pixels without any blend weights.
+            ShaderStage::NeighborhoodBlendingCopyPS => {
+                "layout(location = 0) in float4 offset;
+                 layout(location = 1) in float2 texcoord;
+                 layout(set = 0, binding = 2) uniform texture2D colorTex;
+                 layout(location = 0) out float4 OutColor;
+                 #if SMAA_SECONDARY_OUTPUT
+                 layout(location = 1) out float OutSecondary;
+                 #endif
                  void main() {
-                     OutColor = SMAANeighborhoodBlendingPS(texcoord, offset, colorTex, blendTex);
+                     OutColor = SMAAFromBlendSpace(SMAA_SAMPLE_COLOR(colorTex, texcoord));
+                     #if SMAA_SECONDARY_OUTPUT == 1
+                     OutSecondary = 0.0;
+                     #elif SMAA_SECONDARY_OUTPUT == 2
+                     OutSecondary = dot(OutColor.rgb, float3(0.2126, 0.7152, 0.0722));
+                     #endif
+                     #if SMAA_DEBUG_DIFFERENCE
+                     OutColor = float4(0.0, 0.0, 0.0, 1.0);
+                     #endif
                  }"
             }
             // See: https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve
@@ -149,35 +345,549 @@ impl ShaderStage {
                      float c = 2.43f;
                      float d = 0.59f;
                      float e = 0.14f;
-                     OutColor = SMAANeighborhoodBlendingPS(texcoord, offset, colorTex, blendTex);
+                     OutColor = SMAAFromBlendSpace(
+                         SMAANeighborhoodBlendingPS(texcoord, offset, colorTex, blendTex));
                      vec3 x = OutColor.rgb;
                      OutColor.rgb = clamp((x*(a*x+b))/(x*(c*x+d)+e), vec3(0), vec3(1));
                  }"
             }
+            // The compute stages run both halves of a pass for each pixel, and write zeros instead of
+            // discarding pixels since storage textures can't be cleared by a render pass.
+            ShaderStage::EdgeDetectionCS => {
+                "layout(local_size_x = SMAA_WORKGROUP_SIZE, local_size_y = SMAA_WORKGROUP_SIZE) in;
+                 layout(set = 0, binding = 2) uniform texture2D colorTex;
+                 layout(set = 0, binding = 3, SMAA_EDGES_FORMAT) uniform writeonly image2D edgesImage;
+                 #if SMAA_DEPTH_EARLY_OUT
+                 layout(set = 0, binding = 4) uniform texture2D depthTex;
+                 #endif
+                 void main() {
+                     ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                     if(any(greaterThanEqual(pixel, imageSize(edgesImage)))) return;
+                     // Pixels beyond the color target repeat the edges along its border.
+                     ivec2 source = min(pixel, ivec2(SMAA_RT_METRICS.zw) - 1);
+                     float2 texcoord = (vec2(source) + 0.5) * SMAA_RT_METRICS.xy;
+                     #if SMAA_DEPTH_EARLY_OUT
+                     if(SMAAIsBackground(depthTex, texcoord)) {
+                         imageStore(edgesImage, pixel, vec4(0));
+                         return;
+                     }
+                     #endif
+                     float4 offset[3];
+                     SMAAEdgeDetectionVS(texcoord, offset);
+                     float2 edges = SMAA_EDGE_DETECTION_PS(texcoord, offset, colorTex);
+                     imageStore(edgesImage, pixel, vec4(edges, 0, 0));
+                 }"
+            }
+            ShaderStage::BlendingWeightCS => {
+                "layout(local_size_x = SMAA_WORKGROUP_SIZE, local_size_y = SMAA_WORKGROUP_SIZE) in;
+                 layout(set = 0, binding = 2) uniform texture2D edgesTex;
+                 layout(set = 0, binding = 3) uniform texture2D areaTex;
+                 layout(set = 0, binding = 4) uniform texture2D searchTex;
+                 layout(set = 0, binding = 5, SMAA_BLEND_FORMAT) uniform writeonly image2D blendImage;
+                 void main() {
+                     ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                     if(any(greaterThanEqual(pixel, ivec2(SMAA_RT_METRICS.zw)))) return;
+                     float2 texcoord = (vec2(pixel) + 0.5) * SMAA_RT_METRICS.xy;
+                     float2 pixcoord;
+                     float4 offset[3];
+                     SMAABlendingWeightCalculationVS(texcoord, pixcoord, offset);
+                     vec4 subsampleIndices = vec4(0);
+                     imageStore(blendImage, pixel, SMAABlendingWeightCalculationPS(texcoord,
+                         pixcoord, offset, edgesTex, areaTex, searchTex, subsampleIndices));
+                 }"
+            }
+            ShaderStage::NeighborhoodBlendingCS => {
+                "layout(local_size_x = SMAA_WORKGROUP_SIZE, local_size_y = SMAA_WORKGROUP_SIZE) in;
+                 layout(set = 0, binding = 2) uniform texture2D colorTex;
+                 layout(set = 0, binding = 3) uniform texture2D blendTex;
+                 layout(set = 0, binding = 4, SMAA_OUTPUT_FORMAT) uniform writeonly image2D outputImage;
+                 void main() {
+                     ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                     if(any(greaterThanEqual(pixel, ivec2(SMAA_RT_METRICS.zw)))) return;
+                     float2 texcoord = (vec2(pixel) + 0.5) * SMAA_RT_METRICS.xy;
+                     float4 offset;
+                     SMAANeighborhoodBlendingVS(texcoord, offset);
+                     imageStore(outputImage, pixel, SMAAFromBlendSpace(
+                         SMAANeighborhoodBlendingPS(texcoord, offset, colorTex, blendTex)));
+                 }"
+            }
+            ShaderStage::FusedNeighborhoodBlendingCS => {
+                "layout(local_size_x = SMAA_WORKGROUP_SIZE, local_size_y = SMAA_WORKGROUP_SIZE) in;
+                 layout(set = 0, binding = 2) uniform texture2D colorTex;
+                 layout(set = 0, binding = 3) uniform texture2D edgesTex;
+                 layout(set = 0, binding = 4) uniform texture2D areaTex;
+                 layout(set = 0, binding = 5) uniform texture2D searchTex;
+                 layout(set = 0, binding = 6, SMAA_OUTPUT_FORMAT) uniform writeonly image2D outputImage;
+                 void main() {
+                     ivec2 origin = ivec2(gl_WorkGroupID.xy) * SMAA_WORKGROUP_SIZE;
+                     ivec2 maxPixel = ivec2(SMAA_RT_METRICS.zw) - 1;
+                     int fusedPixels = SMAA_FUSED_SIZE * SMAA_FUSED_SIZE;
+                     int workgroupPixels = SMAA_WORKGROUP_SIZE * SMAA_WORKGROUP_SIZE;
+                     for(int i = int(gl_LocalInvocationIndex); i < fusedPixels; i += workgroupPixels) {
+                         // Clamp like the sampler would when reading past the edge of blendTex.
+                         ivec2 local = ivec2(i % SMAA_FUSED_SIZE, i / SMAA_FUSED_SIZE);
+                         ivec2 weightPixel = min(origin + local, maxPixel);
+                         float2 texcoord = (vec2(weightPixel) + 0.5) * SMAA_RT_METRICS.xy;
+                         float2 pixcoord;
+                         float4 offset[3];
+                         SMAABlendingWeightCalculationVS(texcoord, pixcoord, offset);
+                         vec4 subsampleIndices = vec4(0);
+                         blendWeights[i] = SMAABlendingWeightCalculationPS(texcoord, pixcoord,
+                             offset, edgesTex, areaTex, searchTex, subsampleIndices);
+                     }
+                     barrier();
+
+                     ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+                     if(any(greaterThanEqual(pixel, ivec2(SMAA_RT_METRICS.zw)))) return;
+                     float2 texcoord = (vec2(pixel) + 0.5) * SMAA_RT_METRICS.xy;
+                     float4 offset;
+                     SMAANeighborhoodBlendingVS(texcoord, offset);
+                     // The blend weights come from workgroup memory, so colorTex is only passed
+                     // as a placeholder for blendTex.
+                     imageStore(outputImage, pixel, SMAAFromBlendSpace(
+                         SMAANeighborhoodBlendingPS(texcoord, offset, colorTex, colorTex)));
+                 }"
+            }
         }
     }
 }
 
+// Helpers for converting colors between the stored representation and the spaces that edge
+// detection and neighborhood blending operate in. When the blending pass needs a conversion or
+// the color texture isn't filterable, bilinear filtering of the color texture is done by hand so
+// that texels are converted before they are mixed.
+const COLOR_FUNCTIONS: &str = "
+    // The sRGB curve is mirrored for negative values, so extended range (scRGB) colors survive.
+    vec3 SMAASrgbToLinear(vec3 c) {
+        vec3 a = abs(c);
+        return sign(c) * mix(a / 12.92, pow((a + 0.055) / 1.055, vec3(2.4)), step(vec3(0.04045), a));
+    }
+    vec3 SMAALinearToSrgb(vec3 c) {
+        vec3 a = abs(c);
+        return sign(c) * mix(a * 12.92, 1.055 * pow(a, vec3(1.0 / 2.4)) - 0.055, step(vec3(0.0031308), a));
+    }
+    // Reversible tonemaps selected by SMAA_PRE_TONEMAP. The per-channel curves are mirrored for
+    // negative values like the sRGB curve.
+    #if SMAA_PRE_TONEMAP == 1
+    // Karis-style reversible tonemap, see: http://graphicrants.blogspot.com/2013/12/tone-mapping.html
+    vec3 SMAATonemap(vec3 c) {
+        vec3 a = abs(c);
+        return c / (1.0 + max(max(a.r, a.g), a.b));
+    }
+    vec3 SMAATonemapInverse(vec3 c) {
+        vec3 a = abs(c);
+        return c / max(1.0 - max(max(a.r, a.g), a.b), 1e-5);
+    }
+    #elif SMAA_PRE_TONEMAP == 2
+    vec3 SMAATonemap(vec3 c) {
+        return c / (1.0 + abs(c));
+    }
+    vec3 SMAATonemapInverse(vec3 c) {
+        return c / max(1.0 - abs(c), 1e-5);
+    }
+    #elif SMAA_PRE_TONEMAP == 3
+    // Krzysztof Narkowicz's ACES fit (a x^2 + b x) / (c x^2 + d x + e), inverted by solving the
+    // quadratic. The curve approaches a / c, which the inverse stays just below.
+    vec3 SMAATonemap(vec3 c) {
+        vec3 x = abs(c);
+        return sign(c) * (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    }
+    vec3 SMAATonemapInverse(vec3 c) {
+        vec3 y = min(abs(c), vec3(2.51 / 2.43 - 1e-4));
+        vec3 a = 2.51 - 2.43 * y;
+        vec3 b = 0.03 - 0.59 * y;
+        return sign(c) * (sqrt(b * b + 4.0 * a * 0.14 * y) - b) / (2.0 * a);
+    }
+    #elif SMAA_PRE_TONEMAP == 4
+    vec3 SMAATonemap(vec3 c) {
+        return SMAACustomTonemap(c);
+    }
+    vec3 SMAATonemapInverse(vec3 c) {
+        return SMAACustomTonemapInverse(c);
+    }
+    #endif
+
+    // The conversions apply to the colors that premultiplied alpha was multiplied into, so that
+    // colors are blended premultiplied in every space.
+    #if SMAA_PREMULTIPLIED_ALPHA
+    vec4 SMAAUnpremultiply(vec4 c) {
+        return vec4(c.a > 0.0 ? c.rgb / c.a : vec3(0.0), c.a);
+    }
+    vec4 SMAAPremultiply(vec4 c) {
+        return vec4(c.rgb * c.a, c.a);
+    }
+    #else
+    #define SMAAUnpremultiply(c) (c)
+    #define SMAAPremultiply(c) (c)
+    #endif
+
+    // Single-channel formats read as `(r, 0, 0, 1)`. Spreading the channel over all three makes
+    // the luma edge detection see it unweighted, and writes it as gray to color outputs.
+    vec4 SMAAGrayscale(vec4 c) {
+        return vec4(c.rrr, c.a);
+    }
+
+    vec4 SMAAToEdgeSpace(vec4 c) {
+        c = SMAAUnpremultiply(c);
+        #if SMAA_PRE_TONEMAP
+        c.rgb = SMAATonemap(c.rgb);
+        #endif
+        #if SMAA_EDGE_TRANSFORM == 1
+        c.rgb = SMAASrgbToLinear(c.rgb);
+        #elif SMAA_EDGE_TRANSFORM == 2
+        c.rgb = SMAALinearToSrgb(c.rgb);
+        #endif
+        return SMAAPremultiply(c);
+    }
+    vec4 SMAAToBlendSpace(vec4 c) {
+        c = SMAAUnpremultiply(c);
+        #if SMAA_PRE_TONEMAP
+        c.rgb = SMAATonemap(c.rgb);
+        #endif
+        #if SMAA_BLEND_TRANSFORM == 1
+        c.rgb = SMAASrgbToLinear(c.rgb);
+        #elif SMAA_BLEND_TRANSFORM == 2
+        c.rgb = SMAALinearToSrgb(c.rgb);
+        #endif
+        #if SMAA_PREMULTIPLY_STRAIGHT
+        // Straight colors are blended premultiplied when the output alpha is composited, so that
+        // the colors of transparent pixels don't bleed into their neighbors.
+        return vec4(c.rgb * c.a, c.a);
+        #else
+        return SMAAPremultiply(c);
+        #endif
+    }
+    // SMPTE ST 2084 (PQ) encoding of linear Rec.709 colors for HDR10 outputs.
+    vec3 SMAAEncodePQ(vec3 c) {
+        mat3 rec709ToRec2020 = mat3(
+            0.6274040, 0.0690970, 0.0163916,
+            0.3292820, 0.9195400, 0.0880132,
+            0.0433136, 0.0113612, 0.8955950);
+        vec3 nits = rec709ToRec2020 * c * SMAA_PAPER_WHITE_NITS;
+        vec3 y = clamp(nits, vec3(0), vec3(SMAA_PEAK_NITS)) / 10000.0;
+        vec3 ym = pow(y, vec3(0.1593017578125));
+        return pow((0.8359375 + 18.8515625 * ym) / (1.0 + 18.6875 * ym), vec3(78.84375));
+    }
+
+    vec4 SMAAFromBlendSpace(vec4 c) {
+        #if SMAA_SINGLE_CHANNEL
+        c = SMAAGrayscale(c);
+        #endif
+        #if SMAA_PREMULTIPLY_STRAIGHT
+        c = vec4(c.a > 0.0 ? c.rgb / c.a : vec3(0.0), c.a);
+        #else
+        c = SMAAUnpremultiply(c);
+        #endif
+        #if SMAA_BLEND_TRANSFORM == 1
+        c.rgb = SMAALinearToSrgb(c.rgb);
+        #elif SMAA_BLEND_TRANSFORM == 2
+        c.rgb = SMAASrgbToLinear(c.rgb);
+        #endif
+        #if SMAA_PRE_TONEMAP
+        c.rgb = SMAATonemapInverse(c.rgb);
+        #endif
+        #if SMAA_OUTPUT_TRANSFORM == 1
+        c.rgb = SMAASrgbToLinear(c.rgb);
+        #elif SMAA_OUTPUT_TRANSFORM == 2
+        c.rgb = SMAALinearToSrgb(c.rgb);
+        #endif
+        #if SMAA_OUTPUT_ENCODING == 1
+        c.rgb = SMAAEncodePQ(c.rgb);
+        #elif SMAA_OUTPUT_ENCODING == 2
+        c.rgb *= SMAA_PAPER_WHITE_NITS / 80.0;
+        #endif
+        // Alpha for the surface's composite alpha mode, from colors that are unpremultiplied now
+        // if they were premultiplied.
+        #if SMAA_OUTPUT_ALPHA == 1
+        return vec4(SMAAPremultiply(c).rgb, 1.0);
+        #elif SMAA_OUTPUT_ALPHA == 2
+        return vec4(c.rgb * c.a, c.a);
+        #elif SMAA_OUTPUT_ALPHA == 3
+        return c;
+        #else
+        return SMAAPremultiply(c);
+        #endif
+    }
+
+    #if SMAA_CROP
+    // The color, depth and luma textures are larger than the intermediate targets, which only
+    // cover the region of them starting at `uniforms.crop.xy`. Reads outside of the region are
+    // clamped to its edges, like reads outside of a texture of that size.
+    #define SMAA_CROP_ORIGIN ivec2(uniforms.crop.xy)
+    vec2 SMAACropCoord(vec2 coord) {
+        vec2 pixel = clamp(coord / SMAA_RT_METRICS.xy, vec2(0.5), SMAA_RT_METRICS.zw - 0.5);
+        return (pixel + uniforms.crop.xy) * uniforms.crop.zw;
+    }
+    #else
+    #define SMAA_CROP_ORIGIN ivec2(0)
+    // Stay within the part of the color target that was rendered, which is smaller than the
+    // texture with `SmaaConfig::dynamic_resolution`.
+    #define SMAACropCoord(coord) clamp(coord, \
+        0.5 * SMAA_RT_METRICS.xy, (SMAA_RT_METRICS.zw - 0.5) * SMAA_RT_METRICS.xy)
+    #endif
+
+    #if SMAA_BLEND_TRANSFORM != 0 || SMAA_PRE_TONEMAP || SMAA_MANUAL_FILTERING || SMAA_PREMULTIPLY_STRAIGHT
+    vec4 SMAAFetchBlendSpace(texture2D tex, ivec2 pixel) {
+        ivec2 maxCoord = ivec2(SMAA_RT_METRICS.zw) - 1;
+        pixel = clamp(pixel, ivec2(0), maxCoord) + SMAA_CROP_ORIGIN;
+        return SMAAToBlendSpace(texelFetch(tex, pixel, 0));
+    }
+    vec4 SMAASampleBlendSpace(texture2D tex, vec2 coord) {
+        vec2 pos = coord / SMAA_RT_METRICS.xy - 0.5;
+        vec2 f = fract(pos);
+        ivec2 base = ivec2(floor(pos));
+        vec4 a = SMAAFetchBlendSpace(tex, base);
+        vec4 b = SMAAFetchBlendSpace(tex, base + ivec2(1, 0));
+        vec4 c = SMAAFetchBlendSpace(tex, base + ivec2(0, 1));
+        vec4 d = SMAAFetchBlendSpace(tex, base + ivec2(1, 1));
+        return mix(mix(a, b, f.x), mix(c, d, f.x), f.y);
+    }
+    #define SMAA_SAMPLE_COLOR(tex, coord) SMAASampleBlendSpace(tex, coord)
+    #else
+    #define SMAA_SAMPLE_COLOR(tex, coord) SMAASampleLevelZero(tex, SMAACropCoord(coord))
+    #endif
+
+    #if SMAA_LUMA_INPUT
+    // Edges are detected in a luma texture instead of the colors. The weights that the luma edge
+    // detection applies to the channels sum to one, so this passes the luma through unchanged.
+    #define SMAA_EDGE_SPACE_COLOR(sample) vec4(sample.rrr, 1.0)
+    #elif SMAA_SINGLE_CHANNEL
+    #define SMAA_EDGE_SPACE_COLOR(sample) SMAAGrayscale(SMAAToEdgeSpace(sample))
+    #elif SMAA_EDGE_TRANSFORM != 0 || SMAA_PRE_TONEMAP
+    #define SMAA_EDGE_SPACE_COLOR(sample) SMAAToEdgeSpace(sample)
+    #else
+    #define SMAA_EDGE_SPACE_COLOR(sample) (sample)
+    #endif
+
+    #if SMAA_ALPHA_EDGES == 1
+    // The luma edge detection then sees the alpha channel as the luma.
+    #define SMAA_EDGE_DETECTION_COLOR(sample) vec4((sample).aaa, 1.0)
+    #elif SMAA_ALPHA_EDGES == 2
+    // The color edge detection takes the largest difference of any channel, which is then the
+    // larger of the differences in luma and alpha.
+    vec4 SMAALumaAndAlpha(vec4 c) {
+        return vec4(dot(c.rgb, vec3(0.2126, 0.7152, 0.0722)), c.a, 0.0, 1.0);
+    }
+    #define SMAA_EDGE_DETECTION_COLOR(sample) SMAALumaAndAlpha(SMAA_EDGE_SPACE_COLOR(sample))
+    #else
+    #define SMAA_EDGE_DETECTION_COLOR(sample) SMAA_EDGE_SPACE_COLOR(sample)
+    #endif
+    #if SMAA_ALPHA_EDGES == 2
+    #define SMAA_EDGE_DETECTION_PS SMAAColorEdgeDetectionPS
+    #else
+    #define SMAA_EDGE_DETECTION_PS SMAALumaEdgeDetectionPS
+    #endif
+
+    // Edges are stored on the pixel below or to the right of them, so a pixel can only have any
+    // if it or its left or top neighbor isn't part of the background.
+    bool SMAAIsBackground(texture2D depthTex, vec2 texcoord) {
+        ivec2 pixel = ivec2(texcoord / SMAA_RT_METRICS.xy);
+        float depth = texelFetch(depthTex, pixel + SMAA_CROP_ORIGIN, 0).r;
+        float left = texelFetch(depthTex, max(pixel - ivec2(1, 0), ivec2(0)) + SMAA_CROP_ORIGIN, 0).r;
+        float top = texelFetch(depthTex, max(pixel - ivec2(0, 1), ivec2(0)) + SMAA_CROP_ORIGIN, 0).r;
+        return all(equal(vec3(depth, left, top), vec3(SMAA_BACKGROUND_DEPTH)));
+    }
+";
+
+/// Width and height in pixels of the tiles that blend weights are calculated for when tile
+/// classification is enabled.
+pub const TILE_SIZE: u32 = 16;
+
+/// Width and height of the workgroups used by the compute stages.
+pub const WORKGROUP_SIZE: u32 = 8;
+
+/// GLSL image format qualifier for a storage texture format, if it can be used as one.
+pub fn storage_format_qualifier(format: wgpu::TextureFormat) -> Option<&'static str> {
+    Some(match format {
+        wgpu::TextureFormat::Rgba8Unorm => "rgba8",
+        wgpu::TextureFormat::Rgba8Snorm => "rgba8_snorm",
+        wgpu::TextureFormat::Rgba16Float => "rgba16f",
+        wgpu::TextureFormat::Rgba32Float => "rgba32f",
+        _ => return None,
+    })
+}
+
+/// Replaces the samples of SMAA.hlsl that use implicit derivatives with ones of the base level.
+const LEVEL_ZERO_SAMPLES: &str = "#define SMAASample(tex, coord) SMAASampleLevelZero(tex, coord)
+    #define SMAASampleOffset(tex, coord, offset) SMAASampleLevelZeroOffset(tex, coord, offset)";
+
 pub(crate) struct ShaderSource {
     pub quality: ShaderQuality,
+    pub blend_transform: ColorTransform,
+    pub edge_transform: ColorTransform,
+    pub pre_tonemap: crate::SmaaPreTonemap,
+    pub manual_filtering: bool,
+    pub output_transform: ColorTransform,
+    pub output_encoding: crate::SmaaOutputEncoding,
+    pub background_depth: Option<f32>,
+    pub rgba_edges: bool,
+    pub luma_output: bool,
+    pub luma_input: bool,
+    pub secondary_output: crate::SmaaSecondaryOutput,
+    pub deterministic: bool,
+    pub debug_output: crate::SmaaDebugOutput,
+    pub edge_detection: crate::SmaaEdgeDetection,
+    pub premultiplied_alpha: bool,
+    pub composite_alpha_mode: wgpu::CompositeAlphaMode,
+    pub single_channel: bool,
+    pub cropped_input: bool,
 }
 impl ShaderSource {
-    fn get_stage(&self, stage: ShaderStage) -> String {
+    fn defines(&self) -> String {
+        let pre_tonemap = match self.pre_tonemap {
+            crate::SmaaPreTonemap::Karis => 1,
+            crate::SmaaPreTonemap::Reinhard => 2,
+            crate::SmaaPreTonemap::Aces => 3,
+            crate::SmaaPreTonemap::Custom { .. } => 4,
+            crate::SmaaPreTonemap::None => 0,
+        };
+        let custom_tonemap = match self.pre_tonemap {
+            crate::SmaaPreTonemap::Custom { tonemap, inverse } => format!(
+                "vec3 SMAACustomTonemap(vec3 c) {{ {tonemap} }}
+                vec3 SMAACustomTonemapInverse(vec3 c) {{ {inverse} }}"
+            ),
+            _ => String::new(),
+        };
+        let (encoding, paper_white, peak) = match self.output_encoding {
+            crate::SmaaOutputEncoding::None => (0, 0.0, 0.0),
+            crate::SmaaOutputEncoding::Hdr10 {
+                paper_white_nits,
+                peak_nits,
+            } => (1, paper_white_nits, peak_nits),
+            crate::SmaaOutputEncoding::ScRgb { paper_white_nits } => (2, paper_white_nits, 0.0),
+        };
+        // Coefficients of `a + b * depth`, which for perspective projections is the inverse
+        // distance, 1 / near at the near plane and 1 / far (0 for an infinite far plane) at the
+        // far plane, and for orthographic projections the distance itself.
+        let (depth_edges, near, far, orthographic_threshold) = match self.edge_detection {
+            crate::SmaaEdgeDetection::Luma
+            | crate::SmaaEdgeDetection::Alpha
+            | crate::SmaaEdgeDetection::LumaAndAlpha => (None, 0.0, 0.0, None),
+            crate::SmaaEdgeDetection::Depth(crate::SmaaDepthProjection::Perspective {
+                near,
+                far,
+                reversed_z,
+            }) => (Some(reversed_z), 1.0 / near, 1.0 / far, None),
+            crate::SmaaEdgeDetection::Depth(crate::SmaaDepthProjection::Orthographic {
+                near,
+                far,
+                reversed_z,
+                threshold,
+            }) => (Some(reversed_z), near, far, Some(threshold)),
+        };
+        let output_alpha = match self.composite_alpha_mode {
+            wgpu::CompositeAlphaMode::Opaque => 1,
+            wgpu::CompositeAlphaMode::PreMultiplied => 2,
+            wgpu::CompositeAlphaMode::PostMultiplied => 3,
+            wgpu::CompositeAlphaMode::Auto | wgpu::CompositeAlphaMode::Inherit => 0,
+        };
+        let secondary_output = match self.secondary_output {
+            crate::SmaaSecondaryOutput::None => 0,
+            crate::SmaaSecondaryOutput::EdgeMask => 1,
+            crate::SmaaSecondaryOutput::Luma => 2,
+        };
+        let difference_amplification = match self.debug_output {
+            crate::SmaaDebugOutput::None => None,
+            crate::SmaaDebugOutput::Difference { amplification } => Some(amplification),
+        };
+        let alpha_edges = match self.edge_detection {
+            crate::SmaaEdgeDetection::Alpha => 1,
+            crate::SmaaEdgeDetection::LumaAndAlpha => 2,
+            _ => 0,
+        };
+        let linearize = match depth_edges {
+            Some(true) => (far, near - far),
+            _ => (near, far - near),
+        };
+        format!(
+            "#define SMAA_BLEND_TRANSFORM {}
+            #define SMAA_EDGE_TRANSFORM {}
+            #define SMAA_PRE_TONEMAP {}
+            #define SMAA_MANUAL_FILTERING {}
+            #define SMAA_OUTPUT_TRANSFORM {}
+            #define SMAA_OUTPUT_ENCODING {}
+            #define SMAA_PAPER_WHITE_NITS {:?}
+            #define SMAA_PEAK_NITS {:?}
+            #define SMAA_DEPTH_EARLY_OUT {}
+            #define SMAA_BACKGROUND_DEPTH {:?}
+            #define SMAA_RGBA_EDGES {}
+            #define SMAA_LUMA_OUTPUT {}
+            #define SMAA_LUMA_INPUT {}
+            #define SMAA_SECONDARY_OUTPUT {}
+            #define SMAA_DEBUG_DIFFERENCE {}
+            #define SMAA_DEBUG_AMPLIFICATION {:?}
+            #define SMAA_PREMULTIPLIED_ALPHA {}
+            #define SMAA_OUTPUT_ALPHA {}
+            #define SMAA_PREMULTIPLY_STRAIGHT {}
+            #define SMAA_ALPHA_EDGES {}
+            #define SMAA_SINGLE_CHANNEL {}
+            #define SMAA_CROP {}
+            #define SMAA_DEPTH_EDGES {}
+            #define SMAA_DEPTH_LINEARIZE vec2({:?}, {:?})
+            #define SMAA_DEPTH_ORTHOGRAPHIC {}
+            #define SMAA_DEPTH_ORTHOGRAPHIC_THRESHOLD {:?}
+            {}",
+            self.blend_transform.as_define(),
+            self.edge_transform.as_define(),
+            pre_tonemap,
+            self.manual_filtering as u32,
+            self.output_transform.as_define(),
+            encoding,
+            paper_white,
+            peak,
+            self.background_depth.is_some() as u32,
+            self.background_depth.unwrap_or(0.0),
+            self.rgba_edges as u32,
+            self.luma_output as u32,
+            self.luma_input as u32,
+            secondary_output,
+            difference_amplification.is_some() as u32,
+            difference_amplification.unwrap_or(0.0),
+            self.premultiplied_alpha as u32,
+            output_alpha,
+            (!self.premultiplied_alpha && matches!(output_alpha, 2 | 3)) as u32,
+            alpha_edges,
+            self.single_channel as u32,
+            self.cropped_input as u32,
+            depth_edges.is_some() as u32,
+            linearize.0,
+            linearize.1,
+            orthographic_threshold.is_some() as u32,
+            orthographic_threshold.unwrap_or(0.0),
+            custom_tonemap,
+        )
+    }
+    fn get_stage(&self, stage: ShaderStage, stage_defines: &str) -> String {
         format!(
             "#version 450 core
             #extension GL_EXT_samplerless_texture_functions: require
             #define SMAA_GLSL_4
             #define SMAA_PRESET_{0}
-            #define SMAA_INCLUDE_{1} 0
+            #define SMAA_INCLUDE_VS {1}
+            #define SMAA_INCLUDE_PS {2}
+            {3}
+            #define SMAA_TILE_SIZE {4}
             #define SMAA_RT_METRICS uniforms.rt
+            {5}
             layout(set = 0, binding = 0) uniform sampler linearSampler;
             layout(set = 0, binding = 1) uniform UniformBlock {{
                 vec4 rt;
+                #if SMAA_CROP
+                vec4 crop;
+                #endif
             }} uniforms;
-            {2}
-            {3}",
+            {6}
+            {7}
+            {8}
+            {9}",
             self.quality.as_str(),
-            if stage.is_vertex_shader() { "PS" } else { "VS" },
+            (stage.naga_stage() != wgpu::naga::ShaderStage::Fragment) as u32,
+            (stage.naga_stage() != wgpu::naga::ShaderStage::Vertex) as u32,
+            stage_defines,
+            TILE_SIZE,
+            self.defines(),
+            COLOR_FUNCTIONS,
+            stage.prelude(),
             include_str!("../third_party/smaa/SMAA.hlsl"),
             stage.as_str(),
         )
@@ -188,17 +898,206 @@ impl ShaderSource {
         stage: ShaderStage,
         name: &'static str,
     ) -> wgpu::ShaderModule {
+        self.create_module(device, stage, name, &self.source(stage))
+    }
+    /// GLSL source of one of the render stages.
+    pub fn source(&self, stage: ShaderStage) -> String {
+        // Implicit derivatives are left to the driver, so deterministic shaders avoid them.
+        let stage_defines = match self.deterministic {
+            true => LEVEL_ZERO_SAMPLES,
+            false => "",
+        };
+        self.get_stage(stage, stage_defines)
+    }
+    /// Compile one of the compute stages, which write their results to storage textures of the
+    /// given formats.
+    pub fn get_compute_shader(
+        &self,
+        device: &wgpu::Device,
+        stage: ShaderStage,
+        name: &'static str,
+        edges_format: wgpu::TextureFormat,
+        blend_format: wgpu::TextureFormat,
+        output_format: wgpu::TextureFormat,
+    ) -> wgpu::ShaderModule {
+        let source = self.compute_source(stage, edges_format, blend_format, output_format);
+        self.create_module(device, stage, name, &source)
+    }
+    /// GLSL source of one of the compute stages. Panics if one of the formats can't be used as a
+    /// storage texture.
+    pub fn compute_source(
+        &self,
+        stage: ShaderStage,
+        edges_format: wgpu::TextureFormat,
+        blend_format: wgpu::TextureFormat,
+        output_format: wgpu::TextureFormat,
+    ) -> String {
+        // Compute shaders have no implicit derivatives and can't discard, so every sample uses
+        // the base level and edge detection returns zero edges instead.
+        let stage_defines = format!(
+            "{}
+            #define discard return float2(0.0, 0.0)
+            #define SMAA_WORKGROUP_SIZE {}
+            #define SMAA_EDGES_FORMAT {}
+            #define SMAA_BLEND_FORMAT {}
+            #define SMAA_OUTPUT_FORMAT {}",
+            LEVEL_ZERO_SAMPLES,
+            WORKGROUP_SIZE,
+            storage_format_qualifier(edges_format).unwrap(),
+            storage_format_qualifier(blend_format).unwrap(),
+            storage_format_qualifier(output_format).unwrap(),
+        );
+        self.get_stage(stage, &stage_defines)
+    }
+    fn create_module(
+        &self,
+        device: &wgpu::Device,
+        stage: ShaderStage,
+        name: &'static str,
+        source: &str,
+    ) -> wgpu::ShaderModule {
+        if let ShaderStage::FusedNeighborhoodBlendingCS = stage {
+            return device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(name),
+                source: wgpu::ShaderSource::Naga(std::borrow::Cow::Owned(parse_workgroup_shader(
+                    stage, name, source,
+                ))),
+            });
+        }
         device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some(name),
             source: wgpu::ShaderSource::Glsl {
-                shader: self.get_stage(stage).into(),
-                stage: if stage.is_vertex_shader() {
-                    wgpu::naga::ShaderStage::Vertex
-                } else {
-                    wgpu::naga::ShaderStage::Fragment
-                },
+                shader: source.to_owned().into(),
+                stage: stage.naga_stage(),
                 defines: Default::default(),
             },
         })
     }
 }
+
+/// Parse a compute stage that synchronizes through workgroup memory. The GLSL frontend translates
+/// `barrier()` into a barrier that also covers subgroups, which requires a capability most devices
+/// don't have, so it gets narrowed down to a plain workgroup barrier here.
+pub fn parse_workgroup_shader(stage: ShaderStage, name: &str, source: &str) -> naga::Module {
+    fn narrow_barriers(block: &mut naga::Block) {
+        for statement in block.iter_mut() {
+            match statement {
+                naga::Statement::Barrier(barrier) => *barrier = naga::Barrier::WORK_GROUP,
+                naga::Statement::Block(block) => narrow_barriers(block),
+                naga::Statement::If { accept, reject, .. } => {
+                    narrow_barriers(accept);
+                    narrow_barriers(reject);
+                }
+                naga::Statement::Loop {
+                    body, continuing, ..
+                } => {
+                    narrow_barriers(body);
+                    narrow_barriers(continuing);
+                }
+                naga::Statement::Switch { cases, .. } => cases
+                    .iter_mut()
+                    .for_each(|case| narrow_barriers(&mut case.body)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut module = naga::front::glsl::Frontend::default()
+        .parse(
+            &naga::front::glsl::Options::from(stage.naga_stage()),
+            source,
+        )
+        .unwrap_or_else(|errors| panic!("failed to parse {}: {}", name, errors));
+    for (_, function) in module.functions.iter_mut() {
+        narrow_barriers(&mut function.body);
+    }
+    for entry_point in &mut module.entry_points {
+        narrow_barriers(&mut entry_point.function.body);
+    }
+    module
+}
+
+/// Compute shader that appends each tile containing an edge to the instance buffer used to draw
+/// `BlendingWeightTiledVS`, counting them in the indirect draw arguments. This is written in WGSL
+/// because the GLSL frontend doesn't support atomics.
+pub fn tile_classification_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    let source = format!(
+        "struct DrawArgs {{
+            vertex_count: u32,
+            instance_count: atomic<u32>,
+            first_vertex: u32,
+            first_instance: u32,
+        }}
+        @group(0) @binding(1) var<uniform> rt_metrics: vec4<f32>;
+        @group(0) @binding(2) var edges_tex: texture_2d<f32>;
+        @group(0) @binding(3) var<storage, read_write> draw_args: DrawArgs;
+        @group(0) @binding(4) var<storage, read_write> tile_list: array<u32>;
+        var<workgroup> has_edges: atomic<u32>;
+
+        @compute @workgroup_size({0}, {0})
+        fn main(
+            @builtin(global_invocation_id) pixel: vec3<u32>,
+            @builtin(local_invocation_index) local_index: u32,
+            @builtin(workgroup_id) tile: vec3<u32>,
+        ) {{
+            if all(pixel.xy < vec2<u32>(rt_metrics.zw)) &&
+                any(textureLoad(edges_tex, pixel.xy, 0).rg > vec2(0.0)) {{
+                atomicOr(&has_edges, 1u);
+            }}
+            workgroupBarrier();
+            if local_index == 0u && atomicLoad(&has_edges) != 0u {{
+                let index = atomicAdd(&draw_args.instance_count, 1u);
+                tile_list[index] = tile.x | (tile.y << 16u);
+            }}
+        }}",
+        TILE_SIZE,
+    );
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("smaa.shader.tile_classification.comp"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
+/// Compute shader that enables the indirect draws of the blend weight and neighborhood blending
+/// passes and disables the copy to the output if any edges were detected.
+pub fn edge_presence_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    let source = format!(
+        "struct DrawArgs {{
+            vertex_count: atomic<u32>,
+            instance_count: u32,
+            first_vertex: u32,
+            first_instance: u32,
+        }}
+        struct PassArgs {{
+            blend_weight: DrawArgs,
+            neighborhood_blending: DrawArgs,
+            copy: DrawArgs,
+        }}
+        @group(0) @binding(1) var<uniform> rt_metrics: vec4<f32>;
+        @group(0) @binding(2) var edges_tex: texture_2d<f32>;
+        @group(0) @binding(3) var<storage, read_write> pass_args: PassArgs;
+        var<workgroup> has_edges: atomic<u32>;
+
+        @compute @workgroup_size({0}, {0})
+        fn main(
+            @builtin(global_invocation_id) pixel: vec3<u32>,
+            @builtin(local_invocation_index) local_index: u32,
+        ) {{
+            if all(pixel.xy < vec2<u32>(rt_metrics.zw)) &&
+                any(textureLoad(edges_tex, pixel.xy, 0).rg > vec2(0.0)) {{
+                atomicOr(&has_edges, 1u);
+            }}
+            workgroupBarrier();
+            if local_index == 0u && atomicLoad(&has_edges) != 0u {{
+                atomicStore(&pass_args.blend_weight.vertex_count, 3u);
+                atomicStore(&pass_args.neighborhood_blending.vertex_count, 3u);
+                atomicStore(&pass_args.copy.vertex_count, 0u);
+            }}
+        }}",
+        TILE_SIZE,
+    );
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("smaa.shader.edge_presence.comp"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
@@ -0,0 +1,244 @@
+//! Targets that keep the device and queue they were created with, for applications that already
+//! share them through `Arc`s.
+
+use std::sync::Arc;
+
+use crate::{FrameViews, SmaaConfig, SmaaDropPolicy, SmaaFrame, SmaaMode, SmaaTarget};
+
+/// A [`SmaaTarget`] holding on to the device and queue it was created with, so that they don't
+/// have to be passed to [`SmaaSharedTarget::start_frame`] and [`SmaaSharedTarget::resize`].
+///
+/// Methods of the target that aren't wrapped here are available through
+/// [`SmaaSharedTarget::target_mut`], together with [`SmaaSharedTarget::device`] and
+/// [`SmaaSharedTarget::queue`].
+pub struct SmaaSharedTarget {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    target: SmaaTarget,
+}
+
+impl SmaaSharedTarget {
+    /// Create a new target like [`SmaaTarget::new`].
+    pub fn new(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+    ) -> Self {
+        Self::with_config(
+            device,
+            queue,
+            width,
+            height,
+            format,
+            mode,
+            SmaaConfig::default(),
+        )
+    }
+
+    /// Create a new target using non-default settings like [`SmaaTarget::with_config`].
+    pub fn with_config(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mode: SmaaMode,
+        config: SmaaConfig,
+    ) -> Self {
+        let target = SmaaTarget::with_config(&device, &queue, width, height, format, mode, config);
+        Self {
+            device,
+            queue,
+            target,
+        }
+    }
+
+    /// The device that the target was created on.
+    pub fn device(&self) -> &Arc<wgpu::Device> {
+        &self.device
+    }
+
+    /// The queue that frames are submitted to.
+    pub fn queue(&self) -> &Arc<wgpu::Queue> {
+        &self.queue
+    }
+
+    /// The wrapped target.
+    pub fn target(&self) -> &SmaaTarget {
+        &self.target
+    }
+
+    /// The wrapped target, for calling the methods that aren't wrapped here.
+    pub fn target_mut(&mut self) -> &mut SmaaTarget {
+        &mut self.target
+    }
+
+    /// Resize the target like [`SmaaTarget::resize`].
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.target.resize(&self.device, width, height);
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_frame`].
+    pub fn start_frame<'a>(&'a mut self, output_view: &'a wgpu::TextureView) -> SmaaFrame<'a> {
+        self.target
+            .start_frame(&self.device, &self.queue, output_view)
+    }
+
+    /// Start rendering a frame like [`SmaaTarget::start_frame_with_depth`].
+    pub fn start_frame_with_depth<'a>(
+        &'a mut self,
+        output_view: &'a wgpu::TextureView,
+        depth_view: &'a wgpu::TextureView,
+    ) -> SmaaFrame<'a> {
+        self.target
+            .start_frame_with_depth(&self.device, &self.queue, output_view, depth_view)
+    }
+
+    /// Start rendering a frame into `output_view`, moving the target into a frame that borrows
+    /// nothing, so that it can be held across `.await` points or sent to another task.
+    /// Resolving the frame hands the target back.
+    pub fn into_frame(self, output_view: wgpu::TextureView) -> SmaaOwnedFrame {
+        SmaaOwnedFrame {
+            target: Some(self),
+            output_view,
+            depth_view: None,
+            luma_view: None,
+            secondary_view: None,
+            resolve_view: None,
+            extra_outputs: Vec::new(),
+            output_origin: None,
+            outputs_size: None,
+        }
+    }
+
+    /// Start rendering a frame like [`SmaaSharedTarget::into_frame`], also providing the scene's
+    /// depth buffer like [`SmaaTarget::start_frame_with_depth`].
+    pub fn into_frame_with_depth(
+        self,
+        output_view: wgpu::TextureView,
+        depth_view: wgpu::TextureView,
+    ) -> SmaaOwnedFrame {
+        SmaaOwnedFrame {
+            target: Some(self),
+            output_view,
+            depth_view: Some(depth_view),
+            luma_view: None,
+            secondary_view: None,
+            resolve_view: None,
+            extra_outputs: Vec::new(),
+            output_origin: None,
+            outputs_size: None,
+        }
+    }
+}
+
+/// Frame that owns its target and views, created by [`SmaaSharedTarget::into_frame`].
+///
+/// Dropping the frame without resolving it follows [`SmaaConfig::drop_policy`] like a
+/// [`SmaaFrame`] does, and drops the target along with it.
+pub struct SmaaOwnedFrame {
+    // Only taken by `resolve`.
+    target: Option<SmaaSharedTarget>,
+    output_view: wgpu::TextureView,
+    depth_view: Option<wgpu::TextureView>,
+    luma_view: Option<wgpu::TextureView>,
+    secondary_view: Option<wgpu::TextureView>,
+    resolve_view: Option<wgpu::TextureView>,
+    extra_outputs: Vec<wgpu::TextureView>,
+    output_origin: Option<(u32, u32)>,
+    outputs_size: Option<(u32, u32)>,
+}
+
+impl SmaaOwnedFrame {
+    /// Also resolve the frame into `output_view`, like [`SmaaFrame::add_output`].
+    pub fn add_output(&mut self, output_view: wgpu::TextureView) {
+        let target = self.target.as_ref().unwrap();
+        target.target.assert_extra_outputs_supported();
+        self.extra_outputs.push(output_view);
+    }
+
+    /// Write the frame into a rectangle of the outputs, like [`SmaaFrame::set_output_origin`].
+    pub fn set_output_origin(&mut self, x: u32, y: u32, outputs_size: (u32, u32)) {
+        let target = self.target.as_ref().unwrap();
+        target.target.assert_output_origin_supported();
+        self.output_origin = Some((x, y));
+        self.outputs_size = Some(outputs_size);
+    }
+
+    /// Detect edges in `luma_view`, like [`SmaaFrame::set_luma_input`].
+    pub fn set_luma_input(&mut self, luma_view: wgpu::TextureView) {
+        self.luma_view = Some(luma_view);
+    }
+
+    /// Write the secondary output into `secondary_view`, like
+    /// [`SmaaFrame::set_secondary_output`].
+    pub fn set_secondary_output(&mut self, secondary_view: wgpu::TextureView) {
+        self.secondary_view = Some(secondary_view);
+    }
+
+    /// Resolve the multisampled output into `resolve_view`, like
+    /// [`SmaaFrame::set_resolve_target`].
+    pub fn set_resolve_target(&mut self, resolve_view: wgpu::TextureView) {
+        self.resolve_view = Some(resolve_view);
+    }
+
+    /// Resolve the frame into the output view and return the target for the next frame.
+    pub fn resolve(mut self) -> SmaaSharedTarget {
+        let mut target = self.target.take().unwrap();
+        self.encode_resolve(&mut target);
+        target
+    }
+
+    fn encode_resolve(&self, target: &mut SmaaSharedTarget) {
+        target
+            .target
+            .assert_output_fits(self.output_origin, self.outputs_size);
+        let extra_outputs: Vec<_> = self.extra_outputs.iter().collect();
+        target.target.resolve_frame(
+            &target.device,
+            &target.queue,
+            &self.output_view,
+            &extra_outputs,
+            self.output_origin,
+            FrameViews {
+                depth: self.depth_view.as_ref(),
+                luma: self.luma_view.as_ref(),
+                secondary: self.secondary_view.as_ref(),
+                resolve: self.resolve_view.as_ref(),
+            },
+        );
+    }
+}
+
+impl std::ops::Deref for SmaaOwnedFrame {
+    type Target = wgpu::TextureView;
+    fn deref(&self) -> &Self::Target {
+        let target = self.target.as_ref().unwrap();
+        target.target.frame_color_target(&self.output_view)
+    }
+}
+
+impl Drop for SmaaOwnedFrame {
+    fn drop(&mut self) {
+        let Some(mut target) = self.target.take() else {
+            return;
+        };
+        // Like `SmaaFrame`, don't record anything while a panic unwinds.
+        if std::thread::panicking() {
+            return;
+        }
+        match target.target.config.drop_policy {
+            SmaaDropPolicy::Resolve => self.encode_resolve(&mut target),
+            SmaaDropPolicy::DebugAssert => {
+                debug_assert!(false, "SmaaOwnedFrame dropped without calling resolve");
+                self.encode_resolve(&mut target);
+            }
+            SmaaDropPolicy::Skip => {
+                log::warn!("SmaaOwnedFrame dropped without calling resolve, skipping antialiasing")
+            }
+        }
+    }
+}
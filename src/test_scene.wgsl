@@ -0,0 +1,25 @@
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+// Thin stripes at many different angles, so that every kind of edge pattern shows up and the
+// searches run for long distances.
+@fragment
+fn fs_stripes(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let cell = floor(position.xy / 128.0);
+    let angle = (cell.x * 7.0 + cell.y * 13.0) * 0.1;
+    let distance = dot(position.xy, vec2<f32>(cos(angle), sin(angle)));
+    let stripe = step(0.5, fract(distance / 6.0));
+    return vec4<f32>(stripe, stripe * 0.5, 1.0 - stripe, 1.0);
+}
+
+// Concentric rings, with hard edges curving through every direction.
+@fragment
+fn fs_rings(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let center = vec2<f32>(640.0, 360.0);
+    let ring = step(0.5, fract(length(position.xy - center) / 24.0));
+    return vec4<f32>(vec3<f32>(ring), 1.0);
+}
@@ -0,0 +1,517 @@
+//! Helpers for testing that the output of SMAA doesn't change, with the `test-utils` feature.
+//!
+//! These render the synthetic scenes that the benchmarks use on a headless device, antialias
+//! them and compare the result to a reference image stored next to the tests:
+//!
+//! ```no_run
+//! use smaa::test_utils::*;
+//!
+//! let (device, queue) = headless_device().expect("no adapter available");
+//! let output = antialias_scene(&device, &queue, TestScene::Stripes, 256, 256, Default::default());
+//! assert_matches_reference("tests/reference/stripes.bin", &output, 1);
+//! ```
+//!
+//! The output can also be checked against [`reference_antialias`], a straightforward port of
+//! SMAA 1x that runs on the CPU, which validates every backend without storing images:
+//!
+//! ```no_run
+//! use smaa::test_utils::*;
+//!
+//! let (device, queue) = headless_device().expect("no adapter available");
+//! let input = render_scene(&device, &queue, TestScene::Rings, 256, 256);
+//! let output = antialias_scene(&device, &queue, TestScene::Rings, 256, 256, Default::default());
+//! assert_matches_cpu_reference(256, 256, &input, &output, Default::default(), 4, 256 * 256 / 100);
+//! ```
+//!
+//! Applications can also compile the passes of every combination of settings with
+//! [`compile_all_variants`] when they start in QA builds, to find the variants that a driver
+//! fails to compile before they are needed.
+
+use std::path::Path;
+
+use crate::offscreen::block_on;
+use crate::readback::Readback;
+pub use crate::reference::reference_antialias;
+use crate::{
+    SmaaBackend, SmaaBlendSpace, SmaaConfig, SmaaDebugOutput, SmaaDepthProjection,
+    SmaaEdgeDetection, SmaaMode, SmaaPreTonemap, SmaaPreset, SmaaSecondaryOutput, SmaaTarget,
+};
+
+/// Format that the scenes are rendered in and antialiased output is read back in.
+pub const TEST_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Environment variable that makes [`assert_matches_reference`] overwrite the reference images
+/// instead of comparing against them.
+pub const UPDATE_REFERENCES_VAR: &str = "SMAA_UPDATE_REFERENCES";
+
+/// Synthetic scene with hard edges for SMAA to smooth.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TestScene {
+    /// Thin stripes at many different angles, so that every kind of edge pattern shows up.
+    Stripes,
+    /// Concentric rings, with hard edges curving through every direction.
+    Rings,
+}
+impl TestScene {
+    fn entry_point(&self) -> &'static str {
+        match *self {
+            TestScene::Stripes => "fs_stripes",
+            TestScene::Rings => "fs_rings",
+        }
+    }
+}
+
+/// Request a device from the default adapter without creating a surface, with the adapter's
+/// features and limits. Returns `None` if no adapter is available.
+pub fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = block_on(instance.request_adapter(&Default::default()))?;
+    block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("smaa.test_utils.device"),
+            required_features: adapter.features(),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        },
+        None,
+    ))
+    .ok()
+}
+
+/// Draw a scene into a view of a [`TEST_FORMAT`] texture, replacing its contents.
+pub fn draw_scene(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scene: TestScene,
+    view: &wgpu::TextureView,
+) {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("smaa.test_utils.shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("test_scene.wgsl").into()),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("smaa.test_utils.pipeline"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: scene.entry_point(),
+            targets: &[Some(TEST_FORMAT.into())],
+            compilation_options: Default::default(),
+        }),
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        multiview: None,
+        cache: None,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("smaa.test_utils.command_encoder"),
+    });
+    {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("smaa.test_utils.render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.draw(0..3, 0..1);
+    }
+    queue.submit(Some(encoder.finish()));
+}
+
+/// Draw a scene without antialiasing and return its texels, in [`TEST_FORMAT`] with tightly
+/// packed rows. These are the input that [`reference_antialias`] expects.
+pub fn render_scene(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scene: TestScene,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("smaa.test_utils.scene"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEST_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    draw_scene(
+        device,
+        queue,
+        scene,
+        &texture.create_view(&Default::default()),
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("smaa.test_utils.command_encoder"),
+    });
+    let readback = Readback::new(device, &mut encoder, &texture);
+    queue.submit(Some(encoder.finish()));
+    readback.wait(device)
+}
+
+/// Draw a scene into a [`SmaaTarget`] created with `config` and return the antialiased texels,
+/// in [`TEST_FORMAT`] with tightly packed rows.
+pub fn antialias_scene(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scene: TestScene,
+    width: u32,
+    height: u32,
+    config: SmaaConfig,
+) -> Vec<u8> {
+    let usage = match config.backend {
+        SmaaBackend::Render => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        _ => wgpu::TextureUsages::STORAGE_BINDING,
+    };
+    let output = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("smaa.test_utils.output"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEST_FORMAT,
+        usage: usage | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let output_view = output.create_view(&Default::default());
+    let mut target = SmaaTarget::with_config(
+        device,
+        queue,
+        width,
+        height,
+        TEST_FORMAT,
+        SmaaMode::Smaa1X,
+        config,
+    );
+    let frame = target.start_frame(device, queue, &output_view);
+    draw_scene(device, queue, scene, &frame);
+    frame.resolve();
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("smaa.test_utils.command_encoder"),
+    });
+    let readback = Readback::new(device, &mut encoder, &output);
+    queue.submit(Some(encoder.finish()));
+    readback.wait(device)
+}
+
+/// How much two images differ, channel by channel.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImageDifference {
+    /// Number of channels whose values differ at all.
+    pub differing_channels: usize,
+    /// Largest difference between the values of a channel.
+    pub max_difference: u8,
+}
+impl ImageDifference {
+    /// Compare two images with the same layout. Panics if their lengths differ.
+    pub fn between(actual: &[u8], expected: &[u8]) -> Self {
+        assert_eq!(actual.len(), expected.len(), "images have different sizes");
+        actual
+            .iter()
+            .zip(expected)
+            .map(|(a, b)| a.abs_diff(*b))
+            .filter(|&difference| difference > 0)
+            .fold(Self::default(), |total, difference| ImageDifference {
+                differing_channels: total.differing_channels + 1,
+                max_difference: total.max_difference.max(difference),
+            })
+    }
+}
+
+/// Check that `actual` matches the reference image stored at `path`, with no channel differing
+/// by more than `tolerance`. GPUs round slightly differently, so a tolerance of 1 or 2 avoids
+/// failures on other adapters than the one the reference was created with.
+///
+/// The reference is written instead if it doesn't exist yet or the [`UPDATE_REFERENCES_VAR`]
+/// environment variable is set. It holds the raw texels, as returned by [`antialias_scene`].
+pub fn assert_matches_reference(path: impl AsRef<Path>, actual: &[u8], tolerance: u8) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_REFERENCES_VAR).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(path).unwrap();
+    let difference = ImageDifference::between(actual, &expected);
+    assert!(
+        difference.max_difference <= tolerance,
+        "output differs from {}: {difference:?}",
+        path.display()
+    );
+}
+
+/// Check that `actual`, the output of a [`SmaaTarget`] created with `preset` and otherwise the
+/// default configuration for `input`, matches [`reference_antialias`] with no channel differing
+/// by more than `tolerance`, apart from at most `outliers` pixels.
+///
+/// The outliers are needed because the searches of the blend weight pass end on a comparison of
+/// texture coordinates that is decided by rounding. Depending on how the shader compiler
+/// rearranged the arithmetic, some lines end a step earlier or later on the GPU than on the CPU,
+/// which changes the blending of their pixels considerably. With a tolerance of 4, these should
+/// be far less than one percent of the pixels.
+pub fn assert_matches_cpu_reference(
+    width: u32,
+    height: u32,
+    input: &[u8],
+    actual: &[u8],
+    preset: SmaaPreset,
+    tolerance: u8,
+    outliers: usize,
+) {
+    let expected = reference_antialias(width, height, input, preset);
+    assert_eq!(actual.len(), expected.len(), "images have different sizes");
+    let differing = actual
+        .chunks_exact(4)
+        .zip(expected.chunks_exact(4))
+        .filter(|(a, b)| a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > tolerance))
+        .count();
+    assert!(
+        differing <= outliers,
+        "{differing} pixels differ from the CPU reference by more than {tolerance}, \
+         the limit is {outliers}: {:?}",
+        ImageDifference::between(actual, &expected)
+    );
+}
+
+/// Setting that [`compile_all_variants`] changes from its default.
+struct Toggle {
+    name: &'static str,
+    /// Whether only [`SmaaBackend::Render`] supports it.
+    render_only: bool,
+    /// Whether it needs compute shaders.
+    needs_compute: bool,
+    apply: fn(&mut SmaaConfig),
+}
+
+/// Settings that affect the shaders, which [`compile_all_variants`] enables one at a time.
+const TOGGLES: &[Toggle] = &[
+    Toggle {
+        name: "none",
+        render_only: false,
+        needs_compute: false,
+        apply: |_| {},
+    },
+    Toggle {
+        name: "blend_space",
+        render_only: false,
+        needs_compute: false,
+        apply: |config| config.blend_space = SmaaBlendSpace::Linear,
+    },
+    Toggle {
+        name: "pre_tonemap",
+        render_only: false,
+        needs_compute: false,
+        apply: |config| config.pre_tonemap = SmaaPreTonemap::Reinhard,
+    },
+    Toggle {
+        name: "premultiplied_alpha",
+        render_only: false,
+        needs_compute: false,
+        apply: |config| config.premultiplied_alpha = true,
+    },
+    Toggle {
+        name: "deterministic",
+        render_only: false,
+        needs_compute: false,
+        apply: |config| config.deterministic = true,
+    },
+    Toggle {
+        name: "background_depth",
+        render_only: false,
+        needs_compute: false,
+        apply: |config| config.background_depth = Some(1.0),
+    },
+    Toggle {
+        name: "rgba_edges",
+        render_only: true,
+        needs_compute: false,
+        apply: |config| config.rgba_edges = true,
+    },
+    Toggle {
+        name: "luma_output",
+        render_only: true,
+        needs_compute: false,
+        apply: |config| config.luma_output = true,
+    },
+    Toggle {
+        name: "secondary_output",
+        render_only: true,
+        needs_compute: false,
+        apply: |config| config.secondary_output = SmaaSecondaryOutput::EdgeMask,
+    },
+    Toggle {
+        name: "debug_output",
+        render_only: true,
+        needs_compute: false,
+        apply: |config| config.debug_output = SmaaDebugOutput::Difference { amplification: 4.0 },
+    },
+    Toggle {
+        name: "tile_classification",
+        render_only: true,
+        needs_compute: true,
+        apply: |config| config.tile_classification = true,
+    },
+    Toggle {
+        name: "skip_without_edges",
+        render_only: true,
+        needs_compute: true,
+        apply: |config| config.skip_without_edges = true,
+    },
+];
+
+/// Outcome of compiling the passes of one combination of settings with
+/// [`compile_all_variants`].
+#[derive(Clone, Debug)]
+pub struct VariantResult {
+    /// Mode that the target was created with.
+    pub mode: SmaaMode,
+    /// Settings that the target was created with.
+    pub config: SmaaConfig,
+    /// Name of the field of [`SmaaConfig`] that was changed from its default, besides the
+    /// backend, preset and edge detection, or `"none"`.
+    pub toggle: &'static str,
+    /// The validation and internal errors that wgpu reported while compiling the passes, if any.
+    pub result: Result<(), String>,
+}
+
+/// Create a target for every combination of backend, preset, edge detection mode and one of a
+/// set of other settings that affect the shaders, and report whether wgpu accepted the passes
+/// of each. Creating a target compiles every stage of its backend, which both validates the
+/// shaders and has the driver compile its pipelines.
+///
+/// Combinations that the backend doesn't support are left out, as are [`SmaaBackend::Compute`],
+/// [`SmaaBackend::ComputeFused`], [`SmaaMode::Cmaa2`] and the settings needing compute shaders
+/// if the device has no compute limits or `format` can't be written by compute shaders. Errors
+/// are caught with error scopes, so they don't reach the device's uncaptured error handler.
+/// This compiles a few hundred pipelines, which can take a while.
+pub fn compile_all_variants(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+) -> Vec<VariantResult> {
+    let compute = device.limits().max_compute_invocations_per_workgroup > 0;
+    let compute_outputs = compute && crate::shader::storage_format_qualifier(format).is_some();
+    let backends: &[SmaaBackend] = match compute_outputs {
+        true => &[
+            SmaaBackend::Render,
+            SmaaBackend::Compute,
+            SmaaBackend::ComputeFused,
+        ],
+        false => &[SmaaBackend::Render],
+    };
+    let presets = [
+        SmaaPreset::Low,
+        SmaaPreset::Medium,
+        SmaaPreset::High,
+        SmaaPreset::Ultra,
+    ];
+    let edge_detection = [
+        SmaaEdgeDetection::Luma,
+        SmaaEdgeDetection::Alpha,
+        SmaaEdgeDetection::LumaAndAlpha,
+        SmaaEdgeDetection::Depth(SmaaDepthProjection::Perspective {
+            near: 0.1,
+            far: 1000.0,
+            reversed_z: false,
+        }),
+    ];
+
+    let mut variants = Vec::new();
+    for &backend in backends {
+        let render = backend == SmaaBackend::Render;
+        for preset in presets {
+            for edge_detection in edge_detection {
+                if !render && matches!(edge_detection, SmaaEdgeDetection::Depth(_)) {
+                    continue;
+                }
+                for toggle in TOGGLES {
+                    if (toggle.render_only && !render) || (toggle.needs_compute && !compute) {
+                        continue;
+                    }
+                    let mut config = SmaaConfig {
+                        backend,
+                        preset,
+                        edge_detection,
+                        ..Default::default()
+                    };
+                    (toggle.apply)(&mut config);
+                    variants.push((SmaaMode::Smaa1X, config, toggle.name));
+                }
+            }
+        }
+    }
+    if compute_outputs {
+        for preset in presets {
+            let config = SmaaConfig {
+                preset,
+                ..Default::default()
+            };
+            variants.push((SmaaMode::Cmaa2, config, "none"));
+        }
+    }
+
+    variants
+        .into_iter()
+        .map(|(mode, config, toggle)| {
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            device.push_error_scope(wgpu::ErrorFilter::Internal);
+            drop(SmaaTarget::with_config(
+                device,
+                queue,
+                16,
+                16,
+                format,
+                mode,
+                config.clone(),
+            ));
+            let errors: Vec<String> = [
+                block_on(device.pop_error_scope()),
+                block_on(device.pop_error_scope()),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|error| error.to_string())
+            .collect();
+            VariantResult {
+                mode,
+                config,
+                toggle,
+                result: match errors.is_empty() {
+                    true => Ok(()),
+                    false => Err(errors.join("\n")),
+                },
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,219 @@
+//! GPU timing of the SMAA passes, reported through [`SmaaTarget::pass_times`] and
+//! [`SmaaTarget::stats`] and used to pick the preset that stays within
+//! [`SmaaConfig::time_budget`].
+//!
+//! [`SmaaTarget::pass_times`]: crate::SmaaTarget::pass_times
+//! [`SmaaTarget::stats`]: crate::SmaaTarget::stats
+//! [`SmaaConfig::time_budget`]: crate::SmaaConfig::time_budget
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{SmaaPassStats, SmaaPassTimes, SmaaPreset, SmaaStats};
+
+/// Written at the beginning of the edge detection pass.
+pub const EDGE_DETECTION_BEGIN: u32 = 0;
+/// Written at the end of the edge detection pass.
+pub const EDGE_DETECTION_END: u32 = 1;
+/// Written at the end of the blend weight pass, or the beginning of the neighborhood blending
+/// pass if the weights are calculated there.
+pub const BLEND_WEIGHTS_END: u32 = 2;
+/// Written at the end of the neighborhood blending pass.
+pub const NEIGHBORHOOD_BLENDING_END: u32 = 3;
+const QUERY_COUNT: u32 = 4;
+const BUFFER_SIZE: u64 = QUERY_COUNT as u64 * 8;
+
+/// Number of measurements that [`SmaaStats`] are calculated over.
+pub const STATS_WINDOW: usize = 60;
+
+/// Weight of the latest measurement in the moving average.
+const SMOOTHING: f64 = 0.1;
+/// Number of frames to measure after switching presets before switching again.
+const SETTLE_FRAMES: u32 = 30;
+/// Fraction of the budget that the average has to drop below before raising the preset. The gap
+/// to the budget keeps the target from going back and forth between two presets.
+const RAISE_THRESHOLD: f64 = 0.6;
+
+pub(crate) struct PassTimer {
+    /// Nanoseconds per timestamp tick.
+    period: f64,
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    in_flight: bool,
+    mapped: Arc<AtomicBool>,
+    /// Most recent measurements, oldest first.
+    history: VecDeque<SmaaPassTimes>,
+}
+
+impl PassTimer {
+    /// Returns `None` if the device can't time the passes.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        Some(Self {
+            period: queue.get_timestamp_period() as f64,
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("smaa.query_set.timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: QUERY_COUNT,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.timestamps"),
+                size: BUFFER_SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("smaa.buffer.timestamps_readback"),
+                size: BUFFER_SIZE,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            in_flight: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+            history: VecDeque::with_capacity(STATS_WINDOW),
+        })
+    }
+
+    /// Query set that the passes should write their timestamps to, unless the previous
+    /// measurement is still being read back.
+    pub fn query_set(&self) -> Option<&wgpu::QuerySet> {
+        (!self.in_flight).then_some(&self.query_set)
+    }
+
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..QUERY_COUNT, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            BUFFER_SIZE,
+        );
+    }
+
+    /// Start reading back the timestamps once the commands that resolve them were submitted.
+    pub fn map(&mut self) {
+        let mapped = self.mapped.clone();
+        self.in_flight = true;
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+    }
+
+    /// Return the latest measurement if it has just been read back.
+    pub fn update(&mut self) -> Option<SmaaPassTimes> {
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+        let timestamps: Vec<u64> = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            data.chunks_exact(8)
+                .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        self.in_flight = false;
+
+        let duration = |begin: u32, end: u32| {
+            let ticks = timestamps[end as usize].saturating_sub(timestamps[begin as usize]);
+            Duration::from_nanos((ticks as f64 * self.period) as u64)
+        };
+        let times = SmaaPassTimes {
+            edge_detection: duration(EDGE_DETECTION_BEGIN, EDGE_DETECTION_END),
+            blend_weights: duration(EDGE_DETECTION_END, BLEND_WEIGHTS_END),
+            neighborhood_blending: duration(BLEND_WEIGHTS_END, NEIGHBORHOOD_BLENDING_END),
+        };
+        if self.history.len() == STATS_WINDOW {
+            self.history.pop_front();
+        }
+        self.history.push_back(times);
+        Some(times)
+    }
+
+    /// The most recent measurement that was read back.
+    pub fn latest(&self) -> Option<SmaaPassTimes> {
+        self.history.back().copied()
+    }
+
+    /// Statistics over the recent measurements, if there are any.
+    pub fn stats(&self) -> Option<SmaaStats> {
+        if self.history.is_empty() {
+            return None;
+        }
+        let pass = |time: fn(&SmaaPassTimes) -> Duration| {
+            let times = self.history.iter().map(time);
+            SmaaPassStats {
+                average: times.clone().sum::<Duration>() / self.history.len() as u32,
+                min: times.clone().min().unwrap(),
+                max: times.max().unwrap(),
+            }
+        };
+        Some(SmaaStats {
+            edge_detection: pass(|times| times.edge_detection),
+            blend_weights: pass(|times| times.blend_weights),
+            neighborhood_blending: pass(|times| times.neighborhood_blending),
+            total: pass(SmaaPassTimes::total),
+            frames: self.history.len(),
+        })
+    }
+
+    /// Forget the measurements collected so far.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
+
+pub(crate) struct AdaptiveQuality {
+    /// Budget in nanoseconds.
+    budget: f64,
+    average: Option<f64>,
+    measured_frames: u32,
+}
+
+impl AdaptiveQuality {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget: budget.as_nanos() as f64,
+            average: None,
+            measured_frames: 0,
+        }
+    }
+
+    /// Add a measurement to the average, and return the preset to switch to if the average is
+    /// outside of the budget.
+    pub fn update(&mut self, time: Duration, preset: SmaaPreset) -> Option<SmaaPreset> {
+        let time = time.as_nanos() as f64;
+        let average = match self.average {
+            Some(average) => average + SMOOTHING * (time - average),
+            None => time,
+        };
+        self.average = Some(average);
+        self.measured_frames += 1;
+        if self.measured_frames < SETTLE_FRAMES {
+            return None;
+        }
+
+        let next = if average > self.budget {
+            preset.lower()
+        } else if average < self.budget * RAISE_THRESHOLD {
+            preset.higher()
+        } else {
+            None
+        };
+        if next.is_some() {
+            self.average = None;
+            self.measured_frames = 0;
+        }
+        next
+    }
+}
@@ -0,0 +1,316 @@
+//! Final pass that scales frames to the size of the output, when they were rendered at a lower
+//! resolution or the output is larger or smaller than the target, and rotates them for
+//! pre-rotated surfaces. Interlaced frames are written to every other row of the output. It
+//! also writes frames to outputs that can't be rendered to, as storage textures.
+
+use wgpu::util::DeviceExt;
+
+/// Width and height of the workgroups of the compute entry points in `upscale.wgsl`.
+const WORKGROUP_SIZE: u32 = 8;
+
+enum Pipeline {
+    Render(wgpu::RenderPipeline),
+    // The output view changes from frame to frame, so the bind group of the second layout is
+    // created when the pass is recorded.
+    Compute(wgpu::ComputePipeline, wgpu::BindGroupLayout),
+}
+
+/// What the pass last wrote to its uniforms.
+#[derive(Copy, Clone, PartialEq)]
+struct Params {
+    source_size: (u32, u32),
+    output_size: (u32, u32),
+    transform: crate::SmaaOutputTransform,
+    origin: Option<(u32, u32)>,
+    field: Option<crate::SmaaField>,
+}
+
+pub(crate) struct UpscalePass {
+    pipeline: Pipeline,
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    params: Params,
+}
+
+impl UpscalePass {
+    /// Create a pass upscaling part of `source`, which has `source_format`, into outputs of the
+    /// given format with `filter`.
+    ///
+    /// Panics if the output format can neither be rendered to nor written as a storage texture.
+    pub fn new(
+        device: &wgpu::Device,
+        source: &wgpu::TextureView,
+        source_format: wgpu::TextureFormat,
+        output_format: wgpu::TextureFormat,
+        filter: crate::SmaaUpscaleFilter,
+    ) -> Self {
+        let storage_format = (!crate::renderable(device, output_format)).then(|| {
+            crate::storage_format_name(output_format).unwrap_or_else(|| {
+                panic!(
+                    "output format {output_format:?} can neither be rendered to nor written as a storage texture"
+                )
+            })
+        });
+        let visibility = match storage_format {
+            Some(_) => wgpu::ShaderStages::COMPUTE,
+            None => wgpu::ShaderStages::FRAGMENT,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smaa.bind_group_layout.upscale"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        // Loads from sRGB and float formats are already linear, other formats are assumed to
+        // store colors with the sRGB curve.
+        let srgb_source = !source_format.is_srgb() && !crate::is_float_format(source_format);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smaa.shader.upscale"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("upscale.wgsl")
+                    .replace("OUTPUT_FORMAT", storage_format.unwrap_or("rgba8unorm"))
+                    .replace("SRGB_SOURCE", &srgb_source.to_string())
+                    .into(),
+            ),
+        });
+        let (fragment_entry_point, compute_entry_point) = match filter {
+            crate::SmaaUpscaleFilter::Bilinear => ("fs_bilinear", "cs_bilinear"),
+            crate::SmaaUpscaleFilter::Bicubic => ("fs_bicubic", "cs_bicubic"),
+            crate::SmaaUpscaleFilter::Area => ("fs_area", "cs_area"),
+        };
+        let pipeline = match storage_format {
+            None => {
+                let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("smaa.pipeline_layout.upscale"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+                Pipeline::Render(
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("smaa.pipeline.upscale"),
+                        layout: Some(&layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                            compilation_options: Default::default(),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: fragment_entry_point,
+                            targets: &[Some(output_format.into())],
+                            compilation_options: Default::default(),
+                        }),
+                        primitive: Default::default(),
+                        depth_stencil: None,
+                        multisample: Default::default(),
+                        multiview: None,
+                        cache: None,
+                    }),
+                )
+            }
+            Some(_) => {
+                let output_layout =
+                    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some("smaa.bind_group_layout.upscale_output"),
+                        entries: &[wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: output_format,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        }],
+                    });
+                let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("smaa.pipeline_layout.upscale"),
+                    bind_group_layouts: &[&bind_group_layout, &output_layout],
+                    push_constant_ranges: &[],
+                });
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("smaa.pipeline.upscale"),
+                    layout: Some(&layout),
+                    module: &shader,
+                    entry_point: compute_entry_point,
+                    compilation_options: Default::default(),
+                    cache: None,
+                });
+                Pipeline::Compute(pipeline, output_layout)
+            }
+        };
+        let uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("smaa.buffer.upscale_uniforms"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            contents: &[0; 64],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("smaa.bind_group.upscale"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniforms.as_entire_binding(),
+                },
+            ],
+        });
+        Self {
+            pipeline,
+            uniforms,
+            bind_group,
+            params: Params {
+                source_size: (0, 0),
+                output_size: (0, 0),
+                transform: crate::SmaaOutputTransform::Identity,
+                origin: None,
+                field: None,
+            },
+        }
+    }
+
+    /// Upscale the top left `source_size` pixels of the source to outputs of `output_size`,
+    /// rotated by `transform`. With an `origin`, they are written to the rectangle of that size
+    /// starting at it, leaving the rest of the outputs untouched. With a `field`, the source is
+    /// one field of an interlaced frame, which is scaled to half the height of the output and
+    /// written to its rows, leaving the rows of the other field untouched.
+    pub fn set_params(
+        &mut self,
+        queue: &wgpu::Queue,
+        source_size: (u32, u32),
+        output_size: (u32, u32),
+        transform: crate::SmaaOutputTransform,
+        origin: Option<(u32, u32)>,
+        field: Option<crate::SmaaField>,
+    ) {
+        let params = Params {
+            source_size,
+            output_size,
+            transform,
+            origin,
+            field,
+        };
+        if self.params == params {
+            return;
+        }
+        self.params = params;
+        // Rows of the matrix and the offset mapping coordinates in the output, normalized to
+        // [0, 1], back to normalized coordinates in the upright frame.
+        let (matrix, offset) = match transform {
+            crate::SmaaOutputTransform::Rotate90 => ([0.0, 1.0, -1.0, 0.0], [0.0, 1.0]),
+            crate::SmaaOutputTransform::Rotate180 => ([-1.0, 0.0, 0.0, -1.0], [1.0, 1.0]),
+            crate::SmaaOutputTransform::Rotate270 => ([0.0, -1.0, 1.0, 0.0], [1.0, 0.0]),
+            _ => ([1.0, 0.0, 0.0, 1.0], [0.0, 0.0]),
+        };
+        let (stride, first_row) = match field {
+            Some(crate::SmaaField::Top) => (2, 0),
+            Some(crate::SmaaField::Bottom) => (2, 1),
+            None => (1, 0),
+        };
+        let field_height = output_size.1.div_ceil(stride);
+        let sizes = [source_size.0, source_size.1, output_size.0, field_height].map(|s| s as f32);
+        let (x, y) = origin.unwrap_or((0, 0));
+        let data: Vec<u8> = sizes
+            .iter()
+            .chain(&matrix)
+            .chain(&offset)
+            .chain(&[x as f32, y as f32])
+            .chain(&[stride as f32, first_row as f32, output_size.1 as f32, 0.0])
+            .flat_map(|x: &f32| x.to_ne_bytes())
+            .collect();
+        queue.write_buffer(&self.uniforms, 0, &data);
+    }
+
+    /// Whether the pass writes outputs as storage textures, because they can't be rendered to.
+    /// Frames then always have to go through it.
+    pub fn writes_storage(&self) -> bool {
+        matches!(self.pipeline, Pipeline::Compute(..))
+    }
+
+    /// Record the pass upscaling the source into `output_view`.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+    ) {
+        let (width, height) = self.params.output_size;
+        let origin = self.params.origin;
+        let field_height = match self.params.field {
+            Some(_) => height.div_ceil(2),
+            None => height,
+        };
+        match self.pipeline {
+            Pipeline::Render(ref pipeline) => {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("smaa.render_pass.upscale"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: output_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: match (origin, self.params.field) {
+                                (None, None) => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                _ => wgpu::LoadOp::Load,
+                            },
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                if let Some((x, y)) = origin {
+                    rpass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+                }
+                rpass.set_pipeline(pipeline);
+                rpass.set_bind_group(0, &self.bind_group, &[]);
+                rpass.draw(0..3, 0..1);
+            }
+            Pipeline::Compute(ref pipeline, ref output_layout) => {
+                let output_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("smaa.bind_group.upscale_output"),
+                    layout: output_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(output_view),
+                    }],
+                });
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("smaa.compute_pass.upscale"),
+                    timestamp_writes: None,
+                });
+                cpass.set_pipeline(pipeline);
+                cpass.set_bind_group(0, &self.bind_group, &[]);
+                cpass.set_bind_group(1, &output_bind_group, &[]);
+                cpass.dispatch_workgroups(
+                    width.div_ceil(WORKGROUP_SIZE),
+                    field_height.div_ceil(WORKGROUP_SIZE),
+                    1,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Scales the top left part of the source that a frame was antialiased in to the whole output or
+// a rectangle of it, or to every other row of them for interlaced frames, rotating it for
+// surfaces that expect pre-rotated content. Outputs that can't be rendered to
+// are written by the compute entry points instead, as storage textures of OUTPUT_FORMAT.
+// The filters load the pixels themselves, since float formats with 32 bits per channel can't
+// be filtered by a sampler on every device.
+
+struct Params {
+    // Size of the rendered part of the source in xy, and of the output in zw.
+    sizes: vec4<f32>,
+    // Rows of the rotation from normalized output coordinates to normalized source coordinates.
+    rotation: vec4<f32>,
+    // Offset added after the rotation, in xy, and origin of the output rectangle in zw.
+    offset: vec4<f32>,
+    // Distance between the rows that are written in x, the first of them in y, and the height of
+    // the output rectangle in z. Only every other row is written for interlaced frames, and
+    // sizes.w is then the height of one field.
+    field: vec4<f32>,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(1) @binding(0) var output: texture_storage_2d<OUTPUT_FORMAT, write>;
+
+// Pixel of the source nearest to `pixel`, clamped to its rendered part.
+fn load(pixel: vec2<i32>) -> vec4<f32> {
+    return textureLoad(source, clamp(pixel, vec2<i32>(0), vec2<i32>(params.sizes.xy) - 1), 0);
+}
+
+// Position in the source, in pixels relative to pixel centers, that maps to an output position.
+fn source_position(position: vec2<f32>) -> vec2<f32> {
+    let uv = position / params.sizes.zw;
+    let rotated = vec2<f32>(dot(params.rotation.xy, uv), dot(params.rotation.zw, uv));
+    return (rotated + params.offset.xy) * params.sizes.xy - 0.5;
+}
+
+// Position in the field of the output rectangle of a fragment, or none if the fragment belongs
+// to the other field.
+fn field_position(position: vec2<f32>) -> vec2<f32> {
+    let rectangle = position - params.offset.zw;
+    return vec2<f32>(rectangle.x, (rectangle.y - params.field.y - 0.5) / params.field.x + 0.5);
+}
+
+fn in_field(position: vec2<f32>) -> bool {
+    let row = u32(position.y - params.offset.w);
+    return row % u32(params.field.x) == u32(params.field.y);
+}
+
+// Row of the output rectangle that a row of the field is written to, if it is inside of it.
+fn field_row(row: u32) -> u32 {
+    return row * u32(params.field.x) + u32(params.field.y);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn bilinear(position: vec2<f32>) -> vec4<f32> {
+    let pixel = source_position(position);
+    let base = vec2<i32>(floor(pixel));
+    let f = fract(pixel);
+    let top = mix(load(base), load(base + vec2<i32>(1, 0)), f.x);
+    let bottom = mix(load(base + vec2<i32>(0, 1)), load(base + vec2<i32>(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+// Catmull-Rom weights of the four pixels around a sample that is `t` of the way between the
+// middle two.
+fn catmull_rom(t: f32) -> vec4<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    return vec4<f32>(
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    );
+}
+
+fn bicubic(position: vec2<f32>) -> vec4<f32> {
+    let pixel = source_position(position);
+    let base = vec2<i32>(floor(pixel)) - 1;
+    let wx = catmull_rom(fract(pixel.x));
+    let wy = catmull_rom(fract(pixel.y));
+    var color = vec4<f32>(0.0);
+    for (var y = 0; y < 4; y++) {
+        var row = vec4<f32>(0.0);
+        for (var x = 0; x < 4; x++) {
+            row += load(base + vec2<i32>(x, y)) * wx[x];
+        }
+        color += row * wy[y];
+    }
+    // The negative lobes overshoot below zero next to bright edges.
+    return max(color, vec4<f32>(0.0));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let encoded = 1.055 * pow(max(c, vec3<f32>(0.0)), vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(encoded, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+// Average of the source pixels that the output pixel at `position` covers, weighted by the area
+// of each that it covers. Colors that the source stores with the sRGB curve are averaged in
+// linear light.
+fn area(position: vec2<f32>) -> vec4<f32> {
+    // Corners of the output pixel in the source, in pixels relative to pixel corners. The
+    // rotations are by quarter turns, so the footprint stays aligned to the axes.
+    let a = source_position(position - 0.5) + 0.5;
+    let b = source_position(position + 0.5) + 0.5;
+    let low = min(a, b);
+    let high = max(a, b);
+    let first = vec2<i32>(floor(low));
+    let last = vec2<i32>(ceil(high)) - 1;
+    var color = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var y = first.y; y <= last.y; y++) {
+        let wy = min(high.y, f32(y + 1)) - max(low.y, f32(y));
+        for (var x = first.x; x <= last.x; x++) {
+            let wx = min(high.x, f32(x + 1)) - max(low.x, f32(x));
+            var texel = load(vec2<i32>(x, y));
+            if SRGB_SOURCE {
+                texel = vec4<f32>(srgb_to_linear(texel.rgb), texel.a);
+            }
+            color += texel * (wx * wy);
+            total += wx * wy;
+        }
+    }
+    color /= total;
+    if SRGB_SOURCE {
+        color = vec4<f32>(linear_to_srgb(color.rgb), color.a);
+    }
+    return color;
+}
+
+@fragment
+fn fs_bilinear(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    if !in_field(position.xy) {
+        discard;
+    }
+    return bilinear(field_position(position.xy));
+}
+
+@fragment
+fn fs_bicubic(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    if !in_field(position.xy) {
+        discard;
+    }
+    return bicubic(field_position(position.xy));
+}
+
+@fragment
+fn fs_area(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    if !in_field(position.xy) {
+        discard;
+    }
+    return area(field_position(position.xy));
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_bilinear(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = field_row(id.y);
+    if all(id.xy < vec2<u32>(params.sizes.zw)) && row < u32(params.field.z) {
+        let origin = vec2<u32>(params.offset.zw);
+        textureStore(output, vec2<u32>(id.x, row) + origin, bilinear(vec2<f32>(id.xy) + 0.5));
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_bicubic(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = field_row(id.y);
+    if all(id.xy < vec2<u32>(params.sizes.zw)) && row < u32(params.field.z) {
+        let origin = vec2<u32>(params.offset.zw);
+        textureStore(output, vec2<u32>(id.x, row) + origin, bicubic(vec2<f32>(id.xy) + 0.5));
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_area(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = field_row(id.y);
+    if all(id.xy < vec2<u32>(params.sizes.zw)) && row < u32(params.field.z) {
+        let origin = vec2<u32>(params.offset.zw);
+        textureStore(output, vec2<u32>(id.x, row) + origin, area(vec2<f32>(id.xy) + 0.5));
+    }
+}
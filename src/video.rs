@@ -0,0 +1,282 @@
+//! Antialiasing of recorded frames supplied as bytes, optimized for throughput rather than
+//! latency.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{SmaaBackend, SmaaBlendSpace, SmaaConfig, SmaaMode, SmaaTarget};
+
+/// Textures and readback buffer of one frame in flight.
+struct Slot {
+    input: wgpu::Texture,
+    input_view: wgpu::TextureView,
+    output: wgpu::Texture,
+    output_view: wgpu::TextureView,
+    readback_buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+    submission: Option<wgpu::SubmissionIndex>,
+}
+
+/// Antialiases a stream of frames, such as recorded video, that are uploaded from and read back
+/// into memory.
+///
+/// Several frames are kept in flight, so that the upload of a frame, the passes of the previous
+/// one and the readback of the one before that can overlap instead of leaving the GPU idle
+/// while waiting for each other. Frames are pushed with [`VideoSmaa::push_frame`] and come out
+/// of [`VideoSmaa::pop_frame`] in the same order. Once as many frames are in flight as there
+/// are slots, `push_frame` refuses new frames until the oldest one has been popped.
+///
+/// Frames are uploaded in the color format of the target ([`SmaaConfig::color_format`], or
+/// `format` if that isn't set) and read back in `format`, with tightly packed rows.
+pub struct VideoSmaa {
+    target: SmaaTarget,
+    width: u32,
+    height: u32,
+    input_bytes_per_pixel: u32,
+    output_bytes_per_pixel: u32,
+    // Bytes per row of the readback buffers, padded to `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`.
+    padded_bytes_per_row: u32,
+    slots: Vec<Slot>,
+    // Slot holding the oldest frame in flight, and the number of frames in flight.
+    first: usize,
+    len: usize,
+}
+
+impl VideoSmaa {
+    /// Create a pipeline processing frames of the given size, with `frames_in_flight` slots.
+    /// Three slots are enough to overlap the upload, the passes and the readback.
+    ///
+    /// Panics if [`SmaaConfig::background_depth`] is set or either format is compressed.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        config: SmaaConfig,
+        frames_in_flight: usize,
+    ) -> Self {
+        assert!(
+            config.background_depth.is_none(),
+            "VideoSmaa doesn't support SmaaConfig::background_depth"
+        );
+        assert!(frames_in_flight > 0, "VideoSmaa needs at least one slot");
+        let color_format = config.color_format.unwrap_or(format);
+        let bytes_per_pixel = |format: wgpu::TextureFormat| {
+            format
+                .block_copy_size(None)
+                .expect("VideoSmaa doesn't support compressed formats")
+        };
+        let input_bytes_per_pixel = bytes_per_pixel(color_format);
+        let output_bytes_per_pixel = bytes_per_pixel(format);
+        let padded_bytes_per_row =
+            (width * output_bytes_per_pixel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let output_usage = match config.backend {
+            SmaaBackend::Render => wgpu::TextureUsages::RENDER_ATTACHMENT,
+            _ => wgpu::TextureUsages::STORAGE_BINDING,
+        };
+        let create_texture = |label, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let slots = (0..frames_in_flight)
+            .map(|_| {
+                let input = create_texture(
+                    "smaa.video.input",
+                    color_format,
+                    wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                );
+                let output = create_texture(
+                    "smaa.video.output",
+                    format,
+                    output_usage | wgpu::TextureUsages::COPY_SRC,
+                );
+                Slot {
+                    input_view: input.create_view(&Default::default()),
+                    output_view: output.create_view(&Default::default()),
+                    input,
+                    output,
+                    readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("smaa.video.readback"),
+                        size: padded_bytes_per_row as u64 * height as u64,
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    }),
+                    mapped: Arc::new(AtomicBool::new(false)),
+                    submission: None,
+                }
+            })
+            .collect();
+
+        VideoSmaa {
+            target: SmaaTarget::with_config(
+                device,
+                queue,
+                width,
+                height,
+                format,
+                SmaaMode::Smaa1X,
+                config,
+            ),
+            width,
+            height,
+            input_bytes_per_pixel,
+            output_bytes_per_pixel,
+            padded_bytes_per_row,
+            slots,
+            first: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of frames that were pushed but haven't been popped yet.
+    pub fn frames_in_flight(&self) -> usize {
+        self.len
+    }
+
+    /// Whether every slot holds a frame, so that [`VideoSmaa::push_frame`] won't accept another
+    /// one until a frame is popped.
+    pub fn is_full(&self) -> bool {
+        self.len == self.slots.len()
+    }
+
+    /// Upload a frame and submit its passes and readback, without waiting for any of them.
+    /// Returns `false` without doing anything if [`VideoSmaa::is_full`].
+    ///
+    /// Panics if `data` doesn't hold exactly one frame.
+    pub fn push_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> bool {
+        assert_eq!(
+            data.len(),
+            (self.width * self.height * self.input_bytes_per_pixel) as usize,
+            "frame data must have the size of a frame"
+        );
+        if self.is_full() {
+            return false;
+        }
+        let index = (self.first + self.len) % self.slots.len();
+        let slot = &mut self.slots[index];
+        let size = slot.input.size();
+        queue.write_texture(
+            slot.input.as_image_copy(),
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.width * self.input_bytes_per_pixel),
+                rows_per_image: None,
+            },
+            size,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("smaa.command_encoder.video"),
+        });
+        SmaaTarget::encode_batch(
+            self.target.inner.as_ref().unwrap(),
+            device,
+            &mut encoder,
+            &[(&slot.input_view, &slot.output_view)],
+        );
+        encoder.copy_texture_to_buffer(
+            slot.output.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &slot.readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        slot.submission = Some(queue.submit(Some(encoder.finish())));
+
+        let mapped = slot.mapped.clone();
+        slot.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+        self.len += 1;
+        true
+    }
+
+    /// Take the oldest frame in flight once it has been read back. If `wait` is set, this
+    /// blocks until it has, otherwise it returns `None` while the frame is still being
+    /// processed. Also returns `None` if no frames are in flight.
+    pub fn pop_frame(&mut self, device: &wgpu::Device, wait: bool) -> Option<Vec<u8>> {
+        if self.len == 0 {
+            return None;
+        }
+        let slot = &mut self.slots[self.first];
+        let maintain = match slot.submission.take() {
+            Some(submission) if wait => wgpu::Maintain::WaitForSubmissionIndex(submission),
+            submission => {
+                slot.submission = submission;
+                wgpu::Maintain::Poll
+            }
+        };
+        device.poll(maintain);
+        if !slot.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+
+        let row_bytes = (self.width * self.output_bytes_per_pixel) as usize;
+        let frame = {
+            let data = slot.readback_buffer.slice(..).get_mapped_range();
+            data.chunks_exact(self.padded_bytes_per_row as usize)
+                .flat_map(|row| &row[..row_bytes])
+                .copied()
+                .collect()
+        };
+        slot.readback_buffer.unmap();
+        slot.submission = None;
+        self.first = (self.first + 1) % self.slots.len();
+        self.len -= 1;
+        Some(frame)
+    }
+}
+
+/// Antialias an image of sRGB encoded RGBA pixels with 8 bits per channel and tightly packed
+/// rows, returning the antialiased pixels in the same layout.
+///
+/// This uploads the image, runs the passes and waits for the result to be read back. Every call
+/// creates and compiles a new target, so a [`VideoSmaa`] should be kept around instead when
+/// processing many images of the same size.
+pub fn antialias_rgba8(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Vec<u8> {
+    let config = SmaaConfig {
+        blend_space: SmaaBlendSpace::Linear,
+        ..Default::default()
+    };
+    let mut video = VideoSmaa::new(
+        device,
+        queue,
+        width,
+        height,
+        wgpu::TextureFormat::Rgba8Unorm,
+        config,
+        1,
+    );
+    video.push_frame(device, queue, pixels);
+    video.pop_frame(device, true).unwrap()
+}
@@ -0,0 +1,147 @@
+//! Keeping a window's surface and its [`SmaaTarget`] the same size, with the `winit` feature.
+//!
+//! Configuring the surface and resizing the target separately makes it easy to miss one of the
+//! events that change the size of a window, or to resolve a frame into a surface texture of a
+//! different size than the target. [`SmaaSurface`] owns both and resizes them together:
+//!
+//! ```no_run
+//! # fn run(
+//! #     device: &wgpu::Device,
+//! #     queue: &wgpu::Queue,
+//! #     window: &winit::window::Window,
+//! #     surface: &mut smaa::winit_surface::SmaaSurface,
+//! #     event: &winit::event::WindowEvent,
+//! # ) {
+//! surface.handle_event(device, window, event);
+//! if let winit::event::WindowEvent::RedrawRequested = event {
+//!     let output = surface.current_texture(device).unwrap();
+//!     let output_view = output.texture.create_view(&Default::default());
+//!     let frame = surface.target_mut().start_frame(device, queue, &output_view);
+//!     // Render the scene into `*frame`.
+//!     frame.resolve();
+//!     output.present();
+//! }
+//! # }
+//! ```
+
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use crate::{SmaaConfig, SmaaMode, SmaaTarget};
+
+/// A window's surface together with the [`SmaaTarget`] that frames presented to it are
+/// antialiased with.
+pub struct SmaaSurface<'window> {
+    surface: wgpu::Surface<'window>,
+    config: wgpu::SurfaceConfiguration,
+    target: SmaaTarget,
+}
+
+impl<'window> SmaaSurface<'window> {
+    /// Configure `surface` with `config` and create a target of the same size and format. Unless
+    /// `smaa_config` sets one, the target writes alpha for the surface's
+    /// [`wgpu::SurfaceConfiguration::alpha_mode`].
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface: wgpu::Surface<'window>,
+        config: wgpu::SurfaceConfiguration,
+        mode: SmaaMode,
+        smaa_config: SmaaConfig,
+    ) -> Self {
+        surface.configure(device, &config);
+        let smaa_config = match smaa_config.composite_alpha_mode {
+            wgpu::CompositeAlphaMode::Auto => SmaaConfig {
+                composite_alpha_mode: config.alpha_mode,
+                ..smaa_config
+            },
+            _ => smaa_config,
+        };
+        let target = SmaaTarget::with_config(
+            device,
+            queue,
+            config.width,
+            config.height,
+            config.format,
+            mode,
+            smaa_config,
+        );
+        Self {
+            surface,
+            config,
+            target,
+        }
+    }
+
+    /// The surface that frames are presented to.
+    pub fn surface(&self) -> &wgpu::Surface<'window> {
+        &self.surface
+    }
+
+    /// The configuration that the surface was last configured with.
+    pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+        &self.config
+    }
+
+    /// The target that frames are antialiased with.
+    pub fn target(&self) -> &SmaaTarget {
+        &self.target
+    }
+
+    /// The target that frames are antialiased with, for starting frames. It must not be resized
+    /// directly, use [`SmaaSurface::resize`] instead.
+    pub fn target_mut(&mut self) -> &mut SmaaTarget {
+        &mut self.target
+    }
+
+    /// Resize the surface and the target to the size of the window if `event` changed it.
+    /// Returns whether they were resized.
+    ///
+    /// This handles [`WindowEvent::Resized`] and [`WindowEvent::ScaleFactorChanged`], and
+    /// ignores every other event.
+    pub fn handle_event(
+        &mut self,
+        device: &wgpu::Device,
+        window: &Window,
+        event: &WindowEvent,
+    ) -> bool {
+        let size = match event {
+            WindowEvent::Resized(size) => *size,
+            WindowEvent::ScaleFactorChanged { .. } => window.inner_size(),
+            _ => return false,
+        };
+        self.resize(device, size.width, size.height)
+    }
+
+    /// Reconfigure the surface and resize the target, unless they already have this size.
+    /// Returns whether they were resized.
+    ///
+    /// A width or height of zero, which minimized windows report on some platforms, is ignored
+    /// since surfaces can't be configured with it. Frames keep the previous size until the
+    /// window is restored.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> bool {
+        if width == 0 || height == 0 || (width, height) == (self.config.width, self.config.height) {
+            return false;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(device, &self.config);
+        self.target.resize(device, width, height);
+        true
+    }
+
+    /// Get the next texture to present, reconfiguring the surface and trying again once if it
+    /// was lost or outdated.
+    pub fn current_texture(
+        &self,
+        device: &wgpu::Device,
+    ) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
+        match self.surface.get_current_texture() {
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(device, &self.config);
+                self.surface.get_current_texture()
+            }
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Checks the output of every backend against the CPU port of SMAA 1x, so that regressions in
+//! translating the shaders show up on whichever adapter runs the tests.
+//!
+//! Run with `cargo test --features test-utils`. The tests pass without checking anything when
+//! no adapter is available.
+
+use smaa::test_utils::*;
+use smaa::{SmaaBackend, SmaaConfig, SmaaPreset};
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 128;
+
+fn check_backend(backend: SmaaBackend) {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    if backend != SmaaBackend::Render && device.limits().max_compute_invocations_per_workgroup == 0
+    {
+        eprintln!("adapter doesn't support compute shaders, skipping");
+        return;
+    }
+    for scene in [TestScene::Stripes, TestScene::Rings] {
+        for preset in [SmaaPreset::Low, SmaaPreset::High] {
+            let config = SmaaConfig {
+                backend,
+                preset,
+                ..Default::default()
+            };
+            let input = render_scene(&device, &queue, scene, WIDTH, HEIGHT);
+            let output = antialias_scene(&device, &queue, scene, WIDTH, HEIGHT, config);
+            assert_matches_cpu_reference(
+                WIDTH,
+                HEIGHT,
+                &input,
+                &output,
+                preset,
+                4,
+                (WIDTH * HEIGHT / 100) as usize,
+            );
+        }
+    }
+}
+
+#[test]
+fn render_matches_cpu_reference() {
+    check_backend(SmaaBackend::Render);
+}
+
+#[test]
+fn compute_matches_cpu_reference() {
+    check_backend(SmaaBackend::Compute);
+}
+
+#[test]
+fn compute_fused_matches_cpu_reference() {
+    check_backend(SmaaBackend::ComputeFused);
+}
@@ -0,0 +1,202 @@
+//! Checks that [`SmaaTarget::process_layers`] and [`SmaaTarget::process_cubemap`] antialias
+//! each layer like a separate image passed to [`SmaaTarget::process_batch`].
+//!
+//! Run with `cargo test --features test-utils`. The tests pass without checking anything when
+//! no adapter is available, and the ones processing layers also skip wgpu's GL backend, which
+//! can't sample single layers of a texture.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use futures::executor::block_on;
+use smaa::test_utils::*;
+use smaa::{SmaaMode, SmaaTarget};
+
+// Rows of this width need no padding to be copied into a buffer.
+const SIZE: u32 = 64;
+
+/// Device of the default adapter, unless it is on the GL backend.
+fn layered_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = block_on(instance.request_adapter(&Default::default()))?;
+    if adapter.get_info().backend == wgpu::Backend::Gl {
+        return None;
+    }
+    headless_device()
+}
+
+fn texture(device: &wgpu::Device, size: u32, layers: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEST_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn layer_view(texture: &wgpu::Texture, layer: u32) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2),
+        base_array_layer: layer,
+        array_layer_count: Some(1),
+        ..Default::default()
+    })
+}
+
+fn read(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    layer: u32,
+) -> Vec<u8> {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (SIZE * SIZE * 4) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            origin: wgpu::Origin3d {
+                z: layer,
+                ..Default::default()
+            },
+            ..texture.as_image_copy()
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SIZE * 4),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let bytes = buffer.slice(..).get_mapped_range().to_vec();
+    bytes
+}
+
+/// Draw alternating scenes into the layers of `texture`.
+fn draw_layers(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+    for layer in 0..texture.depth_or_array_layers() {
+        let scene = match layer % 2 {
+            0 => TestScene::Stripes,
+            _ => TestScene::Rings,
+        };
+        draw_scene(device, queue, scene, &layer_view(texture, layer));
+    }
+}
+
+/// Copy every layer of `input` into a texture of its own and antialias it with `process_batch`.
+fn batched(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    target: &mut SmaaTarget,
+    input: &wgpu::Texture,
+) -> Vec<Vec<u8>> {
+    let image = texture(device, SIZE, 1);
+    let image_view = image.create_view(&Default::default());
+    let output = texture(device, SIZE, 1);
+    let output_view = output.create_view(&Default::default());
+    (0..input.depth_or_array_layers())
+        .map(|layer| {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    origin: wgpu::Origin3d {
+                        z: layer,
+                        ..Default::default()
+                    },
+                    ..input.as_image_copy()
+                },
+                image.as_image_copy(),
+                image.size(),
+            );
+            queue.submit(Some(encoder.finish()));
+            target.process_batch(device, queue, &[(&image_view, &output_view)]);
+            read(device, queue, &output, 0)
+        })
+        .collect()
+}
+
+#[test]
+fn layers_match_batched_images() {
+    let Some((device, queue)) = layered_device() else {
+        eprintln!("no adapter without GL available, skipping");
+        return;
+    };
+    let mut target = SmaaTarget::new(&device, &queue, SIZE, SIZE, TEST_FORMAT, SmaaMode::Smaa1X);
+    let input = texture(&device, SIZE, 2);
+    let output = texture(&device, SIZE, 2);
+    draw_layers(&device, &queue, &input);
+    let expected = batched(&device, &queue, &mut target, &input);
+
+    // The second call reuses the views and bind groups of the first.
+    for _ in 0..2 {
+        target.process_layers(&device, &queue, &input, &output);
+        for (layer, expected) in (0..).zip(&expected) {
+            let difference =
+                ImageDifference::between(&read(&device, &queue, &output, layer), expected);
+            assert_eq!(difference, ImageDifference::default(), "layer {layer}");
+        }
+    }
+}
+
+#[test]
+fn cubemap_is_processed_in_place() {
+    let Some((device, queue)) = layered_device() else {
+        eprintln!("no adapter without GL available, skipping");
+        return;
+    };
+    let mut target = SmaaTarget::new(&device, &queue, SIZE, SIZE, TEST_FORMAT, SmaaMode::Smaa1X);
+    let cubemap = texture(&device, SIZE, 6);
+    draw_layers(&device, &queue, &cubemap);
+    let expected = batched(&device, &queue, &mut target, &cubemap);
+
+    target.process_cubemap(&device, &queue, &cubemap, None);
+    for (face, expected) in (0..).zip(&expected) {
+        let difference = ImageDifference::between(&read(&device, &queue, &cubemap, face), expected);
+        assert_eq!(difference, ImageDifference::default(), "face {face}");
+    }
+}
+
+#[test]
+fn layers_of_another_size_are_rejected() {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    let mut target = SmaaTarget::new(&device, &queue, SIZE, SIZE, TEST_FORMAT, SmaaMode::Smaa1X);
+    let input = texture(&device, SIZE / 2, 2);
+    let output = texture(&device, SIZE / 2, 2);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        target.process_layers(&device, &queue, &input, &output)
+    }));
+    let message = result.expect_err("smaller layers were processed");
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(
+        message.contains("input layers must have the size of the target"),
+        "{message}"
+    );
+}
@@ -0,0 +1,58 @@
+//! Checks that frames written at an origin with [`SmaaFrame::set_output_origin`] land in their
+//! rectangle of a larger output, like a sheet of thumbnails.
+//!
+//! Run with `cargo test --features test-utils`. The test passes without checking anything when
+//! no adapter is available.
+
+use smaa::test_utils::*;
+use smaa::{SmaaMode, SmaaTarget};
+
+const SIZE: u32 = 64;
+
+#[test]
+fn frames_are_composited_into_a_sheet() {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    let sheet = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: 2 * SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEST_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = sheet.create_view(&Default::default());
+    let mut target = SmaaTarget::new(&device, &queue, SIZE, SIZE, TEST_FORMAT, SmaaMode::Smaa1X);
+
+    let scenes = [TestScene::Stripes, TestScene::Rings];
+    let mut frame = target.start_frame(&device, &queue, &view);
+    frame.set_output_origin(0, 0, (2 * SIZE, SIZE));
+    draw_scene(&device, &queue, scenes[0], &frame);
+    frame.resolve();
+    let mut frame = target.start_frame(&device, &queue, &view);
+    frame.set_output_origin(SIZE, 0, (2 * SIZE, SIZE));
+    draw_scene(&device, &queue, scenes[1], &frame);
+    let readback = frame.read_output_async(&sheet);
+    device.poll(wgpu::Maintain::Wait);
+    let texels = futures::executor::block_on(readback).unwrap();
+
+    let row_bytes = (SIZE * 4) as usize;
+    for (i, scene) in scenes.into_iter().enumerate() {
+        let expected = antialias_scene(&device, &queue, scene, SIZE, SIZE, Default::default());
+        let actual: Vec<u8> = texels
+            .chunks_exact(2 * row_bytes)
+            .flat_map(|row| &row[i * row_bytes..][..row_bytes])
+            .copied()
+            .collect();
+        let difference = ImageDifference::between(&actual, &expected);
+        assert!(difference.max_difference <= 1, "{scene:?}: {difference:?}");
+    }
+}
@@ -0,0 +1,106 @@
+//! Checks that [`PostProcess::encode`] processes images at the full size of the target, whatever
+//! the render scale of the frames resolved before it.
+//!
+//! Run with `cargo test --features test-utils`. The test passes without checking anything when
+//! no adapter is available.
+
+use smaa::test_utils::*;
+use smaa::{PostProcess, SmaaConfig, SmaaMode, SmaaTarget};
+
+// Rows of this width need no padding to be copied into a buffer.
+const SIZE: u32 = 64;
+
+fn texture(device: &wgpu::Device, usage: wgpu::TextureUsages) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEST_FORMAT,
+        usage: usage | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    })
+}
+
+fn read(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Vec<u8> {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (SIZE * SIZE * 4) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SIZE * 4),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let bytes = buffer.slice(..).get_mapped_range().to_vec();
+    bytes
+}
+
+#[test]
+fn encode_ignores_the_render_scale_of_frames() {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    let config = SmaaConfig {
+        dynamic_resolution: true,
+        ..Default::default()
+    };
+    let mut target = SmaaTarget::with_config(
+        &device,
+        &queue,
+        SIZE,
+        SIZE,
+        TEST_FORMAT,
+        SmaaMode::Smaa1X,
+        config,
+    );
+    let input = texture(&device, wgpu::TextureUsages::TEXTURE_BINDING);
+    let input_view = input.create_view(&Default::default());
+    draw_scene(&device, &queue, TestScene::Stripes, &input_view);
+    let outputs = [(); 2].map(|_| texture(&device, wgpu::TextureUsages::COPY_SRC));
+    let output_views = outputs
+        .each_ref()
+        .map(|output| output.create_view(&Default::default()));
+
+    target.set_render_scale(0.5);
+    let frame = target.start_frame(&device, &queue, &output_views[0]);
+    draw_scene(&device, &queue, TestScene::Rings, &frame);
+    frame.resolve();
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    PostProcess::encode(
+        &mut target,
+        &device,
+        &mut encoder,
+        &input_view,
+        &output_views[0],
+    );
+    queue.submit(Some(encoder.finish()));
+    target.process_batch(&device, &queue, &[(&input_view, &output_views[1])]);
+
+    let encoded = read(&device, &queue, &outputs[0]);
+    let processed = read(&device, &queue, &outputs[1]);
+    let difference = ImageDifference::between(&encoded, &processed);
+    assert_eq!(difference, ImageDifference::default());
+}
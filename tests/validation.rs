@@ -0,0 +1,112 @@
+//! Checks that the strict validation of [`SmaaTarget::try_start_frame`] and
+//! [`SmaaFrame::try_resolve`] reports mistakes as errors before wgpu would panic on them.
+//!
+//! Run with `cargo test --features test-utils`. The tests pass without checking anything when
+//! no adapter is available.
+
+use smaa::test_utils::*;
+use smaa::{SmaaError, SmaaMode, SmaaTarget};
+
+fn output(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+#[test]
+fn srgb_counterpart_is_rejected() {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    let mut target = SmaaTarget::new(&device, &queue, 64, 64, TEST_FORMAT, SmaaMode::Smaa1X);
+    let output = output(&device, 64, 64, wgpu::TextureFormat::Rgba8UnormSrgb);
+    let view = output.create_view(&Default::default());
+    assert_eq!(
+        target.validate_output(&output),
+        Err(SmaaError::OutputFormat {
+            expected: TEST_FORMAT,
+            found: wgpu::TextureFormat::Rgba8UnormSrgb,
+        })
+    );
+    assert!(target
+        .try_start_frame(&device, &queue, &output, &view, None)
+        .is_err());
+}
+
+#[test]
+fn output_size_is_checked_at_the_origin() {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    let mut target = SmaaTarget::new(&device, &queue, 64, 64, TEST_FORMAT, SmaaMode::Smaa1X);
+
+    let small = output(&device, 32, 64, TEST_FORMAT);
+    let view = small.create_view(&Default::default());
+    let frame = target
+        .try_start_frame(&device, &queue, &small, &view, None)
+        .unwrap();
+    assert_eq!(
+        frame.try_resolve(),
+        Err(SmaaError::OutputSize {
+            expected: (64, 64),
+            found: (32, 64),
+        })
+    );
+
+    let sheet = output(&device, 128, 128, TEST_FORMAT);
+    let view = sheet.create_view(&Default::default());
+    let mut frame = target
+        .try_start_frame(&device, &queue, &sheet, &view, None)
+        .unwrap();
+    frame.set_output_origin(64, 64, (128, 128));
+    assert_eq!(frame.try_resolve(), Ok(()));
+}
+
+#[test]
+fn origin_outside_the_output_is_rejected() {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no adapter available, skipping");
+        return;
+    };
+    let mut target = SmaaTarget::new(&device, &queue, 64, 64, TEST_FORMAT, SmaaMode::Smaa1X);
+    let output = output(&device, 64, 64, TEST_FORMAT);
+    let view = output.create_view(&Default::default());
+
+    let mut frame = target
+        .try_start_frame(&device, &queue, &output, &view, None)
+        .unwrap();
+    frame.set_output_origin(32, 32, (64, 64));
+    assert_eq!(
+        frame.try_resolve(),
+        Err(SmaaError::OutputOrigin {
+            origin: (32, 32),
+            size: (64, 64),
+            found: (64, 64),
+        })
+    );
+
+    let resolved = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut frame = target.start_frame(&device, &queue, &view);
+        frame.set_output_origin(32, 32, (64, 64));
+        frame.resolve();
+    }));
+    assert!(resolved.is_err());
+}
@@ -511,6 +511,18 @@
 #define SMAA_DECODE_VELOCITY(sample) sample.rg
 #endif
 
+#ifndef SMAA_SAMPLE_COLOR
+#define SMAA_SAMPLE_COLOR(tex, coord) SMAASampleLevelZero(tex, coord)
+#endif
+
+#ifndef SMAA_EDGE_DETECTION_COLOR
+#define SMAA_EDGE_DETECTION_COLOR(sample) sample
+#endif
+
+#ifndef SMAA_SAMPLE_BLEND_WEIGHTS
+#define SMAA_SAMPLE_BLEND_WEIGHTS(tex, coord) SMAASample(tex, coord)
+#endif
+
 //-----------------------------------------------------------------------------
 // Non-Configurable Defines
 
@@ -562,9 +574,15 @@ SamplerState PointSampler { Filter = MIN_MAG_MIP_POINT; AddressU = Clamp; Addres
 #define SMAASampleLevelZero(tex, coord) textureLod(sampler2D(tex, linearSampler), coord, 0.0)
 #define SMAASampleLevelZeroPoint(tex, coord) textureLod(sampler2D(tex, linearSampler), coord, 0.0)
 #define SMAASampleLevelZeroOffset(tex, coord, offset) textureLodOffset(sampler2D(tex, linearSampler), coord, 0.0, offset)
+#ifndef SMAASample
 #define SMAASample(tex, coord) texture(sampler2D(tex, linearSampler), coord)
+#endif
+#ifndef SMAASamplePoint
 #define SMAASamplePoint(tex, coord) textureLod(sampler2D(tex, linearSampler), coord, 0)
+#endif
+#ifndef SMAASampleOffset
 #define SMAASampleOffset(tex, coord, offset) texture(sampler2D(tex, linearSampler), coord, offset)
+#endif
 #define SMAA_FLATTEN
 #define SMAA_BRANCH
 #define lerp(a, b, t) mix(a, b, t)
@@ -704,10 +722,10 @@ float2 SMAALumaEdgeDetectionPS(float2 texcoord,
 
     // Calculate lumas:
     float3 weights = float3(0.2126, 0.7152, 0.0722);
-    float L = dot(SMAASamplePoint(colorTex, texcoord).rgb, weights);
+    float L = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, texcoord)).rgb, weights);
 
-    float Lleft = dot(SMAASamplePoint(colorTex, offset[0].xy).rgb, weights);
-    float Ltop  = dot(SMAASamplePoint(colorTex, offset[0].zw).rgb, weights);
+    float Lleft = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[0].xy)).rgb, weights);
+    float Ltop  = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[0].zw)).rgb, weights);
 
     // We do the usual threshold:
     float4 delta;
@@ -719,16 +737,16 @@ float2 SMAALumaEdgeDetectionPS(float2 texcoord,
         discard;
 
     // Calculate right and bottom deltas:
-    float Lright = dot(SMAASamplePoint(colorTex, offset[1].xy).rgb, weights);
-    float Lbottom  = dot(SMAASamplePoint(colorTex, offset[1].zw).rgb, weights);
+    float Lright = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[1].xy)).rgb, weights);
+    float Lbottom  = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[1].zw)).rgb, weights);
     delta.zw = abs(L - float2(Lright, Lbottom));
 
     // Calculate the maximum delta in the direct neighborhood:
     float2 maxDelta = max(delta.xy, delta.zw);
 
     // Calculate left-left and top-top deltas:
-    float Lleftleft = dot(SMAASamplePoint(colorTex, offset[2].xy).rgb, weights);
-    float Ltoptop = dot(SMAASamplePoint(colorTex, offset[2].zw).rgb, weights);
+    float Lleftleft = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[2].xy)).rgb, weights);
+    float Ltoptop = dot(SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[2].zw)).rgb, weights);
     delta.zw = abs(float2(Lleft, Ltop) - float2(Lleftleft, Ltoptop));
 
     // Calculate the final maximum delta:
@@ -763,13 +781,13 @@ float2 SMAAColorEdgeDetectionPS(float2 texcoord,
 
     // Calculate color deltas:
     float4 delta;
-    float3 C = SMAASamplePoint(colorTex, texcoord).rgb;
+    float3 C = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, texcoord)).rgb;
 
-    float3 Cleft = SMAASamplePoint(colorTex, offset[0].xy).rgb;
+    float3 Cleft = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[0].xy)).rgb;
     float3 t = abs(C - Cleft);
     delta.x = max(max(t.r, t.g), t.b);
 
-    float3 Ctop  = SMAASamplePoint(colorTex, offset[0].zw).rgb;
+    float3 Ctop  = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[0].zw)).rgb;
     t = abs(C - Ctop);
     delta.y = max(max(t.r, t.g), t.b);
 
@@ -781,11 +799,11 @@ float2 SMAAColorEdgeDetectionPS(float2 texcoord,
         discard;
 
     // Calculate right and bottom deltas:
-    float3 Cright = SMAASamplePoint(colorTex, offset[1].xy).rgb;
+    float3 Cright = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[1].xy)).rgb;
     t = abs(C - Cright);
     delta.z = max(max(t.r, t.g), t.b);
 
-    float3 Cbottom  = SMAASamplePoint(colorTex, offset[1].zw).rgb;
+    float3 Cbottom  = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[1].zw)).rgb;
     t = abs(C - Cbottom);
     delta.w = max(max(t.r, t.g), t.b);
 
@@ -793,11 +811,11 @@ float2 SMAAColorEdgeDetectionPS(float2 texcoord,
     float2 maxDelta = max(delta.xy, delta.zw);
 
     // Calculate left-left and top-top deltas:
-    float3 Cleftleft  = SMAASamplePoint(colorTex, offset[2].xy).rgb;
+    float3 Cleftleft  = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[2].xy)).rgb;
     t = abs(C - Cleftleft);
     delta.z = max(max(t.r, t.g), t.b);
 
-    float3 Ctoptop = SMAASamplePoint(colorTex, offset[2].zw).rgb;
+    float3 Ctoptop = SMAA_EDGE_DETECTION_COLOR(SMAASamplePoint(colorTex, offset[2].zw)).rgb;
     t = abs(C - Ctoptop);
     delta.w = max(max(t.r, t.g), t.b);
 
@@ -1265,14 +1283,14 @@ float4 SMAANeighborhoodBlendingPS(float2 texcoord,
                                   ) {
     // Fetch the blending weights for current pixel:
     float4 a;
-    a.x = SMAASample(blendTex, offset.xy).a; // Right
-    a.y = SMAASample(blendTex, offset.zw).g; // Top
-    a.wz = SMAASample(blendTex, texcoord).xz; // Bottom / Left
+    a.x = SMAA_SAMPLE_BLEND_WEIGHTS(blendTex, offset.xy).a; // Right
+    a.y = SMAA_SAMPLE_BLEND_WEIGHTS(blendTex, offset.zw).g; // Top
+    a.wz = SMAA_SAMPLE_BLEND_WEIGHTS(blendTex, texcoord).xz; // Bottom / Left
 
     // Is there any blending weight with a value greater than 0.0?
     SMAA_BRANCH
     if (dot(a, float4(1.0, 1.0, 1.0, 1.0)) < 1e-5) {
-        float4 color = SMAASampleLevelZero(colorTex, texcoord);
+        float4 color = SMAA_SAMPLE_COLOR(colorTex, texcoord);
 
         #if SMAA_REPROJECTION
         float2 velocity = SMAA_DECODE_VELOCITY(SMAASampleLevelZero(velocityTex, texcoord));
@@ -1297,8 +1315,8 @@ float4 SMAANeighborhoodBlendingPS(float2 texcoord,
 
         // We exploit bilinear filtering to mix current pixel with the chosen
         // neighbor:
-        float4 color = blendingWeight.x * SMAASampleLevelZero(colorTex, blendingCoord.xy);
-        color += blendingWeight.y * SMAASampleLevelZero(colorTex, blendingCoord.zw);
+        float4 color = blendingWeight.x * SMAA_SAMPLE_COLOR(colorTex, blendingCoord.xy);
+        color += blendingWeight.y * SMAA_SAMPLE_COLOR(colorTex, blendingCoord.zw);
 
         #if SMAA_REPROJECTION
         // Antialias velocity for proper reprojection in a later stage: